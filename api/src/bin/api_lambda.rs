@@ -0,0 +1,31 @@
+//! Lambda function serving the API through a function URL. The storage is
+//! configured the same way as for the bot, see [`AnyOutages::connect_from_env`].
+use anyhow::Result;
+use api::lambda::{request_from_event, response_to_event};
+use dotenvy::dotenv;
+use electricity::outages::AnyOutages;
+use lambda_runtime::{service_fn, LambdaEvent};
+use serde_json::Value;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let _ = dotenv();
+
+    let outages = AnyOutages::connect_from_env().await?;
+
+    let outages = &outages;
+    let func = service_fn(move |event: LambdaEvent<Value>| async move { api_handler(outages, event).await });
+    if let Err(e) = lambda_runtime::run(func).await {
+        tracing::error!("Error: {}", e);
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
+
+async fn api_handler(outages: &AnyOutages, event: LambdaEvent<Value>) -> Result<Value> {
+    let request = request_from_event(&event.payload);
+    let response = api::handle(outages, &request).await;
+
+    Ok(response_to_event(response))
+}
@@ -0,0 +1,71 @@
+//! Serves the API over HTTP locally, on `API_ADDR` (`127.0.0.1:3000` by
+//! default). The storage is configured the same way as for the bot, see
+//! [`AnyOutages::connect_from_env`].
+use anyhow::Result;
+use dotenvy::dotenv;
+use electricity::outages::AnyOutages;
+use http_body_util::Full;
+use hyper::body::{Bytes, Incoming};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper_util::rt::TokioIo;
+use std::convert::Infallible;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let _ = dotenv();
+
+    let outages = Arc::new(AnyOutages::connect_from_env().await?);
+    let addr = dotenvy::var("API_ADDR").unwrap_or("127.0.0.1:3000".to_owned());
+    let listener = TcpListener::bind(&addr).await?;
+    println!("listening on http://{addr}");
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let outages = outages.clone();
+
+        tokio::spawn(async move {
+            let service = service_fn(move |request| {
+                let outages = outages.clone();
+                async move { Ok::<_, Infallible>(serve(&outages, request).await) }
+            });
+
+            if let Err(err) = http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service)
+                .await
+            {
+                eprintln!("failed to serve a connection: {err}");
+            }
+        });
+    }
+}
+
+async fn serve(outages: &AnyOutages, request: hyper::Request<Incoming>) -> hyper::Response<Full<Bytes>> {
+    let path_and_query = request
+        .uri()
+        .path_and_query()
+        .map(|it| it.as_str())
+        .unwrap_or("/");
+    let api_request = api::Request {
+        method: request.method().to_string(),
+        if_none_match: request
+            .headers()
+            .get(hyper::header::IF_NONE_MATCH)
+            .and_then(|it| it.to_str().ok())
+            .map(ToOwned::to_owned),
+        ..api::Request::get(path_and_query)
+    };
+
+    let response = api::handle(outages, &api_request).await;
+
+    let mut builder = hyper::Response::builder().status(response.status);
+    for (name, value) in response.headers {
+        builder = builder.header(name, value);
+    }
+
+    builder
+        .body(Full::new(Bytes::from(response.body)))
+        .expect("the response is valid")
+}
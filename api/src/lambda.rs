@@ -0,0 +1,70 @@
+//! Conversion from and to the payload of Lambda function URLs, which is the
+//! same as the one of API Gateway HTTP APIs (version 2.0).
+use crate::{Request, Response};
+use serde_json::{json, Map, Value};
+
+pub fn request_from_event(event: &Value) -> Request {
+    let string = |value: &Value| value.as_str().map(ToOwned::to_owned);
+
+    Request {
+        method: string(&event["requestContext"]["http"]["method"]).unwrap_or_else(|| "GET".to_string()),
+        path: string(&event["rawPath"]).unwrap_or_else(|| "/".to_string()),
+        query: event["queryStringParameters"]
+            .as_object()
+            .into_iter()
+            .flatten()
+            .filter_map(|(name, value)| Some((name.to_owned(), string(value)?)))
+            .collect(),
+        // Header names are lowercase in the payload.
+        if_none_match: string(&event["headers"]["if-none-match"]),
+    }
+}
+
+pub fn response_to_event(response: Response) -> Value {
+    let headers = response
+        .headers
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), Value::String(value)))
+        .collect::<Map<_, _>>();
+
+    json!({
+        "statusCode": response.status,
+        "headers": headers,
+        "body": response.body,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_from_event() {
+        let event = json!({
+            "rawPath": "/outages",
+            "rawQueryString": "date=2023-11-20&street=%D0%A2",
+            "queryStringParameters": { "date": "2023-11-20", "street": "Т" },
+            "headers": { "if-none-match": "\"abc\"" },
+            "requestContext": { "http": { "method": "GET" } },
+        });
+
+        let request = request_from_event(&event);
+
+        assert_eq!(request.path, "/outages");
+        assert_eq!(request.query["street"], "Т");
+        assert_eq!(request.if_none_match.as_deref(), Some("\"abc\""));
+    }
+
+    #[test]
+    fn test_response_to_event() {
+        let event = response_to_event(Response {
+            status: 200,
+            headers: vec![("etag", "\"abc\"".to_string())],
+            body: "{}".to_string(),
+        });
+
+        assert_eq!(event["statusCode"], 200);
+        assert_eq!(event["headers"]["etag"], "\"abc\"");
+        assert_eq!(event["body"], "{}");
+    }
+}
@@ -0,0 +1,261 @@
+//! Public read-only HTTP/JSON API over the parsed outages and the streets of
+//! Belgrade. Requests are handled independently of the transport, the same
+//! handler is served by a Lambda function and by a local HTTP server, see the
+//! binaries of the crate.
+use electricity::outages::Repository;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use tracing::error;
+
+pub mod lambda;
+mod outages;
+mod pagination;
+mod streets;
+
+pub use pagination::{Page, Paginated};
+
+/// The OpenAPI description of the API, served at `/openapi.json`.
+pub const OPENAPI: &str = include_str!("openapi.json");
+
+/// How long clients and proxies may reuse a response without revalidating it.
+const MAX_AGE_SECONDS: u32 = 300;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    /// Decoded query parameters, the last one wins when repeated.
+    pub query: HashMap<String, String>,
+    /// Value of the `If-None-Match` header.
+    pub if_none_match: Option<String>,
+}
+
+impl Request {
+    /// A `GET` request for a path with an optional query string, e.g.
+    /// `/outages?date=2023-11-20`.
+    pub fn get(path_and_query: &str) -> Self {
+        let (path, query) = path_and_query
+            .split_once('?')
+            .unwrap_or((path_and_query, ""));
+
+        Self {
+            method: "GET".to_string(),
+            path: path.to_string(),
+            query: form_urlencoded::parse(query.as_bytes())
+                .into_owned()
+                .collect(),
+            if_none_match: None,
+        }
+    }
+
+    pub fn with_if_none_match(mut self, etag: &str) -> Self {
+        self.if_none_match = Some(etag.to_string());
+        self
+    }
+
+    fn param(&self, name: &str) -> Option<&str> {
+        self.query
+            .get(name)
+            .map(|it| it.trim())
+            .filter(|it| !it.is_empty())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub headers: Vec<(&'static str, String)>,
+    pub body: String,
+}
+
+impl Response {
+    fn json<T: Serialize>(status: u16, value: &T) -> Self {
+        match serde_json::to_string(value) {
+            Ok(body) => Self {
+                status,
+                headers: vec![("content-type", "application/json".to_string())],
+                body,
+            },
+            Err(err) => {
+                error!(error = %err, "failed to serialize a response");
+                Self::error(500, "internal error")
+            }
+        }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        Self {
+            status,
+            headers: vec![("content-type", "application/json".to_string())],
+            body: serde_json::json!({ "error": message }).to_string(),
+        }
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Tags a successful response with the hash of its body, and turns it into
+    /// `304 Not Modified` when the client already has the same content.
+    fn cached(mut self, if_none_match: Option<&str>) -> Self {
+        if self.status != 200 {
+            return self;
+        }
+
+        let etag = format!(
+            "\"{}\"",
+            &hex::encode(Sha256::digest(self.body.as_bytes()))[..32]
+        );
+        self.headers.push(("etag", etag.clone()));
+        self.headers.push((
+            "cache-control",
+            format!("public, max-age={MAX_AGE_SECONDS}"),
+        ));
+
+        let matches = if_none_match.is_some_and(|it| {
+            it.split(',')
+                .map(|tag| tag.trim().trim_start_matches("W/"))
+                .any(|tag| tag == etag || tag == "*")
+        });
+        if matches {
+            self.status = 304;
+            self.body = String::new();
+            self.headers.retain(|(name, _)| *name != "content-type");
+        }
+
+        self
+    }
+}
+
+/// An error which is reported to the client as it is.
+#[derive(Debug)]
+pub(crate) struct BadRequest(pub String);
+
+impl std::fmt::Display for BadRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for BadRequest {}
+
+pub async fn handle<R>(outages: &R, request: &Request) -> Response
+where
+    R: Repository,
+{
+    if request.method != "GET" && request.method != "HEAD" {
+        return Response::error(405, "only GET requests are supported");
+    }
+
+    let segments = request
+        .path
+        .split('/')
+        .filter(|it| !it.is_empty())
+        .collect::<Vec<_>>();
+
+    let response = match segments.as_slice() {
+        ["outages"] => outages::list(outages, request).await,
+        ["outages", id] => outages::get(outages, id).await,
+        ["streets", "search"] => streets::search(request),
+        ["openapi.json"] => Ok(Response {
+            status: 200,
+            headers: vec![("content-type", "application/json".to_string())],
+            body: OPENAPI.to_string(),
+        }),
+        _ => Ok(Response::error(404, "not found")),
+    };
+
+    let mut response = match response {
+        Ok(response) => response.cached(request.if_none_match.as_deref()),
+        Err(err) => match err.downcast::<BadRequest>() {
+            Ok(BadRequest(message)) => Response::error(400, &message),
+            Err(err) => {
+                error!(error = %err, path = request.path, "failed to handle a request");
+                Response::error(500, "internal error")
+            }
+        },
+    };
+
+    // HEAD gets the status and the headers of GET, without the body.
+    if request.method == "HEAD" {
+        response.body = String::new();
+    }
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use electricity::outages::InMemoryOutages;
+
+    #[tokio::test]
+    async fn test_etag() {
+        let outages = InMemoryOutages::new();
+
+        let response = handle(&outages, &Request::get("/openapi.json")).await;
+        assert_eq!(response.status, 200);
+        let etag = response.header("etag").unwrap().to_string();
+
+        let response = handle(
+            &outages,
+            &Request::get("/openapi.json").with_if_none_match(&etag),
+        )
+        .await;
+        assert_eq!(response.status, 304);
+        assert!(response.body.is_empty());
+        assert_eq!(response.header("etag"), Some(etag.as_str()));
+
+        let response = handle(
+            &outages,
+            &Request::get("/openapi.json").with_if_none_match("\"other\""),
+        )
+        .await;
+        assert_eq!(response.status, 200);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_routes() {
+        let outages = InMemoryOutages::new();
+
+        assert_eq!(handle(&outages, &Request::get("/")).await.status, 404);
+        assert_eq!(
+            handle(&outages, &Request::get("/outages/1/2")).await.status,
+            404
+        );
+
+        let request = Request {
+            method: "POST".to_string(),
+            ..Request::get("/outages")
+        };
+        assert_eq!(handle(&outages, &request).await.status, 405);
+
+        let get = handle(&outages, &Request::get("/openapi.json")).await;
+        let head = |path| Request {
+            method: "HEAD".to_string(),
+            ..Request::get(path)
+        };
+        let response = handle(&outages, &head("/openapi.json")).await;
+        assert_eq!(response.status, 200);
+        assert!(response.body.is_empty());
+        assert_eq!(response.header("etag"), get.header("etag"));
+        assert_eq!(response.header("content-type"), Some("application/json"));
+
+        let response = handle(&outages, &head("/")).await;
+        assert_eq!(response.status, 404);
+        assert!(response.body.is_empty());
+    }
+
+    #[test]
+    fn test_openapi_is_json() {
+        let openapi: serde_json::Value = serde_json::from_str(OPENAPI).unwrap();
+
+        for path in ["/outages", "/outages/{id}", "/streets/search"] {
+            assert!(openapi["paths"][path]["get"].is_object(), "{path}");
+        }
+    }
+}
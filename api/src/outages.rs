@@ -0,0 +1,244 @@
+use crate::{BadRequest, Page, Request, Response};
+use anyhow::Result;
+use chrono::{NaiveDate, Utc};
+use electricity::outages::{Outage, Repository};
+use electricity::translit::Translit;
+use electricity::BEOGRAD_ELECTRICITY_PAGES;
+use serde::Serialize;
+
+/// An outage as exposed by the API, dates are `YYYY-MM-DD`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OutageItem {
+    pub id: String,
+    pub date: NaiveDate,
+    pub time: String,
+    pub municipality: String,
+    pub settlement: Option<String>,
+    pub street: String,
+}
+
+impl From<Outage> for OutageItem {
+    fn from(outage: Outage) -> Self {
+        Self {
+            id: outage.id,
+            date: outage.date,
+            time: outage.time.trim().to_owned(),
+            municipality: outage.region.trim().to_owned(),
+            settlement: outage.settlement,
+            street: outage.street,
+        }
+    }
+}
+
+/// `GET /outages`, the outages of the day or, without a date, the ones of the
+/// published schedule, a page per day from today on. Every day is a keyed
+/// lookup, the table is never scanned. Municipality has to match, street is a
+/// part of the name, both are compared transliterated, so either script can
+/// be used.
+pub(crate) async fn list<R>(outages: &R, request: &Request) -> Result<Response>
+where
+    R: Repository,
+{
+    let page = Page::from_request(request)?;
+    let date = request
+        .param("date")
+        .map(|value| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .map_err(|_| BadRequest("date must be formatted as YYYY-MM-DD".to_string()))
+        })
+        .transpose()?;
+    let municipality = request.param("municipality").map(|it| it.translit());
+    let street = request.param("street").map(|it| it.translit());
+
+    let days = match date {
+        Some(date) => vec![date],
+        None => Utc::now()
+            .date_naive()
+            .iter_days()
+            .take(BEOGRAD_ELECTRICITY_PAGES.len())
+            .collect(),
+    };
+    let mut found = vec![];
+    for day in days {
+        found.extend(outages.find_on(day).await?);
+    }
+
+    let mut outages = found
+        .into_iter()
+        .filter(|it| {
+            municipality
+                .as_ref()
+                .is_none_or(|municipality| it.region.translit().trim() == municipality)
+        })
+        .filter(|it| {
+            street
+                .as_ref()
+                .is_none_or(|street| it.street.translit().contains(street.as_str()))
+        })
+        .map(OutageItem::from)
+        .collect::<Vec<_>>();
+    // A stable order, otherwise pages would overlap.
+    outages.sort_by(|a, b| (a.date, &a.time, &a.street, &a.id).cmp(&(b.date, &b.time, &b.street, &b.id)));
+
+    Ok(Response::json(200, &page.apply(outages)))
+}
+
+/// `GET /outages/{id}`.
+pub(crate) async fn get<R>(outages: &R, id: &str) -> Result<Response>
+where
+    R: Repository,
+{
+    match outages.find_by_id(id).await? {
+        Some(outage) => Ok(Response::json(200, &OutageItem::from(outage))),
+        None => Ok(Response::error(404, "outage not found")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{handle, Request};
+    use chrono::{Days, NaiveDate, Utc};
+    use electricity::outages::{InMemoryOutages, NewOutage, Repository as _};
+    use serde_json::{json, Value};
+
+    fn outage(date: NaiveDate, region: &str, street: &str) -> NewOutage {
+        NewOutage {
+            date,
+            time: "08:00 - 16:00".to_string(),
+            region: region.to_string(),
+            settlement: None,
+            street: street.to_string(),
+        }
+    }
+
+    async fn storage() -> InMemoryOutages {
+        let storage = InMemoryOutages::new();
+        let day = |day| NaiveDate::from_ymd_opt(2023, 11, day).unwrap();
+
+        for (date, region, street) in [
+            (day(20), "Палилула", "Таковска"),
+            (day(20), "Палилула", "Кнеза Милоша"),
+            (day(20), "Звездара", "Булевар краља Александра"),
+            (day(21), "Палилула", "Таковска"),
+        ] {
+            storage.append(outage(date, region, street)).await.unwrap();
+        }
+
+        storage
+    }
+
+    async fn get(storage: &InMemoryOutages, path: &str) -> (u16, Value) {
+        let response = handle(storage, &Request::get(path)).await;
+
+        (
+            response.status,
+            serde_json::from_str(&response.body).unwrap(),
+        )
+    }
+
+    fn streets(page: &Value) -> Vec<&str> {
+        page["items"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|it| it["street"].as_str().unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_list_filters() {
+        let storage = storage().await;
+
+        let (status, page) = get(&storage, "/outages?date=2023-11-20").await;
+        assert_eq!(status, 200);
+        assert_eq!(page["total"], 3);
+        assert_eq!(
+            streets(&page),
+            vec!["Булевар краља Александра", "Кнеза Милоша", "Таковска"]
+        );
+
+        let (_, page) = get(
+            &storage,
+            "/outages?date=2023-11-20&municipality=palilula&street=takov",
+        )
+        .await;
+        assert_eq!(streets(&page), vec!["Таковска"]);
+        assert_eq!(
+            page["items"][0],
+            json!({
+                "id": "1",
+                "date": "2023-11-20",
+                "time": "08:00 - 16:00",
+                "municipality": "Палилула",
+                "settlement": null,
+                "street": "Таковска",
+            })
+        );
+
+        let (_, page) = get(
+            &storage,
+            "/outages?date=2023-11-20&municipality=%D0%B7%D0%B2%D0%B5%D0%B7%D0%B4%D0%B0%D1%80%D0%B0",
+        )
+        .await;
+        assert_eq!(streets(&page), vec!["Булевар краља Александра"]);
+    }
+
+    #[tokio::test]
+    async fn test_list_published_days() {
+        let storage = InMemoryOutages::new();
+        let today = Utc::now().date_naive();
+
+        for (days, street) in [
+            (0, "Таковска"),
+            (3, "Кнеза Милоша"),
+            (4, "Булевар краља Александра"),
+        ] {
+            let date = today.checked_add_days(Days::new(days)).unwrap();
+            storage
+                .append(outage(date, "Палилула", street))
+                .await
+                .unwrap();
+        }
+        let yesterday = today.pred_opt().unwrap();
+        storage
+            .append(outage(yesterday, "Палилула", "Цара Душана"))
+            .await
+            .unwrap();
+
+        let (status, page) = get(&storage, "/outages").await;
+        assert_eq!(status, 200);
+        assert_eq!(streets(&page), vec!["Таковска", "Кнеза Милоша"]);
+    }
+
+    #[tokio::test]
+    async fn test_list_pagination() {
+        let storage = storage().await;
+
+        let (_, page) = get(&storage, "/outages?date=2023-11-20&limit=2&offset=1").await;
+        assert_eq!(page["total"], 3);
+        assert_eq!(page["limit"], 2);
+        assert_eq!(page["offset"], 1);
+        assert_eq!(streets(&page), vec!["Кнеза Милоша", "Таковска"]);
+    }
+
+    #[tokio::test]
+    async fn test_list_bad_request() {
+        let storage = storage().await;
+
+        let (status, body) = get(&storage, "/outages?date=20.11.2023").await;
+        assert_eq!(status, 400);
+        assert!(body["error"].as_str().unwrap().contains("YYYY-MM-DD"));
+    }
+
+    #[tokio::test]
+    async fn test_get() {
+        let storage = storage().await;
+
+        let (status, outage) = get(&storage, "/outages/4").await;
+        assert_eq!(status, 200);
+        assert_eq!(outage["date"], "2023-11-21");
+
+        let (status, _) = get(&storage, "/outages/unknown").await;
+        assert_eq!(status, 404);
+    }
+}
@@ -0,0 +1,101 @@
+use crate::{BadRequest, Request};
+use anyhow::Result;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Page {
+    pub limit: usize,
+    pub offset: usize,
+}
+
+impl Page {
+    pub const DEFAULT_LIMIT: usize = 50;
+    pub const MAX_LIMIT: usize = 200;
+
+    /// Reads `limit` and `offset` query parameters.
+    pub(crate) fn from_request(request: &Request) -> Result<Self> {
+        let number = |name: &str| {
+            request
+                .param(name)
+                .map(|value| {
+                    value
+                        .parse::<usize>()
+                        .map_err(|_| BadRequest(format!("{name} must be a non-negative number")))
+                })
+                .transpose()
+        };
+
+        let limit = number("limit")?.unwrap_or(Self::DEFAULT_LIMIT);
+        if limit == 0 || limit > Self::MAX_LIMIT {
+            return Err(BadRequest(format!("limit must be between 1 and {}", Self::MAX_LIMIT)).into());
+        }
+
+        Ok(Self {
+            limit,
+            offset: number("offset")?.unwrap_or(0),
+        })
+    }
+
+    pub fn apply<T>(&self, items: Vec<T>) -> Paginated<T> {
+        let total = items.len();
+
+        Paginated {
+            items: items
+                .into_iter()
+                .skip(self.offset)
+                .take(self.limit)
+                .collect(),
+            total,
+            limit: self.limit,
+            offset: self.offset,
+        }
+    }
+}
+
+/// A page of the results together with the number of all of them.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Paginated<T> {
+    pub items: Vec<T>,
+    pub total: usize,
+    pub limit: usize,
+    pub offset: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_from_request() {
+        assert_eq!(
+            Page::from_request(&Request::get("/outages")).unwrap(),
+            Page {
+                limit: Page::DEFAULT_LIMIT,
+                offset: 0
+            }
+        );
+        assert_eq!(
+            Page::from_request(&Request::get("/outages?limit=10&offset=20")).unwrap(),
+            Page {
+                limit: 10,
+                offset: 20
+            }
+        );
+
+        for query in ["limit=0", "limit=1000", "limit=-1", "offset=x"] {
+            assert!(Page::from_request(&Request::get(&format!("/outages?{query}"))).is_err());
+        }
+    }
+
+    #[test]
+    fn test_apply() {
+        let page = Page {
+            limit: 2,
+            offset: 1,
+        }
+        .apply(vec![1, 2, 3, 4]);
+
+        assert_eq!(page.items, vec![2, 3]);
+        assert_eq!(page.total, 4);
+    }
+}
@@ -0,0 +1,104 @@
+use crate::{BadRequest, Page, Request, Response};
+use anyhow::Result;
+use beograd_streets::{StaticStreet, STREETS};
+use electricity::translit::Translit;
+use serde::Serialize;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StreetItem {
+    pub name: &'static str,
+    pub old_name: Option<&'static str>,
+    pub municipality: &'static str,
+    pub settlement: &'static str,
+    pub settlement_part: &'static str,
+}
+
+impl From<&StaticStreet> for StreetItem {
+    fn from(street: &StaticStreet) -> Self {
+        Self {
+            name: street.street_name,
+            old_name: street.old_street_name,
+            municipality: street.municipality,
+            settlement: street.settlement,
+            settlement_part: street.settlement_part,
+        }
+    }
+}
+
+/// `GET /streets/search?q=`, the streets whose current or old name contains
+/// the query, the names starting with it go first.
+pub(crate) fn search(request: &Request) -> Result<Response> {
+    let page = Page::from_request(request)?;
+    let query = request
+        .param("q")
+        .ok_or_else(|| BadRequest("q is required".to_string()))?
+        .translit();
+    let municipality = request.param("municipality").map(|it| it.translit());
+
+    let mut streets = STREETS
+        .iter()
+        .filter(|it| it.street_name.contains(&query) || it.old_street_name.is_some_and(|name| name.contains(&query)))
+        .filter(|it| {
+            municipality
+                .as_ref()
+                .is_none_or(|municipality| it.municipality == municipality)
+        })
+        .collect::<Vec<_>>();
+    streets.sort_by_key(|it| {
+        (
+            !it.street_name.starts_with(&query),
+            it.street_name,
+            it.municipality,
+            it.settlement_part,
+        )
+    });
+
+    Ok(Response::json(
+        200,
+        &page.apply(streets.into_iter().map(StreetItem::from).collect()),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{handle, Request};
+    use electricity::outages::InMemoryOutages;
+    use serde_json::Value;
+
+    async fn search(query: &str) -> (u16, Value) {
+        let response = handle(
+            &InMemoryOutages::new(),
+            &Request::get(&format!("/streets/search?{query}")),
+        )
+        .await;
+
+        (
+            response.status,
+            serde_json::from_str(&response.body).unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_search() {
+        let (status, page) = search("q=%D0%9A%D0%BD%D0%B5%D0%B7%D0%B0%20%D0%9C%D0%B8%D0%BB%D0%BE%D1%88%D0%B0").await;
+
+        assert_eq!(status, 200);
+        assert!(page["total"].as_u64().unwrap() >= 1);
+        assert_eq!(page["items"][0]["name"], "kneza miloša");
+    }
+
+    #[tokio::test]
+    async fn test_search_by_municipality() {
+        let (_, page) = search("q=kneza+milo%C5%A1a&municipality=stari+grad").await;
+
+        assert_eq!(page["total"], 1);
+        assert_eq!(page["items"][0]["municipality"], "stari grad");
+    }
+
+    #[tokio::test]
+    async fn test_search_requires_query() {
+        let (status, _) = search("q=+").await;
+
+        assert_eq!(status, 400);
+    }
+}
@@ -0,0 +1,9 @@
+CREATE TYPE lead_time_type AS ENUM ('same_day', 'one_day', 'all_days');
+
+CREATE TYPE delivery_mode_type AS ENUM ('instant', 'digest');
+
+ALTER TABLE preference
+    ADD COLUMN lead_time     lead_time_type NOT NULL DEFAULT 'one_day',
+    ADD COLUMN delivery_mode delivery_mode_type NOT NULL DEFAULT 'instant',
+    ADD COLUMN quiet_from    SMALLINT CHECK (quiet_from BETWEEN 0 AND 23),
+    ADD COLUMN quiet_to      SMALLINT CHECK (quiet_to BETWEEN 0 AND 23);
@@ -0,0 +1,2 @@
+ALTER TABLE subscriptions ALTER COLUMN id TYPE BIGINT;
+ALTER TABLE messages ALTER COLUMN id TYPE BIGINT;
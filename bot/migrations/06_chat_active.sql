@@ -0,0 +1 @@
+ALTER TABLE preference ADD COLUMN active BOOLEAN NOT NULL DEFAULT TRUE;
@@ -0,0 +1,11 @@
+CREATE TABLE outages (
+    id         SERIAL PRIMARY KEY,
+    date       DATE NOT NULL,
+    time       TEXT NOT NULL,
+    region     TEXT NOT NULL,
+    settlement TEXT,
+    street     TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX outages_date_idx ON outages (date);
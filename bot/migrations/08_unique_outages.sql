@@ -0,0 +1,12 @@
+-- The parser writes the outages of every stored page on each run, so a row
+-- is kept once.
+DELETE FROM outages a
+    USING outages b
+    WHERE a.id > b.id
+      AND a.date = b.date
+      AND a.time = b.time
+      AND a.region = b.region
+      AND a.settlement IS NOT DISTINCT FROM b.settlement
+      AND a.street = b.street;
+
+CREATE UNIQUE INDEX outages_unique_idx ON outages (date, time, region, COALESCE(settlement, ''), street);
@@ -0,0 +1,40 @@
+//! Lambda function serving the calendar feeds of the chats through a function
+//! URL, see [`bot::calendar::FeedConfig::from_env`] for the settings.
+use anyhow::{Context, Result};
+use bot::calendar::{FeedConfig, FeedResponse};
+use bot::storage::{AnyStorage, StorageConfig};
+use dotenvy::dotenv;
+use lambda_runtime::{service_fn, LambdaEvent};
+use serde_json::{json, Value};
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let _ = dotenv();
+
+    let config = FeedConfig::from_env().context("CALENDAR_FEED_SECRET is required")?;
+    let storage = StorageConfig::from_env()?.connect().await?;
+
+    let (storage, config) = (&storage, &config);
+    let func = service_fn(move |event: LambdaEvent<Value>| async move { feed_handler(storage, config, event).await });
+    if let Err(e) = lambda_runtime::run(func).await {
+        tracing::error!("Error: {}", e);
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
+
+async fn feed_handler(storage: &AnyStorage, config: &FeedConfig, event: LambdaEvent<Value>) -> Result<Value> {
+    let path = event.payload["rawPath"].as_str().unwrap_or_default();
+
+    let response = match storage.calendar_feed(config, path).await? {
+        FeedResponse::Calendar(calendar) => json!({
+            "statusCode": 200,
+            "headers": { "content-type": "text/calendar; charset=utf-8" },
+            "body": calendar,
+        }),
+        FeedResponse::NotFound => json!({ "statusCode": 404 }),
+    };
+
+    Ok(response)
+}
@@ -0,0 +1,41 @@
+//! Manages the Postgres schema of the bot.
+//!
+//! Usage: `migrate [status|up|check]`
+//!
+//! * `status` prints every embedded migration and whether it is applied;
+//! * `up` applies the pending migrations;
+//! * `check` fails when the database is behind the build (pending migrations)
+//!   or ahead of it (migrations unknown to the build, which would have to be
+//!   reverted first).
+use anyhow::{bail, Result};
+use bot::migrations;
+use dotenvy::dotenv;
+use sqlx::postgres::PgPoolOptions;
+use std::env;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let _ = dotenv();
+
+    let command = env::args().nth(1).unwrap_or("status".to_owned());
+    let database_url = dotenvy::var("POSTGRESQL_URL")?;
+    let pool = PgPoolOptions::new()
+        .max_connections(1)
+        .connect(&database_url)
+        .await?;
+
+    match command.as_str() {
+        "status" => print!("{}", migrations::status(&pool).await?),
+        "up" => {
+            migrations::up(&pool).await?;
+            print!("{}", migrations::status(&pool).await?);
+        }
+        "check" => {
+            migrations::verify(&pool).await?;
+            println!("the database schema is up to date");
+        }
+        command => bail!("unknown command {command}, expected one of: status, up, check"),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,48 @@
+//! Tells the subscribers how the published schedule has changed, meant to be
+//! run periodically after the collector, which queues the changes in
+//! `CHANGES_TABLE_NAME`. A change stays queued while some chat waits for it,
+//! e.g. because of its quiet hours.
+//!
+//! `DATABASE_URL` points the queue client to LocalStack or DynamoDB Local.
+use anyhow::Result;
+use bot::storage::StorageConfig;
+use bot::telegram::bot_from_env;
+use dotenvy::dotenv;
+use electricity::changes;
+use electricity::db::{changes_table_name, init_client, init_custom_client};
+use std::env;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let _ = dotenv();
+
+    let storage = StorageConfig::from_env()?.connect().await?;
+    let bot = bot_from_env()?;
+    let client = if env::var("DATABASE_URL").is_ok() {
+        init_custom_client().await?
+    } else {
+        init_client().await?
+    };
+    let table_name = changes_table_name();
+
+    let pending = changes::pending(&client, &table_name).await?;
+    let delivery = storage
+        .notify_changes(&bot, &pending, chrono::Local::now().naive_local())
+        .await?;
+
+    for change in &pending {
+        if delivery.waiting.contains(&change.id) {
+            let chats = delivery
+                .notified
+                .get(&change.id)
+                .map_or(&[][..], Vec::as_slice);
+            changes::mark_notified(&client, &table_name, &change.id, chats).await?;
+        } else {
+            changes::delete(&client, &table_name, &change.id).await?;
+        }
+    }
+
+    println!("{} changes, {}", pending.len(), delivery.stats);
+
+    Ok(())
+}
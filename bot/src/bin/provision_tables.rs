@@ -0,0 +1,46 @@
+//! Creates the DynamoDB tables of the bot and of the electricity collector.
+//!
+//! Usage: `provision_tables [provision|check]`
+//!
+//! * `provision` creates missing tables and indexes, enables missing TTL
+//!   settings and fills the street keys of older subscriptions, existing
+//!   tables are left untouched otherwise;
+//! * `check` only verifies that every table exists and matches its spec.
+//!
+//! `DATABASE_URL` points the client to LocalStack or DynamoDB Local.
+use anyhow::{bail, Result};
+use bot::storage::dynamo_tables;
+use bot::subscriptions::index_streets;
+use dotenvy::dotenv;
+use electricity::db::table_spec::{ensure_tables, Mode};
+use electricity::db::{init_client, init_custom_client, tables};
+use std::env;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let _ = dotenv();
+
+    let mode = match env::args().nth(1).as_deref() {
+        None | Some("provision") => Mode::Provision,
+        Some("check") => Mode::Verify,
+        Some(command) => bail!("unknown command {command}, expected one of: provision, check"),
+    };
+
+    let client = if env::var("DATABASE_URL").is_ok() {
+        init_custom_client().await?
+    } else {
+        init_client().await?
+    };
+
+    let specs = [dynamo_tables(), tables()].concat();
+    for (table, state) in ensure_tables(&client, &specs, mode).await? {
+        println!("{table:<30} {state}");
+    }
+
+    if mode == Mode::Provision {
+        let indexed = index_streets(&client).await?;
+        println!("indexed the streets of {indexed} subscribed addresses");
+    }
+
+    Ok(())
+}
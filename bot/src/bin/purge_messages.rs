@@ -0,0 +1,18 @@
+//! Removes the messages outside of the retention policy, meant to be run
+//! periodically. See [`bot::messages::RetentionPolicy::from_env`] for the settings.
+use anyhow::Result;
+use bot::storage::StorageConfig;
+use dotenvy::dotenv;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let _ = dotenv();
+
+    let config = StorageConfig::from_env()?;
+    let storage = config.connect().await?;
+
+    let purged = storage.purge_messages(&config.retention).await?;
+    println!("purged {purged} messages");
+
+    Ok(())
+}
@@ -0,0 +1,445 @@
+//! Outages at the addresses of a chat as an iCalendar (RFC 5545) file. The bot
+//! sends it on `/calendar` and serves the same content under a secret URL of
+//! the chat, so calendar applications keep polling it and pick up every newly
+//! parsed version of the schedule on their own.
+use crate::outages::{schedule, Repository as _, Schedule};
+use crate::preferences::{Language, Repository as _};
+use crate::storage::Storage;
+use crate::subscriptions::Repository as _;
+use anyhow::Result;
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, Utc};
+use hmac::{Hmac, Mac};
+use rust_i18n::t as _t;
+use sha2::{Digest, Sha256};
+
+type HmacSha256 = Hmac<Sha256>;
+
+const TIMEZONE: &str = "Europe/Belgrade";
+
+/// Rules of the Central European time as observed in Serbia.
+const VTIMEZONE: [&str; 17] = [
+    "BEGIN:VTIMEZONE",
+    "TZID:Europe/Belgrade",
+    "BEGIN:DAYLIGHT",
+    "TZOFFSETFROM:+0100",
+    "TZOFFSETTO:+0200",
+    "TZNAME:CEST",
+    "DTSTART:19700329T020000",
+    "RRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=-1SU",
+    "END:DAYLIGHT",
+    "BEGIN:STANDARD",
+    "TZOFFSETFROM:+0200",
+    "TZOFFSETTO:+0100",
+    "TZNAME:CET",
+    "DTSTART:19701025T030000",
+    "RRULE:FREQ=YEARLY;BYMONTH=10;BYDAY=-1SU",
+    "END:STANDARD",
+    "END:VTIMEZONE",
+];
+
+/// Past outages are kept in the calendar for a while, otherwise they would
+/// disappear from the subscribed calendars as soon as the day is over.
+const HISTORY_DAYS: i64 = 7;
+
+/// Longest line allowed by RFC 5545, in octets.
+const MAX_LINE_LENGTH: usize = 75;
+
+/// Builds the calendar of the chat from its subscriptions and the outages
+/// parsed so far.
+pub async fn chat_calendar<S>(storage: &S, chat_id: i64, language: Language) -> Result<String>
+where
+    S: Storage,
+{
+    let addresses = storage
+        .subscriptions()
+        .find_all_by_chat_id(chat_id)
+        .await?
+        .into_iter()
+        .map(|it| it.address)
+        .collect::<Vec<_>>();
+
+    let now = Utc::now();
+    let outages = storage
+        .outages()
+        .find_since(now.date_naive() - Duration::days(HISTORY_DAYS))
+        .await?;
+
+    Ok(render(&schedule(&outages, &addresses), language, now))
+}
+
+/// Renders one event per date, time window and address.
+pub fn render(schedule: &Schedule, language: Language, now: DateTime<Utc>) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//electricity-bot//outages//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+        "METHOD:PUBLISH".to_string(),
+        format!(
+            "X-WR-CALNAME:{}",
+            escape_text(&_t!("calendar.name", locale = language.as_ref()))
+        ),
+        format!("X-WR-TIMEZONE:{TIMEZONE}"),
+        // Hints for the clients subscribed to the feed how often to refresh it.
+        "REFRESH-INTERVAL;VALUE=DURATION:PT1H".to_string(),
+        "X-PUBLISHED-TTL:PT1H".to_string(),
+    ];
+    lines.extend(VTIMEZONE.iter().map(|it| it.to_string()));
+
+    for (date, windows) in schedule {
+        for (time, addresses) in windows {
+            for address in addresses {
+                lines.push("BEGIN:VEVENT".to_string());
+                lines.push(format!("UID:{}", uid(*date, time, address)));
+                lines.push(format!("DTSTAMP:{}", now.format("%Y%m%dT%H%M%SZ")));
+                lines.extend(event_time(*date, time));
+                lines.push(format!(
+                    "SUMMARY:{}",
+                    escape_text(&_t!(
+                        "calendar.summary",
+                        locale = language.as_ref(),
+                        address = address
+                    ))
+                ));
+                lines.push(format!("LOCATION:{}", escape_text(address)));
+                lines.push(format!("DESCRIPTION:{}", escape_text(time)));
+                lines.push("TRANSP:TRANSPARENT".to_string());
+                lines.push("END:VEVENT".to_string());
+            }
+        }
+    }
+
+    lines.push("END:VCALENDAR".to_string());
+
+    lines.iter().map(|line| fold(line) + "\r\n").collect()
+}
+
+/// The identifier of an event does not change between renders, so calendars
+/// update the events they already know instead of duplicating them.
+fn uid(date: NaiveDate, time: &str, address: &str) -> String {
+    let hash = Sha256::digest(format!("{date}|{time}|{address}"));
+
+    format!("{}@electricity-bot", &hex::encode(hash)[..32])
+}
+
+/// `DTSTART` and `DTEND` of the event, the whole day when the published time
+/// window cannot be read.
+fn event_time(date: NaiveDate, time: &str) -> [String; 2] {
+    match parse_window(time) {
+        Some((from, to)) => {
+            // A window like `22:00 - 02:00` ends on the next day.
+            let end_date = if to <= from {
+                date + Duration::days(1)
+            } else {
+                date
+            };
+
+            [
+                format!(
+                    "DTSTART;TZID={TIMEZONE}:{}",
+                    date.and_time(from).format("%Y%m%dT%H%M%S")
+                ),
+                format!(
+                    "DTEND;TZID={TIMEZONE}:{}",
+                    end_date.and_time(to).format("%Y%m%dT%H%M%S")
+                ),
+            ]
+        }
+        None => [
+            format!("DTSTART;VALUE=DATE:{}", date.format("%Y%m%d")),
+            format!(
+                "DTEND;VALUE=DATE:{}",
+                (date + Duration::days(1)).format("%Y%m%d")
+            ),
+        ],
+    }
+}
+
+/// Reads a time window as published, e.g. `08:00 - 16:00`.
+fn parse_window(time: &str) -> Option<(NaiveTime, NaiveTime)> {
+    let (from, to) = time.split_once('-')?;
+
+    Some((
+        NaiveTime::parse_from_str(from.trim(), "%H:%M").ok()?,
+        NaiveTime::parse_from_str(to.trim(), "%H:%M").ok()?,
+    ))
+}
+
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Splits a long content line into lines of at most 75 octets, the
+/// continuation lines start with a space.
+fn fold(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len());
+    let mut length = 0;
+
+    for c in line.chars() {
+        if length + c.len_utf8() > MAX_LINE_LENGTH {
+            folded.push_str("\r\n ");
+            length = 1;
+        }
+
+        folded.push(c);
+        length += c.len_utf8();
+    }
+
+    folded
+}
+
+/// Settings of the calendar feeds served over HTTP.
+#[derive(Debug, Clone)]
+pub struct FeedConfig {
+    /// The key the feed tokens of the chats are derived from.
+    pub secret: String,
+    /// URL the feed endpoint is reachable at, without the trailing slash.
+    pub base_url: Option<String>,
+}
+
+impl FeedConfig {
+    /// Reads the configuration from `CALENDAR_FEED_SECRET` and
+    /// `CALENDAR_FEED_URL` environment variables, the feeds are disabled
+    /// unless the secret is set.
+    pub fn from_env() -> Option<Self> {
+        let secret = dotenvy::var("CALENDAR_FEED_SECRET")
+            .ok()
+            .filter(|it| !it.is_empty())?;
+
+        Some(Self {
+            secret,
+            base_url: dotenvy::var("CALENDAR_FEED_URL")
+                .ok()
+                .map(|it| it.trim_end_matches('/').to_owned()),
+        })
+    }
+
+    fn mac(&self, chat_id: i64) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(self.secret.as_bytes()).expect("HMAC accepts keys of any size");
+        mac.update(chat_id.to_string().as_bytes());
+        mac
+    }
+
+    /// The secret part of the feed URL of the chat. It is derived from the
+    /// chat id, so nothing has to be stored and it cannot be guessed without
+    /// the key.
+    pub fn token(&self, chat_id: i64) -> String {
+        hex::encode(self.mac(chat_id).finalize().into_bytes())
+    }
+
+    fn verify(&self, chat_id: i64, token: &str) -> bool {
+        hex::decode(token).is_ok_and(|token| self.mac(chat_id).verify_slice(&token).is_ok())
+    }
+
+    pub fn url(&self, chat_id: i64) -> Option<String> {
+        self.base_url
+            .as_ref()
+            .map(|base_url| format!("{base_url}/{chat_id}/{}.ics", self.token(chat_id)))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FeedResponse {
+    Calendar(String),
+    NotFound,
+}
+
+/// Serves a request for `/<chat id>/<token>.ics`. Unknown chats and wrong
+/// tokens are not told apart.
+pub async fn feed<S>(storage: &S, config: &FeedConfig, path: &str) -> Result<FeedResponse>
+where
+    S: Storage,
+{
+    let mut segments = path.trim_end_matches('/').rsplit('/');
+    let token = segments.next().and_then(|it| it.strip_suffix(".ics"));
+    let chat_id = segments.next().and_then(|it| it.parse::<i64>().ok());
+
+    let (Some(chat_id), Some(token)) = (chat_id, token) else {
+        return Ok(FeedResponse::NotFound);
+    };
+    if !config.verify(chat_id, token) {
+        return Ok(FeedResponse::NotFound);
+    }
+
+    match storage.preferences().find_one(chat_id).await? {
+        Some(preference) => Ok(FeedResponse::Calendar(
+            chat_calendar(storage, chat_id, preference.language).await?,
+        )),
+        // The chat has been forgotten.
+        None => Ok(FeedResponse::NotFound),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::outages::NewOutage;
+    use crate::preferences::ChatPreference;
+    use crate::storage::InMemoryStorage;
+    use crate::subscriptions::NewSubscription;
+    use chrono::TimeZone;
+
+    fn config() -> FeedConfig {
+        FeedConfig {
+            secret: "secret".to_string(),
+            base_url: Some("https://example.com/feed".to_string()),
+        }
+    }
+
+    fn date() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2023, 11, 20).unwrap()
+    }
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2023, 11, 19, 10, 0, 0).unwrap()
+    }
+
+    fn schedule_of(entries: &[(&str, &str)]) -> Schedule {
+        let mut schedule = Schedule::new();
+        for (time, address) in entries {
+            schedule
+                .entry(date())
+                .or_default()
+                .entry(time.to_string())
+                .or_default()
+                .insert(address.to_string());
+        }
+        schedule
+    }
+
+    fn events(calendar: &str) -> Vec<&str> {
+        calendar
+            .split("BEGIN:VEVENT\r\n")
+            .skip(1)
+            .collect::<Vec<_>>()
+    }
+
+    #[test]
+    fn test_render_event_per_window_and_address() {
+        let calendar = render(
+            &schedule_of(&[
+                ("08:00 - 16:00", "kneza miloša 5"),
+                ("08:00 - 16:00", "takovska 10"),
+                ("22:00 - 02:00", "takovska 10"),
+            ]),
+            Language::En,
+            now(),
+        );
+
+        assert!(calendar.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+        assert!(calendar.ends_with("END:VCALENDAR\r\n"));
+        assert!(calendar.contains("TZID:Europe/Belgrade\r\n"));
+
+        let events = events(&calendar);
+        assert_eq!(events.len(), 3);
+        assert!(events[0].contains("DTSTART;TZID=Europe/Belgrade:20231120T080000\r\n"));
+        assert!(events[0].contains("DTEND;TZID=Europe/Belgrade:20231120T160000\r\n"));
+        assert!(events[0].contains("LOCATION:kneza miloša 5\r\n"));
+        assert!(events[0].contains("DTSTAMP:20231119T100000Z\r\n"));
+        assert!(events[2].contains("DTEND;TZID=Europe/Belgrade:20231121T020000\r\n"));
+    }
+
+    #[test]
+    fn test_render_whole_day_for_unknown_window() {
+        let calendar = render(
+            &schedule_of(&[("during the day", "takovska 10")]),
+            Language::En,
+            now(),
+        );
+
+        let events = events(&calendar);
+        assert!(events[0].contains("DTSTART;VALUE=DATE:20231120\r\n"));
+        assert!(events[0].contains("DTEND;VALUE=DATE:20231121\r\n"));
+    }
+
+    #[test]
+    fn test_uid_is_stable() {
+        let first = uid(date(), "08:00 - 16:00", "takovska 10");
+
+        assert_eq!(first, uid(date(), "08:00 - 16:00", "takovska 10"));
+        assert_ne!(first, uid(date(), "08:00 - 16:00", "takovska 12"));
+        assert_ne!(first, uid(date(), "08:00 - 15:00", "takovska 10"));
+    }
+
+    #[test]
+    fn test_escape_and_fold() {
+        assert_eq!(escape_text("a, b; c\\d\ne"), "a\\, b\\; c\\\\d\\ne");
+
+        let line = format!("LOCATION:{}", "š".repeat(50));
+        let folded = fold(&line);
+        assert!(folded.split("\r\n").all(|it| it.len() <= MAX_LINE_LENGTH));
+        assert_eq!(folded.replace("\r\n ", ""), line);
+    }
+
+    #[test]
+    fn test_token() {
+        let config = config();
+
+        assert!(config.verify(5, &config.token(5)));
+        assert!(!config.verify(6, &config.token(5)));
+        assert!(!config.verify(5, "not a token"));
+        assert_eq!(
+            config.url(5),
+            Some(format!(
+                "https://example.com/feed/5/{}.ics",
+                config.token(5)
+            ))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_feed() {
+        let storage = InMemoryStorage::default();
+        let config = config();
+        let chat_id = 5;
+        storage
+            .preferences()
+            .insert(ChatPreference::new(chat_id, Language::En))
+            .await
+            .unwrap();
+        storage
+            .subscriptions()
+            .append(NewSubscription {
+                chat_id,
+                address: "takovska 10".to_string(),
+            })
+            .await
+            .unwrap();
+        storage
+            .outages()
+            .append(NewOutage {
+                date: Utc::now().date_naive(),
+                time: "08:00 - 16:00".to_string(),
+                region: "Palilula".to_string(),
+                settlement: None,
+                street: "takovska".to_string(),
+            })
+            .await
+            .unwrap();
+
+        let path = format!("/{chat_id}/{}.ics", config.token(chat_id));
+        let FeedResponse::Calendar(calendar) = feed(&storage, &config, &path).await.unwrap() else {
+            panic!("the feed is not found");
+        };
+        assert_eq!(events(&calendar).len(), 1);
+
+        let wrong_token = format!("/{chat_id}/{}.ics", config.token(6));
+        assert_eq!(
+            feed(&storage, &config, &wrong_token).await.unwrap(),
+            FeedResponse::NotFound
+        );
+        assert_eq!(
+            feed(&storage, &config, "/favicon.ico").await.unwrap(),
+            FeedResponse::NotFound
+        );
+
+        let unknown_chat = format!("/6/{}.ics", config.token(6));
+        assert_eq!(
+            feed(&storage, &config, &unknown_chat).await.unwrap(),
+            FeedResponse::NotFound
+        );
+    }
+}
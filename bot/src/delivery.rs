@@ -0,0 +1,412 @@
+//! Delivery of notifications to Telegram. Messages go through a queue which
+//! keeps within the global and per-chat rate limits of the Bot API, retries
+//! flood-control and network failures, and reports the chats which can no
+//! longer be reached instead of giving up on everyone else.
+use std::collections::{HashMap, VecDeque};
+use std::fmt::{Display, Formatter};
+use std::future::Future;
+use std::time::Duration;
+use teloxide_core::errors::{ApiError, RequestError};
+use teloxide_core::payloads::SendMessageSetters;
+use teloxide_core::prelude::*;
+use teloxide_core::types::{ChatId, ParseMode};
+use tokio::time::{sleep_until, Instant};
+use tracing::{event, Level};
+
+const RATE_WINDOW: Duration = Duration::from_secs(1);
+
+#[derive(Debug)]
+pub enum SendError {
+    /// Flood control, the request can be repeated after the given time.
+    RetryAfter(Duration),
+    /// The bot was blocked, kicked, or the chat does not exist anymore.
+    Unreachable,
+    /// A network failure, the request can be repeated.
+    Transient(anyhow::Error),
+    Failed(anyhow::Error),
+}
+
+impl From<RequestError> for SendError {
+    fn from(err: RequestError) -> Self {
+        match err {
+            RequestError::RetryAfter(duration) => SendError::RetryAfter(duration),
+            RequestError::Api(
+                ApiError::BotBlocked
+                | ApiError::BotKicked
+                | ApiError::BotKickedFromSupergroup
+                | ApiError::UserDeactivated
+                | ApiError::ChatNotFound,
+            ) => SendError::Unreachable,
+            err @ (RequestError::Network(_) | RequestError::Io(_)) => SendError::Transient(err.into()),
+            err => SendError::Failed(err.into()),
+        }
+    }
+}
+
+/// Sends a single MarkdownV2 message to a chat.
+pub trait Sender {
+    fn send(&self, chat_id: i64, text: &str) -> impl Future<Output = Result<(), SendError>> + Send;
+}
+
+impl Sender for Bot {
+    async fn send(&self, chat_id: i64, text: &str) -> Result<(), SendError> {
+        self.send_message(ChatId(chat_id), text)
+            .parse_mode(ParseMode::MarkdownV2)
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DeliveryLimits {
+    /// Messages sent to all chats within a second.
+    pub messages_per_second: usize,
+    /// The minimal interval between two messages to the same chat.
+    pub per_chat_interval: Duration,
+    pub max_attempts: u32,
+    /// The delay before the first retry, doubled on every next one.
+    pub backoff: Duration,
+}
+
+impl Default for DeliveryLimits {
+    fn default() -> Self {
+        Self {
+            messages_per_second: 30,
+            per_chat_interval: Duration::from_secs(1),
+            max_attempts: 5,
+            backoff: Duration::from_secs(1),
+        }
+    }
+}
+
+impl DeliveryLimits {
+    fn backoff(&self, attempt: u32) -> Duration {
+        self.backoff * 2u32.saturating_pow(attempt.saturating_sub(1))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Outgoing {
+    pub chat_id: i64,
+    pub text: String,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct DeliveryStats {
+    pub sent: usize,
+    /// Chats which have received their messages.
+    pub delivered: Vec<i64>,
+    pub retries: usize,
+    pub failed: usize,
+    /// Chats which have blocked the bot or do not exist anymore, their
+    /// remaining messages are dropped.
+    pub unreachable: Vec<i64>,
+    /// Chats which are not messaged yet because of their notification
+    /// preferences, e.g. quiet hours.
+    pub deferred: Vec<i64>,
+}
+
+impl Display for DeliveryStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "sent {}, retries {}, failed {}, unreachable chats {}, deferred chats {}",
+            self.sent,
+            self.retries,
+            self.failed,
+            self.unreachable.len(),
+            self.deferred.len()
+        )
+    }
+}
+
+struct RateLimiter {
+    limits: DeliveryLimits,
+    recent: VecDeque<Instant>,
+    last_by_chat: HashMap<i64, Instant>,
+    paused_until: Option<Instant>,
+}
+
+impl RateLimiter {
+    fn new(limits: DeliveryLimits) -> Self {
+        Self {
+            limits,
+            recent: VecDeque::new(),
+            last_by_chat: HashMap::new(),
+            paused_until: None,
+        }
+    }
+
+    /// The earliest time a message can be sent to the chat.
+    fn ready_at(&self, chat_id: i64, now: Instant) -> Instant {
+        let global = if self.recent.len() < self.limits.messages_per_second {
+            now
+        } else {
+            self.recent[self.recent.len() - self.limits.messages_per_second] + RATE_WINDOW
+        };
+        let chat = self
+            .last_by_chat
+            .get(&chat_id)
+            .map_or(now, |last| *last + self.limits.per_chat_interval);
+
+        [Some(global), Some(chat), self.paused_until]
+            .into_iter()
+            .flatten()
+            .max()
+            .unwrap_or(now)
+    }
+
+    fn record(&mut self, chat_id: i64, now: Instant) {
+        while self
+            .recent
+            .front()
+            .is_some_and(|it| *it + RATE_WINDOW <= now)
+        {
+            self.recent.pop_front();
+        }
+
+        self.recent.push_back(now);
+        self.last_by_chat.insert(chat_id, now);
+    }
+
+    fn pause_until(&mut self, until: Instant) {
+        self.paused_until = self.paused_until.max(Some(until));
+    }
+}
+
+struct Pending {
+    message: Outgoing,
+    attempt: u32,
+    not_before: Instant,
+}
+
+/// Sends every message, one at a time. A message which has to wait for its
+/// chat does not hold back the messages to other chats.
+pub async fn deliver<S>(sender: &S, messages: Vec<Outgoing>, limits: DeliveryLimits) -> DeliveryStats
+where
+    S: Sender,
+{
+    let start = Instant::now();
+    let mut queue = messages
+        .into_iter()
+        .map(|message| Pending {
+            message,
+            attempt: 1,
+            not_before: start,
+        })
+        .collect::<VecDeque<_>>();
+    let mut limiter = RateLimiter::new(limits);
+    let mut stats = DeliveryStats::default();
+
+    while !queue.is_empty() {
+        let now = Instant::now();
+        let ready_at = |pending: &Pending| {
+            limiter
+                .ready_at(pending.message.chat_id, now)
+                .max(pending.not_before)
+        };
+
+        let Some(index) = queue.iter().position(|it| ready_at(it) <= now) else {
+            let wake_at = queue.iter().map(ready_at).min().unwrap_or(now);
+            sleep_until(wake_at).await;
+            continue;
+        };
+        let Some(mut pending) = queue.remove(index) else {
+            continue;
+        };
+        let chat_id = pending.message.chat_id;
+
+        if stats.unreachable.contains(&chat_id) {
+            continue;
+        }
+
+        limiter.record(chat_id, now);
+
+        let delay = match sender.send(chat_id, &pending.message.text).await {
+            Ok(()) => {
+                stats.sent += 1;
+                stats.delivered.push(chat_id);
+                continue;
+            }
+            Err(SendError::Unreachable) => {
+                event!(Level::INFO, chat_id, "chat is unreachable");
+                stats.unreachable.push(chat_id);
+                continue;
+            }
+            Err(SendError::Failed(err)) => {
+                event!(Level::WARN, chat_id, error = %err, "failed to send a message");
+                stats.failed += 1;
+                continue;
+            }
+            Err(SendError::RetryAfter(retry_after)) => {
+                limiter.pause_until(now + retry_after);
+                retry_after.max(limits.backoff(pending.attempt))
+            }
+            Err(SendError::Transient(err)) => {
+                event!(Level::DEBUG, chat_id, error = %err, "sending a message failed, will retry");
+                limits.backoff(pending.attempt)
+            }
+        };
+
+        if pending.attempt < limits.max_attempts {
+            stats.retries += 1;
+            pending.attempt += 1;
+            pending.not_before = now + delay;
+            queue.push_back(pending);
+        } else {
+            event!(
+                Level::WARN,
+                chat_id,
+                "giving up on a message after {} attempts",
+                pending.attempt
+            );
+            stats.failed += 1;
+        }
+    }
+
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    struct FakeSender {
+        responses: Mutex<HashMap<i64, VecDeque<SendError>>>,
+        sent: Mutex<Vec<(i64, Duration)>>,
+        start: Instant,
+    }
+
+    impl FakeSender {
+        fn new() -> Self {
+            Self {
+                responses: Mutex::new(HashMap::new()),
+                sent: Mutex::new(vec![]),
+                start: Instant::now(),
+            }
+        }
+
+        fn fail(self, chat_id: i64, error: SendError) -> Self {
+            self.responses
+                .lock()
+                .unwrap()
+                .entry(chat_id)
+                .or_default()
+                .push_back(error);
+            self
+        }
+
+        /// Chats and times (since the start) of the successful sends.
+        fn sent(&self) -> Vec<(i64, Duration)> {
+            self.sent.lock().unwrap().clone()
+        }
+    }
+
+    impl Sender for FakeSender {
+        async fn send(&self, chat_id: i64, _text: &str) -> Result<(), SendError> {
+            if let Some(error) = self
+                .responses
+                .lock()
+                .unwrap()
+                .get_mut(&chat_id)
+                .and_then(VecDeque::pop_front)
+            {
+                return Err(error);
+            }
+
+            let elapsed = Instant::now() - self.start;
+            self.sent.lock().unwrap().push((chat_id, elapsed));
+
+            Ok(())
+        }
+    }
+
+    fn outgoing(chat_ids: &[i64]) -> Vec<Outgoing> {
+        chat_ids
+            .iter()
+            .map(|chat_id| Outgoing {
+                chat_id: *chat_id,
+                text: format!("message for {chat_id}"),
+            })
+            .collect()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_unreachable_chat_does_not_stop_delivery() {
+        let sender = FakeSender::new().fail(2, SendError::Unreachable);
+
+        let stats = deliver(&sender, outgoing(&[1, 2, 2, 3]), DeliveryLimits::default()).await;
+
+        assert_eq!(stats.sent, 2);
+        assert_eq!(stats.delivered, vec![1, 3]);
+        assert_eq!(stats.unreachable, vec![2]);
+        assert_eq!(
+            sender.sent().iter().map(|it| it.0).collect::<Vec<_>>(),
+            vec![1, 3]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_retry_after_is_respected() {
+        let sender = FakeSender::new().fail(1, SendError::RetryAfter(Duration::from_secs(5)));
+
+        let stats = deliver(&sender, outgoing(&[1, 2]), DeliveryLimits::default()).await;
+
+        assert_eq!(stats.sent, 2);
+        assert_eq!(stats.retries, 1);
+        // Flood control pauses the whole queue.
+        assert!(sender
+            .sent()
+            .iter()
+            .all(|it| it.1 >= Duration::from_secs(5)));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_gives_up_after_max_attempts() {
+        let limits = DeliveryLimits {
+            max_attempts: 2,
+            ..Default::default()
+        };
+        let sender = FakeSender::new()
+            .fail(1, SendError::Transient(anyhow::anyhow!("timeout")))
+            .fail(1, SendError::Transient(anyhow::anyhow!("timeout")));
+
+        let stats = deliver(&sender, outgoing(&[1, 2]), limits).await;
+
+        assert_eq!(stats.sent, 1);
+        assert_eq!(stats.retries, 1);
+        assert_eq!(stats.failed, 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_rate_limits() {
+        let limits = DeliveryLimits {
+            messages_per_second: 3,
+            ..Default::default()
+        };
+        let sender = FakeSender::new();
+
+        let stats = deliver(&sender, outgoing(&[1, 1, 2, 3, 4, 5]), limits).await;
+
+        assert_eq!(stats.sent, 6);
+        let sent = sender.sent();
+        // The second message to chat 1 waits, the others go first.
+        assert_eq!(
+            sent.iter().map(|it| it.0).collect::<Vec<_>>(),
+            vec![1, 2, 3, 1, 4, 5]
+        );
+        for window in sent.windows(4) {
+            assert!(window[3].1 - window[0].1 >= RATE_WINDOW);
+        }
+    }
+
+    #[test]
+    fn test_backoff_doubles() {
+        let limits = DeliveryLimits::default();
+
+        assert_eq!(limits.backoff(1), Duration::from_secs(1));
+        assert_eq!(limits.backoff(3), Duration::from_secs(4));
+    }
+}
@@ -1,10 +1,14 @@
+pub mod calendar;
+pub mod delivery;
 pub mod message_handler;
-pub mod repositories;
-pub mod repository;
+pub mod migrations;
+pub mod storage;
 pub mod subscriptions;
+pub mod telegram;
 pub mod utils;
 
 pub mod messages;
+pub mod outages;
 pub mod preferences;
 
 #[cfg(test)]
@@ -1,24 +1,31 @@
+use crate::calendar::{chat_calendar, FeedConfig};
+use crate::delivery::{deliver, DeliveryLimits, DeliveryStats, Outgoing};
 use crate::messages::{Message, MessageType, Repository as MessagesRepository};
-use crate::preferences::{ChatPreference, Language, PgChatPreference, Repository as PreferencesRepository};
-use crate::utils::{escape_markdown, t};
-use crate::{
-    repositories::subscription_repository::{NewSubscription, Repository as _, SubscriptionsRepository},
-    utils::Escape,
+use crate::outages::{schedule, Outage, Repository as OutagesRepository};
+use crate::preferences::{
+    ChatPreference, DeliveryMode, Language, LeadTime, NotificationPreference, QuietHours,
+    Repository as PreferencesRepository, SendAt,
 };
+use crate::storage::{forget_chat, Storage};
+use crate::subscriptions::{NewSubscription, Repository as SubscriptionsRepository, Subscription};
+use crate::utils::{escape_markdown, t, Escape};
 use anyhow::Context as _;
-use anyhow::{Ok, Result};
+use anyhow::{anyhow, Ok, Result};
+use chrono::{NaiveDate, NaiveDateTime};
+use electricity::changes::PendingChange;
+use electricity::diff::Change;
 use electricity::translit::Translit;
 use rust_i18n::t as _t;
-use sqlx::postgres::PgPoolOptions;
+use std::collections::{BTreeMap, BTreeSet};
 use std::str::FromStr;
 use teloxide_core::{
     prelude::*,
     types::{
-        ChatId, InlineKeyboardButton, InlineKeyboardButtonKind, InlineKeyboardMarkup, KeyboardButton, KeyboardMarkup,
-        ParseMode, Update, UpdateKind,
+        ChatId, InlineKeyboardButton, InlineKeyboardButtonKind, InlineKeyboardMarkup, InputFile, KeyboardButton,
+        KeyboardMarkup, ParseMode, Update, UpdateKind,
     },
 };
-use tracing::debug;
+use tracing::{debug, info};
 
 fn get_settings_action_text(preference: &ChatPreference) -> String {
     format!("⚙️ {}", t("menu.settings", preference.language))
@@ -40,11 +47,16 @@ fn get_unsubscribe_action_text(preference: &ChatPreference) -> String {
     format!("🔕 {}", t("menu.unsubscribe", preference.language))
 }
 
+fn get_upcoming_action_text(preference: &ChatPreference) -> String {
+    format!("📅 {}", t("menu.upcoming", preference.language))
+}
+
 fn get_full_menu(preference: &ChatPreference) -> KeyboardMarkup {
     KeyboardMarkup::new(vec![
-        vec![KeyboardButton::new(get_check_address_action_text(
-            preference,
-        ))],
+        vec![
+            KeyboardButton::new(get_check_address_action_text(preference)),
+            KeyboardButton::new(get_upcoming_action_text(preference)),
+        ],
         vec![
             KeyboardButton::new(get_subscribe_action_text(preference)),
             KeyboardButton::new(get_unsubscribe_action_text(preference)),
@@ -70,6 +82,202 @@ fn get_settings_actions(preference: &ChatPreference) -> InlineKeyboardMarkup {
     ]])
 }
 
+fn get_unsubscribe_actions(subscriptions: &[Subscription]) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new(subscriptions.iter().map(|subscription| {
+        vec![InlineKeyboardButton::new(
+            format!("🔕 {}", subscription.address),
+            InlineKeyboardButtonKind::CallbackData(format!("unsubscribe_{}", subscription.id)),
+        )]
+    }))
+}
+
+fn get_unsubscribe_confirm_actions(preference: &ChatPreference, subscription: &Subscription) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new(vec![vec![
+        InlineKeyboardButton::new(
+            format!("✅ {}", t("unsubscribe.accept", preference.language)),
+            InlineKeyboardButtonKind::CallbackData(format!("unsubscribe_confirm_{}", subscription.id)),
+        ),
+        InlineKeyboardButton::new(
+            format!("❌ {}", t("unsubscribe.decline", preference.language)),
+            InlineKeyboardButtonKind::CallbackData("unsubscribe_cancel".to_string()),
+        ),
+    ]])
+}
+
+fn get_forget_confirm_actions(preference: &ChatPreference) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new(vec![vec![
+        InlineKeyboardButton::new(
+            format!("✅ {}", t("forget.accept", preference.language)),
+            InlineKeyboardButtonKind::CallbackData("forget_confirm".to_string()),
+        ),
+        InlineKeyboardButton::new(
+            format!("❌ {}", t("forget.decline", preference.language)),
+            InlineKeyboardButtonKind::CallbackData("forget_cancel".to_string()),
+        ),
+    ]])
+}
+
+/// Finds the chat's subscription referenced by an `unsubscribe_*` callback.
+async fn find_subscription<S>(subscriptions: &S, chat_id: i64, id: &str) -> Result<Option<Subscription>>
+where
+    S: SubscriptionsRepository,
+{
+    let id = id
+        .parse::<i64>()
+        .with_context(|| format!("invalid subscription id: {id}"))?;
+    let subscription = subscriptions
+        .find_all_by_chat_id(chat_id)
+        .await?
+        .into_iter()
+        .find(|it| it.id == id);
+
+    Ok(subscription)
+}
+
+/// Lists the outages affecting the subscribed addresses grouped by date and
+/// time window.
+fn get_upcoming_text(language: Language, subscriptions: &[Subscription], outages: &[Outage]) -> String {
+    let addresses = subscriptions
+        .iter()
+        .map(|it| it.address.to_owned())
+        .collect::<Vec<_>>();
+    let schedule = schedule(outages, &addresses);
+
+    if schedule.is_empty() {
+        return t("upcoming.nothing", language);
+    }
+
+    let mut text = t("upcoming.title", language);
+    for (date, windows) in schedule {
+        text.push_str(&format!(
+            "\n\n*{}*",
+            escape_markdown(&date.format("%d.%m.%Y").to_string())
+        ));
+
+        for (time, addresses) in windows {
+            text.push_str(&format!("\n🕘 {}", escape_markdown(&time)));
+
+            for address in addresses {
+                text.push_str(&format!("\n    • {}", escape_markdown(&address)));
+            }
+        }
+    }
+
+    text
+}
+
+/// Quiet hours a chat can choose from the notification settings.
+const QUIET_HOURS_PRESETS: [(i16, i16); 2] = [(22, 7), (23, 8)];
+
+fn checked(text: String, is_checked: bool) -> String {
+    if is_checked {
+        format!("✅ {text}")
+    } else {
+        text
+    }
+}
+
+fn get_notification_actions(preference: &ChatPreference) -> InlineKeyboardMarkup {
+    let notifications = &preference.notifications;
+
+    let lead_time = [LeadTime::SameDay, LeadTime::OneDay, LeadTime::AllDays]
+        .into_iter()
+        .map(|lead_time| {
+            InlineKeyboardButton::new(
+                checked(
+                    format!(
+                        "📅 {}",
+                        t(
+                            &format!("notifications.{}", lead_time.as_ref()),
+                            preference.language
+                        )
+                    ),
+                    notifications.lead_time == lead_time,
+                ),
+                InlineKeyboardButtonKind::CallbackData(format!("notification_lead_{}", lead_time.as_ref())),
+            )
+        })
+        .collect();
+
+    let delivery_mode = [DeliveryMode::Instant, DeliveryMode::Digest]
+        .into_iter()
+        .map(|mode| {
+            InlineKeyboardButton::new(
+                checked(
+                    format!(
+                        "📨 {}",
+                        t(
+                            &format!("notifications.{}", mode.as_ref()),
+                            preference.language
+                        )
+                    ),
+                    notifications.delivery_mode == mode,
+                ),
+                InlineKeyboardButtonKind::CallbackData(format!("notification_mode_{}", mode.as_ref())),
+            )
+        })
+        .collect();
+
+    let mut quiet_hours = vec![InlineKeyboardButton::new(
+        checked(
+            format!(
+                "🌙 {}",
+                t("notifications.no_quiet_hours", preference.language)
+            ),
+            notifications.quiet_hours().is_none(),
+        ),
+        InlineKeyboardButtonKind::CallbackData("notification_quiet_off".to_string()),
+    )];
+    quiet_hours.extend(QUIET_HOURS_PRESETS.into_iter().map(|(from, to)| {
+        let is_checked = notifications
+            .quiet_hours()
+            .is_some_and(|it| it.from == from && it.to == to);
+
+        InlineKeyboardButton::new(
+            checked(format!("🌙 {from:02}:00-{to:02}:00"), is_checked),
+            InlineKeyboardButtonKind::CallbackData(format!("notification_quiet_{from}_{to}")),
+        )
+    }));
+
+    InlineKeyboardMarkup::new(vec![
+        lead_time,
+        delivery_mode,
+        quiet_hours,
+        vec![InlineKeyboardButton::new(
+            format!("🔙 {}", t("settings.back", preference.language)),
+            InlineKeyboardButtonKind::CallbackData("notifications_back".to_string()),
+        )],
+    ])
+}
+
+/// Applies a `notification_*` callback action to the current notification settings.
+fn parse_notification_action(action: &str, current: NotificationPreference) -> Result<NotificationPreference> {
+    if let Some(lead_time) = action.strip_prefix("lead_") {
+        Ok(NotificationPreference::new(
+            LeadTime::from_str(lead_time)?,
+            current.delivery_mode,
+            current.quiet_hours(),
+        ))
+    } else if let Some(mode) = action.strip_prefix("mode_") {
+        Ok(NotificationPreference::new(
+            current.lead_time,
+            DeliveryMode::from_str(mode)?,
+            current.quiet_hours(),
+        ))
+    } else if action == "quiet_off" {
+        Ok(current.with_quiet_hours(None))
+    } else if let Some((from, to)) = action
+        .strip_prefix("quiet_")
+        .and_then(|hours| hours.split_once('_'))
+    {
+        let quiet_hours = QuietHours::new(from.parse()?, to.parse()?)?;
+
+        Ok(current.with_quiet_hours(Some(quiet_hours)))
+    } else {
+        Err(anyhow!("unknown notification action {action}"))
+    }
+}
+
 fn get_language_actions(preference: &ChatPreference, with_back_button: Option<bool>) -> InlineKeyboardMarkup {
     let english = if preference.language == Language::En {
         "✅ 🇺🇸 English"
@@ -114,16 +322,6 @@ fn get_language_actions(preference: &ChatPreference, with_back_button: Option<bo
     InlineKeyboardMarkup::new(languages)
 }
 
-async fn get_sqlx_database_client() -> Result<sqlx::PgPool> {
-    let database_url = dotenvy::var("POSTGRESQL_URL")?;
-    let pool = PgPoolOptions::new()
-        .max_connections(5)
-        .connect(&database_url)
-        .await?;
-
-    Ok(pool)
-}
-
 fn get_update_language_code(update: &Update) -> Language {
     let mut language_code = Language::En;
 
@@ -138,11 +336,7 @@ fn get_update_language_code(update: &Update) -> Language {
     language_code
 }
 
-async fn get_chat_preference<T>(
-    chat_preference_repository: &mut T,
-    update: &Update,
-    chat_id: i64,
-) -> Result<ChatPreference>
+async fn get_chat_preference<T>(chat_preference_repository: &T, update: &Update, chat_id: i64) -> Result<ChatPreference>
 where
     T: PreferencesRepository,
 {
@@ -153,10 +347,7 @@ where
     } else {
         let language_code = get_update_language_code(update);
 
-        let new_chat_preference = ChatPreference {
-            chat_id,
-            language: language_code,
-        };
+        let new_chat_preference = ChatPreference::new(chat_id, language_code);
         chat_preference_repository
             .insert(new_chat_preference)
             .await?;
@@ -174,20 +365,18 @@ where
     }
 }
 
-#[tracing::instrument(level = "info", skip(subscriptions, messages, preferences))]
-pub async fn handle_update<T, M>(
-    update: &Update,
-    subscriptions: SubscriptionsRepository<'_>,
-    messages: &mut M,
-    preferences: &mut T,
-) -> Result<()>
+#[tracing::instrument(level = "info", skip(storage, bot))]
+pub async fn handle_update<S>(update: &Update, storage: &S, bot: &Bot) -> Result<()>
 where
-    T: PreferencesRepository,
-    M: MessagesRepository,
+    S: Storage,
 {
     debug!(message = format!("{update:?}"), "received telegram update");
 
-    let bot = Bot::from_env().parse_mode(ParseMode::MarkdownV2);
+    let subscriptions = storage.subscriptions();
+    let messages = storage.messages();
+    let preferences = storage.preferences();
+
+    let bot = bot.clone().parse_mode(ParseMode::MarkdownV2);
 
     match &update.kind {
         UpdateKind::Message(message) => {
@@ -195,6 +384,11 @@ where
             let ChatId(chat_id_i64) = chat_id;
             let chat_preference = get_chat_preference(preferences, update, chat_id_i64).await?;
 
+            // The user is back after blocking the bot.
+            if !chat_preference.active {
+                preferences.update_active(chat_id_i64, true).await?;
+            }
+
             if let Some(text) = message.text() {
                 let mut message_type = MessageType::Text;
 
@@ -203,6 +397,61 @@ where
                     bot.send_message(chat_id, t("start", chat_preference.language))
                         .reply_markup(get_full_menu(&chat_preference))
                         .await?;
+                } else if text == "/forget" {
+                    message_type = MessageType::Command;
+                    bot.send_message(chat_id, t("forget.confirm", chat_preference.language))
+                        .reply_markup(get_forget_confirm_actions(&chat_preference))
+                        .await?;
+                } else if text == "/upcoming" || text == get_upcoming_action_text(&chat_preference) {
+                    message_type = MessageType::Command;
+
+                    let subscriptions = subscriptions.find_all_by_chat_id(chat_id_i64).await?;
+                    let schedule = if subscriptions.is_empty() {
+                        t("upcoming.no_subscriptions", chat_preference.language)
+                    } else {
+                        let today = chrono::Utc::now().date_naive();
+                        let outages = storage.outages().find_since(today).await?;
+
+                        get_upcoming_text(chat_preference.language, &subscriptions, &outages)
+                    };
+
+                    bot.send_message(chat_id, schedule).await?;
+                } else if text == "/calendar" {
+                    message_type = MessageType::Command;
+
+                    if subscriptions
+                        .find_all_by_chat_id(chat_id_i64)
+                        .await?
+                        .is_empty()
+                    {
+                        bot.send_message(
+                            chat_id,
+                            t("upcoming.no_subscriptions", chat_preference.language),
+                        )
+                        .await?;
+                    } else {
+                        let calendar = chat_calendar(storage, chat_id_i64, chat_preference.language).await?;
+
+                        let mut caption = t("calendar.caption", chat_preference.language);
+                        if let Some(url) = FeedConfig::from_env().and_then(|it| it.url(chat_id_i64)) {
+                            caption.push_str("\n\n");
+                            caption.push_str(
+                                &_t!(
+                                    "calendar.feed",
+                                    locale = chat_preference.language.as_ref(),
+                                    url = url
+                                )
+                                .escape_markdown(),
+                            );
+                        }
+
+                        bot.send_document(
+                            chat_id,
+                            InputFile::memory(calendar.into_bytes()).file_name("outages.ics"),
+                        )
+                        .caption(caption)
+                        .await?;
+                    }
                 } else if text == get_check_address_action_text(&chat_preference) {
                     message_type = MessageType::Command;
                     bot.send_message(chat_id, t("check_address_text", chat_preference.language))
@@ -213,22 +462,17 @@ where
                 } else if text == get_unsubscribe_action_text(&chat_preference) {
                     let subscriptions = subscriptions.find_all_by_chat_id(chat_id_i64).await?;
 
+                    message_type = MessageType::Command;
                     if !subscriptions.is_empty() {
-                        let mut addresses = String::new();
-
-                        for (index, subscription) in subscriptions.iter().enumerate() {
-                            let address = subscription.address.to_owned();
-                            addresses.push_str(&format!("\\[{}\\] {}\n", index, address));
-                        }
-
-                        message_type = MessageType::Command;
+                        bot.send_message(chat_id, t("unsubscribe.choose", chat_preference.language))
+                            .reply_markup(get_unsubscribe_actions(&subscriptions))
+                            .await?;
+                    } else {
                         bot.send_message(
                             chat_id,
-                            format!("Выберите адреса, которые хотите отписать:\n{}", addresses),
+                            t("unsubscribe.no_subscriptions", chat_preference.language),
                         )
                         .await?;
-                    } else {
-                        bot.send_message(chat_id, "У вас нет подписок").await?;
                     }
                 } else if text == get_my_addresses_action_text(&chat_preference) {
                     message_type = MessageType::Command;
@@ -266,7 +510,7 @@ where
                                 .await?;
                         } else if last_command_text == get_subscribe_action_text(&chat_preference) {
                             subscriptions
-                                .insert(NewSubscription {
+                                .append(NewSubscription {
                                     chat_id: chat_id_i64,
                                     address: text.translit().to_owned(),
                                 })
@@ -274,40 +518,6 @@ where
 
                             bot.send_message(chat_id, t("subscribed", chat_preference.language))
                                 .await?;
-                        } else if last_command_text == get_unsubscribe_action_text(&chat_preference) {
-                            let subs = subscriptions.find_all_by_chat_id(chat_id_i64).await?;
-
-                            let ids = text
-                                .to_owned()
-                                .replace(' ', ",")
-                                .split(',')
-                                .filter(|s| !s.is_empty())
-                                .map(|s| s.parse::<usize>())
-                                .filter_map(|s| s.ok())
-                                .collect::<Vec<usize>>();
-
-                            let ids_to_remove = ids
-                                .iter()
-                                .map(|index| subs[index.to_owned()].id)
-                                .map(i64::from)
-                                .collect::<Vec<i64>>();
-
-                            subscriptions.delete_by_ids(ids_to_remove).await?;
-
-                            let removed_addresses = ids
-                                .iter()
-                                .map(|index| subs[index.to_owned()].address.to_owned())
-                                .collect::<Vec<String>>()
-                                .join(",");
-
-                            bot.send_message(
-                                chat_id,
-                                format!(
-                                    "Вы отписались от уведомлений по адресам: {}",
-                                    removed_addresses
-                                ),
-                            )
-                            .await?;
                         } else {
                             bot.send_message(chat_id, "Неизвестная команда").await?;
                         }
@@ -390,8 +600,99 @@ where
                             .reply_markup(get_full_menu(&chat_preference))
                             .await?;
                     } else if data == "notification_settings" {
-                        bot.send_message(chat_id, "Not implemented yet").await?;
-                    } else if data == "languages_back" {
+                        bot.edit_message_text(
+                            chat_id,
+                            message.id,
+                            t("notifications.text", chat_preference.language),
+                        )
+                        .reply_markup(get_notification_actions(&chat_preference))
+                        .await?;
+                    } else if let Some(action) = data.strip_prefix("notification_") {
+                        let notifications = parse_notification_action(action, chat_preference.notifications)
+                            .with_context(|| format!("invalid notification settings command: {data}"))?;
+
+                        if notifications == chat_preference.notifications {
+                            return Ok(());
+                        }
+
+                        preferences
+                            .update_notifications(chat_id_i64, notifications)
+                            .await?;
+                        chat_preference = get_chat_preference(preferences, update, chat_id_i64).await?;
+
+                        bot.edit_message_text(
+                            chat_id,
+                            message.id,
+                            t("notifications.text", chat_preference.language),
+                        )
+                        .reply_markup(get_notification_actions(&chat_preference))
+                        .await?;
+                    } else if data == "forget_confirm" {
+                        forget_chat(storage, chat_id_i64).await?;
+
+                        bot.edit_message_text(
+                            chat_id,
+                            message.id,
+                            t("forget.done", chat_preference.language),
+                        )
+                        .await?;
+                    } else if data == "forget_cancel" {
+                        bot.edit_message_text(
+                            chat_id,
+                            message.id,
+                            t("forget.cancelled", chat_preference.language),
+                        )
+                        .await?;
+                    } else if data == "unsubscribe_cancel" {
+                        bot.edit_message_text(
+                            chat_id,
+                            message.id,
+                            t("unsubscribe.cancelled", chat_preference.language),
+                        )
+                        .await?;
+                    } else if let Some(id) = data.strip_prefix("unsubscribe_confirm_") {
+                        let text = match find_subscription(subscriptions, chat_id_i64, id).await? {
+                            Some(subscription) => {
+                                subscriptions.delete(chat_id_i64, subscription.id).await?;
+
+                                _t!(
+                                    "unsubscribe.done",
+                                    locale = chat_preference.language.as_ref(),
+                                    address = subscription.address
+                                )
+                                .escape_markdown()
+                            }
+                            None => t("unsubscribe.not_found", chat_preference.language),
+                        };
+
+                        bot.edit_message_text(chat_id, message.id, text).await?;
+                    } else if let Some(id) = data.strip_prefix("unsubscribe_") {
+                        match find_subscription(subscriptions, chat_id_i64, id).await? {
+                            Some(subscription) => {
+                                let text = _t!(
+                                    "unsubscribe.confirm",
+                                    locale = chat_preference.language.as_ref(),
+                                    address = subscription.address
+                                )
+                                .escape_markdown();
+
+                                bot.edit_message_text(chat_id, message.id, text)
+                                    .reply_markup(get_unsubscribe_confirm_actions(
+                                        &chat_preference,
+                                        &subscription,
+                                    ))
+                                    .await?;
+                            }
+                            None => {
+                                bot.edit_message_text(
+                                    chat_id,
+                                    message.id,
+                                    t("unsubscribe.not_found", chat_preference.language),
+                                )
+                                .await?;
+                            }
+                        }
+                    } else if data == "languages_back" || data == "notifications_back" {
                         bot.edit_message_text(
                             chat_id,
                             message.id,
@@ -409,74 +710,303 @@ where
     Ok(())
 }
 
-#[allow(dead_code)]
-async fn send_message(chat_id: i64, message: &str) -> Result<()> {
-    let bot = Bot::from_env().parse_mode(ParseMode::MarkdownV2);
-
-    bot.send_message(ChatId(chat_id), escape_markdown(message))
+/// Warns the chats subscribed to the addresses about an outage on `date`,
+/// `now` is the local time. Chats which don't want the warning yet are
+/// reported as deferred, digests get a single message. Chats which turn out
+/// to be unreachable are marked as inactive and are not notified anymore.
+pub async fn notify_addresses<S>(
+    storage: &S,
+    bot: &Bot,
+    date: NaiveDate,
+    addresses: Vec<String>,
+    now: NaiveDateTime,
+) -> Result<DeliveryStats>
+where
+    S: Storage,
+{
+    let subscriptions = storage
+        .subscriptions()
+        .find_all_by_addresses(addresses)
         .await?;
 
-    Ok(())
+    let mut addresses_by_chat = BTreeMap::<i64, Vec<String>>::new();
+    for subscription in subscriptions {
+        addresses_by_chat
+            .entry(subscription.chat_id)
+            .or_default()
+            .push(subscription.address);
+    }
+
+    let mut outgoing = vec![];
+    let mut deferred = vec![];
+    for (chat_id, addresses) in addresses_by_chat {
+        let Some(chat_preference) = storage
+            .preferences()
+            .find_one(chat_id)
+            .await?
+            .filter(|it| it.active)
+        else {
+            continue;
+        };
+
+        match chat_preference.notifications.send_at(date, now) {
+            SendAt::Now => {}
+            SendAt::Later => {
+                deferred.push(chat_id);
+                continue;
+            }
+            SendAt::Never => continue,
+        }
+
+        let texts = addresses.into_iter().map(|address| {
+            _t!(
+                "shutdown_warning",
+                locale = chat_preference.language.as_ref(),
+                address = address
+            )
+            .escape_markdown()
+        });
+
+        if chat_preference.notifications.delivery_mode == DeliveryMode::Digest {
+            outgoing.push(Outgoing {
+                chat_id,
+                text: texts.collect::<Vec<_>>().join("\n"),
+            });
+        } else {
+            outgoing.extend(texts.map(|text| Outgoing { chat_id, text }));
+        }
+    }
+
+    let stats = DeliveryStats {
+        deferred,
+        ..deliver(bot, outgoing, DeliveryLimits::default()).await
+    };
+
+    for chat_id in &stats.unreachable {
+        storage.preferences().update_active(*chat_id, false).await?;
+    }
+
+    info!(%stats, "notifications delivered");
+
+    Ok(stats)
 }
 
-pub async fn notify_addresses(addresses: Vec<String>) -> Result<()> {
-    let sqlx_database_client = get_sqlx_database_client().await?;
+/// What happened to the queued changes in a run of [`notify_changes`].
+#[derive(Debug, Default)]
+pub struct ChangesDelivery {
+    pub stats: DeliveryStats,
+    /// Chats which have been told about a change, by the change id.
+    pub notified: BTreeMap<String, Vec<i64>>,
+    /// Changes some chat is still waiting for, because of its preferences or
+    /// a message which failed.
+    pub waiting: BTreeSet<String>,
+}
 
-    let subscription_repository = SubscriptionsRepository::new(&sqlx_database_client);
+/// Tells the chats subscribed to the affected addresses how the published
+/// schedule has changed, with a single message per chat. Chats already told
+/// about a change are skipped, changes the chat doesn't want yet are left out
+/// and the chat is reported as deferred.
+pub async fn notify_changes<S>(
+    storage: &S,
+    bot: &Bot,
+    changes: &[PendingChange],
+    now: NaiveDateTime,
+) -> Result<ChangesDelivery>
+where
+    S: Storage,
+{
+    let mut delivery = ChangesDelivery::default();
+    if changes.is_empty() {
+        return Ok(delivery);
+    }
 
-    let subscriptions = subscription_repository
-        .find_all_by_addresses(addresses)
-        .await?;
+    let subscriptions = storage.subscriptions().find_all().await?;
+
+    let mut changes_by_chat = BTreeMap::<i64, Vec<(&str, &PendingChange)>>::new();
+    for subscription in &subscriptions {
+        for pending in changes
+            .iter()
+            .filter(|it| !it.notified.contains(&subscription.chat_id) && it.change.affects(&subscription.address))
+        {
+            let chat_changes = changes_by_chat.entry(subscription.chat_id).or_default();
+            // A chat subscribed to several buildings of the street is told once.
+            if chat_changes.iter().all(|(_, it)| it.id != pending.id) {
+                chat_changes.push((&subscription.address, pending));
+            }
+        }
+    }
 
-    for subscription in subscriptions {
-        let chat_id = subscription.chat_id;
-        let chat_preference_repository = PgChatPreference::new(&sqlx_database_client);
-        let chat_preference = chat_preference_repository.find_one(chat_id).await?;
+    let mut outgoing = vec![];
+    let mut deferred = vec![];
+    let mut sent = BTreeMap::<i64, BTreeSet<&str>>::new();
+    for (chat_id, changes) in changes_by_chat {
+        let Some(chat_preference) = storage
+            .preferences()
+            .find_one(chat_id)
+            .await?
+            .filter(|it| it.active)
+        else {
+            continue;
+        };
 
-        if let Some(chat_preference) = chat_preference {
-            send_message(
-                chat_id,
-                _t!(
-                    "shutdown_warning",
-                    locale = chat_preference.language.as_ref(),
-                    address = subscription.address
-                )
-                .escape_markdown()
-                .as_str(),
-            )
-            .await?;
+        let mut text = t("schedule_changed.title", chat_preference.language);
+        let mut is_deferred = false;
+        let mut ids = BTreeSet::new();
+        for (address, pending) in changes {
+            let change = &pending.change;
+            match chat_preference
+                .notifications
+                .send_at(change.place().date, now)
+            {
+                SendAt::Now => {
+                    text.push('\n');
+                    text.push_str(&get_change_text(chat_preference.language, address, change).escape_markdown());
+                    ids.insert(pending.id.as_str());
+                }
+                SendAt::Later => {
+                    is_deferred = true;
+                    delivery.waiting.insert(pending.id.clone());
+                }
+                SendAt::Never => {}
+            }
+        }
+
+        if is_deferred {
+            deferred.push(chat_id);
+        }
+        if !ids.is_empty() {
+            outgoing.push(Outgoing { chat_id, text });
+            sent.insert(chat_id, ids);
         }
     }
 
-    Ok(())
+    let stats = DeliveryStats {
+        deferred,
+        ..deliver(bot, outgoing, DeliveryLimits::default()).await
+    };
+
+    for (chat_id, ids) in sent {
+        if stats.delivered.contains(&chat_id) {
+            for id in ids {
+                delivery
+                    .notified
+                    .entry(id.to_owned())
+                    .or_default()
+                    .push(chat_id);
+            }
+        } else if !stats.unreachable.contains(&chat_id) {
+            delivery
+                .waiting
+                .extend(ids.into_iter().map(ToOwned::to_owned));
+        }
+    }
+
+    for chat_id in &stats.unreachable {
+        storage.preferences().update_active(*chat_id, false).await?;
+    }
+
+    info!(%stats, "schedule changes delivered");
+    delivery.stats = stats;
+
+    Ok(delivery)
+}
+
+fn get_change_text(language: Language, address: &str, change: &Change) -> String {
+    let locale = language.as_ref();
+    let date = change.place().date.format("%d.%m.%Y").to_string();
+
+    match change {
+        Change::Added {
+            time, buildings, ..
+        } if buildings.is_empty() => _t!(
+            "schedule_changed.added",
+            locale = locale,
+            date = date,
+            address = address,
+            time = time
+        ),
+        Change::Added {
+            time, buildings, ..
+        } => _t!(
+            "schedule_changed.added_buildings",
+            locale = locale,
+            date = date,
+            address = address,
+            time = time,
+            buildings = buildings.join(", ")
+        ),
+        Change::Removed {
+            time, buildings, ..
+        } if buildings.is_empty() => _t!(
+            "schedule_changed.cancelled",
+            locale = locale,
+            date = date,
+            address = address,
+            time = time
+        ),
+        Change::Removed {
+            time, buildings, ..
+        } => _t!(
+            "schedule_changed.cancelled_buildings",
+            locale = locale,
+            date = date,
+            address = address,
+            time = time,
+            buildings = buildings.join(", ")
+        ),
+        Change::Modified {
+            from,
+            to,
+            buildings,
+            ..
+        } if buildings.is_empty() => _t!(
+            "schedule_changed.moved",
+            locale = locale,
+            date = date,
+            address = address,
+            from = from,
+            to = to
+        ),
+        Change::Modified {
+            from,
+            to,
+            buildings,
+            ..
+        } => _t!(
+            "schedule_changed.moved_buildings",
+            locale = locale,
+            date = date,
+            address = address,
+            from = from,
+            to = to,
+            buildings = buildings.join(", ")
+        ),
+    }
+    .to_string()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::outages::NewOutage;
+    use crate::preferences::DIGEST_HOUR;
+    use crate::storage::InMemoryStorage;
     use crate::test;
+    use electricity::diff::Place;
 
     #[tokio::test]
     async fn test_get_existing_chat_preference() {
-        let mut repository = test::preferences::TestChatPreference::new();
+        let repository = test::preferences::TestChatPreference::new();
 
         repository.set_chat_preferences(vec![
-            ChatPreference {
-                chat_id: 4,
-                language: Language::En,
-            },
-            ChatPreference {
-                chat_id: 5,
-                language: Language::Ru,
-            },
+            ChatPreference::new(4, Language::En),
+            ChatPreference::new(5, Language::Ru),
         ]);
 
         let update_mock = test::mock::BotUpdateMock::new();
         let update = update_mock.get_update();
 
-        let found_chat_preference = get_chat_preference(&mut repository, &update, 5)
-            .await
-            .unwrap();
+        let found_chat_preference = get_chat_preference(&repository, &update, 5).await.unwrap();
 
         assert_eq!(found_chat_preference.chat_id, 5);
         assert_eq!(found_chat_preference.language, Language::Ru);
@@ -484,17 +1014,500 @@ mod tests {
 
     #[tokio::test]
     async fn test_get_created_chat_preference() {
-        let mut repository = test::preferences::TestChatPreference::new();
+        let repository = test::preferences::TestChatPreference::new();
 
         let mut update_mock = test::mock::BotUpdateMock::new();
         update_mock.set_user_language(Language::Rs);
         let update = update_mock.get_update();
 
-        let found_chat_preference = get_chat_preference(&mut repository, &update, 5)
-            .await
-            .unwrap();
+        let found_chat_preference = get_chat_preference(&repository, &update, 5).await.unwrap();
 
         assert_eq!(found_chat_preference.chat_id, 5);
         assert_eq!(found_chat_preference.language, Language::Rs);
     }
+
+    #[test]
+    fn test_parse_notification_action() {
+        let current = NotificationPreference::default();
+
+        let res = parse_notification_action("lead_all_days", current).unwrap();
+        assert_eq!(res.lead_time, LeadTime::AllDays);
+
+        let res = parse_notification_action("mode_digest", current).unwrap();
+        assert_eq!(res.delivery_mode, DeliveryMode::Digest);
+
+        let res = parse_notification_action("quiet_22_7", current).unwrap();
+        assert_eq!(res.quiet_hours(), Some(QuietHours::new(22, 7).unwrap()));
+
+        let res = parse_notification_action("quiet_off", res).unwrap();
+        assert_eq!(res.quiet_hours(), None);
+
+        assert!(parse_notification_action("quiet_25_7", current).is_err());
+        assert!(parse_notification_action("unknown", current).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_update_notification_preference() {
+        let repository = test::preferences::TestChatPreference::new();
+        repository.set_chat_preferences(vec![ChatPreference::new(5, Language::En)]);
+
+        let notifications = parse_notification_action("mode_digest", NotificationPreference::default()).unwrap();
+        repository
+            .update_notifications(5, notifications)
+            .await
+            .unwrap();
+
+        let chat_preference = repository.find_one(5).await.unwrap().unwrap();
+        assert_eq!(
+            chat_preference.notifications.delivery_mode,
+            DeliveryMode::Digest
+        );
+        assert_eq!(chat_preference.language, Language::En);
+    }
+
+    fn outage(date: NaiveDate, time: &str, street: &str) -> NewOutage {
+        NewOutage {
+            date,
+            time: time.to_string(),
+            region: "Palilula".to_string(),
+            settlement: None,
+            street: street.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_get_upcoming_text() {
+        let subscription = |id, address: &str| Subscription {
+            id,
+            chat_id: 5,
+            address: address.to_string(),
+        };
+        let subscriptions = vec![
+            subscription(1, "kneza miloša 5"),
+            subscription(2, "takovska 10"),
+        ];
+        let monday = NaiveDate::from_ymd_opt(2023, 11, 20).unwrap();
+        let tuesday = NaiveDate::from_ymd_opt(2023, 11, 21).unwrap();
+        let outages = [
+            outage(tuesday, "09:00 - 14:00", "Таковска"),
+            outage(monday, "08:00 - 16:00", "kneza miloša"),
+            outage(monday, "08:00 - 16:00", "takovska"),
+            // The parser can store the same row more than once.
+            outage(monday, "08:00 - 16:00", "takovska"),
+            outage(monday, "10:00 - 12:00", "bulevar oslobođenja"),
+        ]
+        .into_iter()
+        .enumerate()
+        .map(|(id, it)| Outage {
+            id: id.to_string(),
+            date: it.date,
+            time: it.time,
+            region: it.region,
+            settlement: it.settlement,
+            street: it.street,
+        })
+        .collect::<Vec<_>>();
+
+        let text = get_upcoming_text(Language::En, &subscriptions, &outages);
+
+        assert_eq!(
+            text,
+            format!(
+                "{}\n\n*20\\.11\\.2023*\n🕘 08:00 \\- 16:00\n    • kneza miloša 5\n    • takovska 10\n\n*21\\.11\\.2023*\n🕘 09:00 \\- 14:00\n    • takovska 10",
+                t("upcoming.title", Language::En)
+            )
+        );
+
+        assert_eq!(
+            get_upcoming_text(Language::En, &subscriptions, &outages[4..]),
+            t("upcoming.nothing", Language::En)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_upcoming() {
+        use test::mock::BotUpdateMock;
+
+        let conversation = Conversation::start().await;
+        let storage = &conversation.storage;
+        let upcoming = get_upcoming_action_text(&ChatPreference::new(5, Language::En));
+
+        let calls = conversation.send(BotUpdateMock::text("/upcoming")).await;
+        assert_eq!(
+            calls[0].text(),
+            t("upcoming.no_subscriptions", Language::En)
+        );
+
+        storage
+            .subscriptions()
+            .append(NewSubscription {
+                chat_id: 5,
+                address: "kneza miloša 5".to_string(),
+            })
+            .await
+            .unwrap();
+        let today = chrono::Utc::now().date_naive();
+        for date in [today - chrono::Duration::days(1), today] {
+            storage
+                .outages()
+                .append(outage(date, "08:00 - 16:00", "kneza miloša"))
+                .await
+                .unwrap();
+        }
+
+        let calls = conversation.send(BotUpdateMock::text(&upcoming)).await;
+        assert_eq!(calls.len(), 1);
+        // Only the outages from today on are listed.
+        assert_eq!(
+            calls[0].text(),
+            format!(
+                "{}\n\n*{}*\n🕘 08:00 \\- 16:00\n    • kneza miloša 5",
+                t("upcoming.title", Language::En),
+                escape_markdown(&today.format("%d.%m.%Y").to_string())
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_calendar() {
+        use test::mock::BotUpdateMock;
+
+        let conversation = Conversation::start().await;
+        conversation
+            .storage
+            .subscriptions()
+            .append(NewSubscription {
+                chat_id: 5,
+                address: "kneza miloša 5".to_string(),
+            })
+            .await
+            .unwrap();
+
+        let calls = conversation.send(BotUpdateMock::text("/calendar")).await;
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].method, "senddocument");
+        assert_eq!(calls[0].body["chat_id"], 5);
+        assert_eq!(
+            calls[0].body["caption"],
+            t("calendar.caption", Language::En)
+        );
+
+        let (file_name, content) = calls[0].file("document").unwrap();
+        assert_eq!(file_name, "outages.ics");
+        assert!(content.starts_with("BEGIN:VCALENDAR\r\n"));
+    }
+
+    struct Conversation {
+        api: test::telegram::FakeBotApi,
+        bot: Bot,
+        storage: InMemoryStorage,
+    }
+
+    impl Conversation {
+        async fn start() -> Self {
+            let api = test::telegram::FakeBotApi::start().await;
+            let bot = api.bot();
+
+            Self {
+                api,
+                bot,
+                storage: InMemoryStorage::default(),
+            }
+        }
+
+        /// Handles the update and returns the requests the bot has made.
+        async fn send(&self, update: test::mock::BotUpdateMock) -> Vec<test::telegram::Call> {
+            handle_update(&update.get_update(), &self.storage, &self.bot)
+                .await
+                .unwrap();
+
+            self.api.take_calls()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_conversation() {
+        use test::mock::BotUpdateMock;
+
+        let conversation = Conversation::start().await;
+        let subscribe = get_subscribe_action_text(&ChatPreference::new(5, Language::En));
+
+        let calls = conversation.send(BotUpdateMock::text("/start")).await;
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].method, "sendmessage");
+        assert_eq!(calls[0].text(), t("start", Language::En));
+        assert!(calls[0].keyboard().contains(&subscribe));
+
+        let calls = conversation.send(BotUpdateMock::text(&subscribe)).await;
+        assert_eq!(calls[0].text(), "Введите ваш адрес");
+
+        let calls = conversation
+            .send(BotUpdateMock::text("Kneza Miloša 5"))
+            .await;
+        assert_eq!(calls[0].text(), t("subscribed", Language::En));
+
+        // Addresses are stored transliterated and lowercase.
+        let address = "Kneza Miloša 5".translit();
+        let calls = conversation
+            .send(BotUpdateMock::text("📝 My addresses"))
+            .await;
+        assert!(calls[0].text().contains(&address));
+
+        let calls = conversation
+            .send(BotUpdateMock::text("🔕 Unsubscribe"))
+            .await;
+        assert_eq!(calls[0].text(), t("unsubscribe.choose", Language::En));
+        assert_eq!(calls[0].callback_data(), vec!["unsubscribe_1"]);
+
+        let calls = conversation
+            .send(BotUpdateMock::callback("unsubscribe_1"))
+            .await;
+        assert_eq!(calls[0].method, "editmessagetext");
+        assert_eq!(
+            calls[0].callback_data(),
+            vec!["unsubscribe_confirm_1", "unsubscribe_cancel"]
+        );
+
+        let calls = conversation
+            .send(BotUpdateMock::callback("unsubscribe_confirm_1"))
+            .await;
+        assert!(calls[0].text().contains(&address));
+        assert!(conversation
+            .storage
+            .subscriptions()
+            .find_all_by_chat_id(5)
+            .await
+            .unwrap()
+            .is_empty());
+
+        let calls = conversation
+            .send(BotUpdateMock::callback("change_language"))
+            .await;
+        assert!(calls[0]
+            .callback_data()
+            .contains(&"change_language_ru".to_string()));
+
+        let calls = conversation
+            .send(BotUpdateMock::callback("change_language_ru"))
+            .await;
+        assert_eq!(calls.len(), 2);
+        assert_eq!(
+            calls[1].text(),
+            t("settings.language_changed", Language::Ru)
+        );
+
+        let calls = conversation.send(BotUpdateMock::text("/start")).await;
+        assert_eq!(calls[0].text(), t("start", Language::Ru));
+    }
+
+    #[tokio::test]
+    async fn test_notify_addresses() {
+        let conversation = Conversation::start().await;
+        let storage = &conversation.storage;
+        storage
+            .preferences()
+            .insert(ChatPreference::new(5, Language::En))
+            .await
+            .unwrap();
+        storage
+            .subscriptions()
+            .append(NewSubscription {
+                chat_id: 5,
+                address: "Kneza Miloša 5".to_string(),
+            })
+            .await
+            .unwrap();
+
+        let stats = notify_addresses(
+            storage,
+            &conversation.bot,
+            monday(),
+            vec!["Kneza Miloša 5".to_string()],
+            monday().and_hms_opt(12, 0, 0).unwrap(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(stats.sent, 1);
+        let calls = conversation.api.take_calls();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].body["chat_id"], 5);
+        assert!(calls[0].text().contains("Kneza Miloša 5"));
+    }
+
+    #[tokio::test]
+    async fn test_notify_addresses_applies_preferences() {
+        let conversation = Conversation::start().await;
+        let storage = &conversation.storage;
+        let quiet = NotificationPreference::default().with_quiet_hours(Some(QuietHours::new(22, 7).unwrap()));
+        let digest = NotificationPreference::new(LeadTime::OneDay, DeliveryMode::Digest, None);
+        for (chat_id, notifications) in [(5, quiet), (6, digest)] {
+            storage
+                .preferences()
+                .insert(ChatPreference::new(chat_id, Language::En))
+                .await
+                .unwrap();
+            storage
+                .preferences()
+                .update_notifications(chat_id, notifications)
+                .await
+                .unwrap();
+            for address in ["Kneza Miloša 5", "Takovska 2"] {
+                storage
+                    .subscriptions()
+                    .append(NewSubscription {
+                        chat_id,
+                        address: address.to_string(),
+                    })
+                    .await
+                    .unwrap();
+            }
+        }
+        let notify = |now: NaiveDateTime| {
+            notify_addresses(
+                storage,
+                &conversation.bot,
+                monday(),
+                vec!["Kneza Miloša 5".to_string(), "Takovska 2".to_string()],
+                now,
+            )
+        };
+
+        // The quiet chat is not messaged at night, the digest waits for its hour.
+        let stats = notify(monday().and_hms_opt(23, 0, 0).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(stats.sent, 0);
+        assert_eq!(stats.deferred, vec![5, 6]);
+        assert!(conversation.api.take_calls().is_empty());
+
+        let stats = notify(monday().and_hms_opt(DIGEST_HOUR, 0, 0).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(stats.sent, 3);
+        assert!(stats.deferred.is_empty());
+        let calls = conversation.api.take_calls();
+        let digest = calls
+            .iter()
+            .find(|it| it.body["chat_id"] == 6)
+            .unwrap()
+            .text();
+        assert!(digest.contains("Kneza Miloša 5") && digest.contains("Takovska 2"));
+    }
+
+    fn monday() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2023, 11, 20).unwrap()
+    }
+
+    fn change(id: &str, street: &str, change: impl FnOnce(Place) -> Change) -> PendingChange {
+        PendingChange {
+            id: id.to_string(),
+            change: change(Place {
+                date: NaiveDate::from_ymd_opt(2023, 11, 20).unwrap(),
+                region: "Palilula".to_string(),
+                settlement: None,
+                street: street.to_string(),
+            }),
+            notified: BTreeSet::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_notify_changes() {
+        let conversation = Conversation::start().await;
+        let storage = &conversation.storage;
+        for (chat_id, address) in [
+            (5, "Kneza Miloša 5"),
+            (5, "Takovska 2"),
+            (5, "Takovska 6"),
+            (6, "Takovska 10"),
+            (7, "Cara Dušana 1"),
+            (8, "Takovska 4"),
+            (9, "Cara Dušana 7"),
+        ] {
+            storage
+                .preferences()
+                .insert(ChatPreference::new(chat_id, Language::En))
+                .await
+                .unwrap();
+            storage
+                .subscriptions()
+                .append(NewSubscription {
+                    chat_id,
+                    address: address.to_string(),
+                })
+                .await
+                .unwrap();
+        }
+        let quiet = NotificationPreference::default().with_quiet_hours(Some(QuietHours::new(10, 14).unwrap()));
+        storage
+            .preferences()
+            .update_notifications(8, quiet)
+            .await
+            .unwrap();
+
+        let changes = vec![
+            change("moved", "kneza miloša", |place| Change::Modified {
+                place,
+                from: "09:00 - 13:00".to_string(),
+                to: "10:00 - 15:00".to_string(),
+                buildings: vec![],
+            }),
+            PendingChange {
+                notified: BTreeSet::from([6]),
+                ..change("cancelled", "takovska", |place| Change::Removed {
+                    place,
+                    time: "08:00 - 16:00".to_string(),
+                    buildings: vec![],
+                })
+            },
+            change("buildings", "cara dušana", |place| Change::Removed {
+                place,
+                time: "08:00 - 16:00".to_string(),
+                buildings: vec!["1".to_string(), "3".to_string()],
+            }),
+        ];
+
+        let now = NaiveDate::from_ymd_opt(2023, 11, 19)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap();
+        let delivery = notify_changes(storage, &conversation.bot, &changes, now)
+            .await
+            .unwrap();
+
+        assert_eq!(delivery.stats.sent, 2);
+        assert_eq!(delivery.stats.deferred, vec![8]);
+        assert_eq!(
+            delivery.notified,
+            BTreeMap::from([
+                ("buildings".to_string(), vec![7]),
+                ("cancelled".to_string(), vec![5]),
+                ("moved".to_string(), vec![5]),
+            ])
+        );
+        assert_eq!(delivery.waiting, BTreeSet::from(["cancelled".to_string()]));
+
+        let calls = conversation.api.take_calls();
+        let text_of = |chat_id: i64| {
+            calls
+                .iter()
+                .find(|it| it.body["chat_id"] == chat_id)
+                .map(|it| it.text().to_string())
+        };
+        assert_eq!(
+            text_of(5).unwrap(),
+            "The outage schedule has changed:\n\
+             20\\.11\\.2023, Kneza Miloša 5: the outage is moved from 09:00 \\- 13:00 to 10:00 \\- 15:00\n\
+             20\\.11\\.2023, Takovska 2: the outage at 08:00 \\- 16:00 is cancelled"
+        );
+        assert_eq!(
+            text_of(7).unwrap(),
+            "The outage schedule has changed:\n\
+             20\\.11\\.2023, Cara Dušana 1: the outage at 08:00 \\- 16:00 is cancelled for buildings 1, 3"
+        );
+        assert_eq!(text_of(6), None);
+        assert_eq!(text_of(8), None);
+        assert_eq!(text_of(9), None);
+    }
 }
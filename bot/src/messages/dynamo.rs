@@ -1,18 +1,40 @@
-use super::models::{Message, MessageType};
+use super::models::{Message, MessageType, RetentionPolicy};
 use super::repository::Repository;
 use anyhow::anyhow;
 use anyhow::Result;
 use aws_sdk_dynamodb::types::AttributeValue;
 use aws_sdk_dynamodb::Client;
+use electricity::db::table_spec::{KeySpec, TableSpec};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 const TABLE_NAME: &str = "messages";
 const ID_FIELD: &str = "id";
 const TEXT_FIELD: &str = "text";
 const MESSAGE_TYPE: &str = "message_type";
+/// Expiration time of an item (epoch seconds), DynamoDB removes expired items itself.
+const EXPIRES_AT_FIELD: &str = "expires_at";
 
-impl Repository for Client {
+pub fn tables() -> Vec<TableSpec> {
+    vec![TableSpec::new(TABLE_NAME, KeySpec::s(ID_FIELD)).with_ttl(EXPIRES_AT_FIELD)]
+}
+
+/// Messages in DynamoDB, every item expires according to the policy.
+#[derive(Clone)]
+pub struct DynamoMessages {
+    client: Client,
+    policy: RetentionPolicy,
+}
+
+impl DynamoMessages {
+    pub fn new(client: Client, policy: RetentionPolicy) -> Self {
+        Self { client, policy }
+    }
+}
+
+impl Repository for DynamoMessages {
     async fn append(&self, message: Message) -> Result<()> {
         let request = self
+            .client
             .put_item()
             .table_name(TABLE_NAME)
             .item(ID_FIELD, make_id(message.chat_id, message.message_type))
@@ -20,6 +42,10 @@ impl Repository for Client {
             .item(
                 MESSAGE_TYPE,
                 AttributeValue::S(message.message_type.as_ref().to_string()),
+            )
+            .item(
+                EXPIRES_AT_FIELD,
+                AttributeValue::N(self.policy.expires_at(now()?).to_string()),
             );
 
         let _ = request.send().await?;
@@ -29,6 +55,7 @@ impl Repository for Client {
 
     async fn find_last(&self, chat_id: i64, message_type: MessageType) -> Result<Option<Message>> {
         let request = self
+            .client
             .get_item()
             .table_name(TABLE_NAME)
             .key(ID_FIELD, make_id(chat_id, message_type));
@@ -49,8 +76,68 @@ impl Repository for Client {
             Ok(None)
         }
     }
-}
 
+    async fn delete_all_by_chat_id(&self, chat_id: i64) -> Result<()> {
+        // Only the last message of each type is kept for a chat.
+        for message_type in [MessageType::Text, MessageType::Command] {
+            self.client
+                .delete_item()
+                .table_name(TABLE_NAME)
+                .key(ID_FIELD, make_id(chat_id, message_type))
+                .send()
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    // The history is capped by design and expired items are removed by
+    // DynamoDB TTL, so only the items written before TTL was introduced
+    // are given an expiration time.
+    async fn purge(&self, policy: &RetentionPolicy) -> Result<u64> {
+        let expires_at = AttributeValue::N(policy.expires_at(now()?).to_string());
+        let mut updated = 0;
+        let mut start_key = None;
+
+        loop {
+            let response = self
+                .client
+                .scan()
+                .table_name(TABLE_NAME)
+                .filter_expression(format!("attribute_not_exists({EXPIRES_AT_FIELD})"))
+                .set_exclusive_start_key(start_key)
+                .send()
+                .await?;
+
+            for item in response.items() {
+                let Some(id) = item.get(ID_FIELD) else {
+                    continue;
+                };
+
+                self.client
+                    .update_item()
+                    .table_name(TABLE_NAME)
+                    .key(ID_FIELD, id.clone())
+                    .update_expression(format!("SET {EXPIRES_AT_FIELD} = :expires_at"))
+                    .expression_attribute_values(":expires_at", expires_at.clone())
+                    .send()
+                    .await?;
+                updated += 1;
+            }
+
+            start_key = response.last_evaluated_key;
+            if start_key.is_none() {
+                break;
+            }
+        }
+
+        Ok(updated)
+    }
+}
 fn make_id(chat_id: i64, message_type: MessageType) -> AttributeValue {
     AttributeValue::S(format!("{}-{}", chat_id, message_type))
 }
+
+fn now() -> Result<u64> {
+    Ok(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs())
+}
@@ -0,0 +1,167 @@
+use super::models::{Message, MessageType, RetentionPolicy};
+use super::repository::Repository;
+use anyhow::Result;
+use std::sync::RwLock;
+use std::time::{Duration, SystemTime};
+
+struct StoredMessage {
+    message: Message,
+    created_at: SystemTime,
+}
+
+/// Message history kept in memory, trimmed the same way as in Postgres.
+#[derive(Default)]
+pub struct InMemoryMessages {
+    messages: RwLock<Vec<StoredMessage>>,
+    policy: RetentionPolicy,
+}
+
+impl InMemoryMessages {
+    pub fn new(policy: RetentionPolicy) -> Self {
+        Self {
+            messages: RwLock::default(),
+            policy,
+        }
+    }
+}
+
+impl Repository for InMemoryMessages {
+    async fn append(&self, message: Message) -> Result<()> {
+        let mut messages = self.messages.write().unwrap();
+        let chat_id = message.chat_id;
+
+        messages.push(StoredMessage {
+            message,
+            created_at: SystemTime::now(),
+        });
+        trim_history(&mut messages, chat_id, self.policy.max_per_chat);
+
+        Ok(())
+    }
+
+    async fn find_last(&self, chat_id: i64, message_type: MessageType) -> Result<Option<Message>> {
+        let messages = self.messages.read().unwrap();
+
+        Ok(messages
+            .iter()
+            .rev()
+            .map(|it| &it.message)
+            .find(|it| it.chat_id == chat_id && it.message_type == message_type)
+            .map(|it| Message {
+                chat_id: it.chat_id,
+                text: it.text.clone(),
+                message_type: it.message_type,
+            }))
+    }
+
+    async fn delete_all_by_chat_id(&self, chat_id: i64) -> Result<()> {
+        self.messages
+            .write()
+            .unwrap()
+            .retain(|it| it.message.chat_id != chat_id);
+
+        Ok(())
+    }
+
+    async fn purge(&self, policy: &RetentionPolicy) -> Result<u64> {
+        let mut messages = self.messages.write().unwrap();
+        let before = messages.len();
+
+        let max_age = Duration::from_secs(policy.expires_at(0));
+        let now = SystemTime::now();
+        messages.retain(|it| now.duration_since(it.created_at).unwrap_or_default() <= max_age);
+
+        let mut chat_ids = messages
+            .iter()
+            .map(|it| it.message.chat_id)
+            .collect::<Vec<_>>();
+        chat_ids.sort_unstable();
+        chat_ids.dedup();
+        for chat_id in chat_ids {
+            trim_history(&mut messages, chat_id, policy.max_per_chat);
+        }
+
+        Ok((before - messages.len()) as u64)
+    }
+}
+
+/// Keeps only the latest `limit` messages of the chat.
+fn trim_history(messages: &mut Vec<StoredMessage>, chat_id: i64, limit: u32) {
+    let count = messages
+        .iter()
+        .filter(|it| it.message.chat_id == chat_id)
+        .count();
+    let mut excess = count.saturating_sub(limit as usize);
+
+    messages.retain(|it| {
+        if excess > 0 && it.message.chat_id == chat_id {
+            excess -= 1;
+            false
+        } else {
+            true
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_append_trims_history_by_policy() {
+        let repository = InMemoryMessages::new(RetentionPolicy {
+            max_age_days: 1,
+            max_per_chat: 2,
+        });
+        for text in ["first", "second", "third"] {
+            repository
+                .append(Message {
+                    chat_id: 1,
+                    text: text.to_string(),
+                    message_type: MessageType::Text,
+                })
+                .await
+                .unwrap();
+        }
+
+        let texts = repository
+            .messages
+            .read()
+            .unwrap()
+            .iter()
+            .map(|it| it.message.text.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(texts, vec!["second", "third"]);
+    }
+
+    #[tokio::test]
+    async fn test_purge_trims_history() {
+        let repository = InMemoryMessages::default();
+        for text in ["first", "second", "third"] {
+            repository
+                .append(Message {
+                    chat_id: 1,
+                    text: text.to_string(),
+                    message_type: MessageType::Text,
+                })
+                .await
+                .unwrap();
+        }
+
+        let purged = repository
+            .purge(&RetentionPolicy {
+                max_age_days: 1,
+                max_per_chat: 1,
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(purged, 2);
+        let last = repository
+            .find_last(1, MessageType::Text)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(last.text, "third");
+    }
+}
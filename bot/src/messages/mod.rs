@@ -1,7 +1,11 @@
 mod dynamo;
+mod memory;
 mod models;
 mod pg;
 mod repository;
 
-pub(crate) use models::*;
-pub(crate) use repository::*;
+pub use dynamo::{tables as dynamo_tables, DynamoMessages};
+pub use memory::InMemoryMessages;
+pub use models::*;
+pub use pg::PgMessages;
+pub use repository::*;
@@ -2,7 +2,7 @@ use anyhow::anyhow;
 use std::fmt::{Display, Formatter};
 use std::str::FromStr;
 
-#[derive(sqlx::Type, Debug, Clone, Copy)]
+#[derive(sqlx::Type, Debug, Clone, Copy, PartialEq)]
 #[sqlx(type_name = "message_type", rename_all = "lowercase")]
 pub enum MessageType {
     Text,
@@ -43,3 +43,76 @@ pub struct Message {
     #[sqlx(rename = "type")]
     pub message_type: MessageType,
 }
+
+/// How long the messages of a chat are kept and how many of them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetentionPolicy {
+    pub max_age_days: u32,
+    pub max_per_chat: u32,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            max_age_days: 30,
+            max_per_chat: 20,
+        }
+    }
+}
+
+impl RetentionPolicy {
+    /// Reads the policy from `MESSAGES_RETENTION_DAYS` and `MESSAGES_HISTORY_LIMIT`
+    /// environment variables, missing values fall back to the defaults.
+    pub fn from_env() -> anyhow::Result<Self> {
+        let defaults = Self::default();
+
+        let max_age_days = match dotenvy::var("MESSAGES_RETENTION_DAYS") {
+            Ok(value) => parse_positive("MESSAGES_RETENTION_DAYS", &value)?,
+            Err(_) => defaults.max_age_days,
+        };
+        let max_per_chat = match dotenvy::var("MESSAGES_HISTORY_LIMIT") {
+            Ok(value) => parse_positive("MESSAGES_HISTORY_LIMIT", &value)?,
+            Err(_) => defaults.max_per_chat,
+        };
+
+        Ok(Self {
+            max_age_days,
+            max_per_chat,
+        })
+    }
+
+    /// Expiration time (epoch seconds) of a message written at `now` (epoch seconds).
+    pub fn expires_at(&self, now: u64) -> u64 {
+        now + u64::from(self.max_age_days) * 24 * 60 * 60
+    }
+}
+
+fn parse_positive(name: &str, value: &str) -> anyhow::Result<u32> {
+    match value.parse::<u32>() {
+        Ok(value) if value > 0 => Ok(value),
+        _ => Err(anyhow!("{name} must be a positive number, got [{value}]")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expires_at() {
+        let policy = RetentionPolicy {
+            max_age_days: 2,
+            max_per_chat: 10,
+        };
+
+        assert_eq!(policy.expires_at(1_000), 1_000 + 2 * 86_400);
+    }
+
+    #[test]
+    fn test_parse_positive() {
+        assert_eq!(parse_positive("LIMIT", "15").unwrap(), 15);
+        assert!(parse_positive("LIMIT", "0").is_err());
+        assert!(parse_positive("LIMIT", "-1").is_err());
+        assert!(parse_positive("LIMIT", "week").is_err());
+    }
+}
@@ -1,16 +1,41 @@
-use super::models::{Message, MessageType};
+use super::models::{Message, MessageType, RetentionPolicy};
 use super::repository::Repository;
 use sqlx::PgPool;
 
-impl Repository for PgPool {
+/// Messages in Postgres, the history of a chat is trimmed by the policy on
+/// every append.
+#[derive(Clone)]
+pub struct PgMessages {
+    pool: PgPool,
+    policy: RetentionPolicy,
+}
+
+impl PgMessages {
+    pub fn new(pool: PgPool, policy: RetentionPolicy) -> Self {
+        Self { pool, policy }
+    }
+}
+
+impl Repository for PgMessages {
     async fn append(&self, message: Message) -> anyhow::Result<()> {
+        let chat_id = message.chat_id;
+
         sqlx::query("INSERT INTO messages (chat_id, text, type) VALUES ($1, $2, $3)")
             .bind(message.chat_id)
             .bind(message.text)
             .bind(message.message_type)
-            .execute(self)
+            .execute(&self.pool)
             .await?;
 
+        sqlx::query(
+            "DELETE FROM messages WHERE chat_id = $1 AND id NOT IN \
+             (SELECT id FROM messages WHERE chat_id = $1 ORDER BY created_at DESC, id DESC LIMIT $2)",
+        )
+        .bind(chat_id)
+        .bind(i64::from(self.policy.max_per_chat))
+        .execute(&self.pool)
+        .await?;
+
         Ok(())
     }
 
@@ -20,9 +45,36 @@ impl Repository for PgPool {
         )
         .bind(chat_id)
         .bind(message_type)
-        .fetch_all(self)
+        .fetch_all(&self.pool)
         .await?;
 
         Ok(subscriptions.into_iter().next())
     }
+
+    async fn delete_all_by_chat_id(&self, chat_id: i64) -> anyhow::Result<()> {
+        sqlx::query("DELETE FROM messages WHERE chat_id = $1")
+            .bind(chat_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn purge(&self, policy: &RetentionPolicy) -> anyhow::Result<u64> {
+        let expired = sqlx::query("DELETE FROM messages WHERE created_at < NOW() - make_interval(days => $1)")
+            .bind(policy.max_age_days as i32)
+            .execute(&self.pool)
+            .await?;
+
+        let over_limit = sqlx::query(
+            "DELETE FROM messages WHERE id IN (SELECT id FROM \
+             (SELECT id, row_number() OVER (PARTITION BY chat_id ORDER BY created_at DESC, id DESC) AS position \
+             FROM messages) AS history WHERE position > $1)",
+        )
+        .bind(i64::from(policy.max_per_chat))
+        .execute(&self.pool)
+        .await?;
+
+        Ok(expired.rows_affected() + over_limit.rows_affected())
+    }
 }
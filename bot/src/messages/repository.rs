@@ -1,12 +1,17 @@
-use super::models::{Message, MessageType};
+use super::models::{Message, MessageType, RetentionPolicy};
 use anyhow::Result;
 use std::future::Future;
 
 pub trait Repository {
+    /// Appends a message, the chat's history is trimmed according to the
+    /// [`RetentionPolicy`] of the repository.
     fn append(&self, message: Message) -> impl Future<Output = Result<()>> + Send;
     fn find_last(
         &self,
         chat_id: i64,
         message_type: MessageType,
     ) -> impl Future<Output = Result<Option<Message>>> + Send;
+    fn delete_all_by_chat_id(&self, chat_id: i64) -> impl Future<Output = Result<()>> + Send;
+    /// Removes the messages outside of the policy, returns how many items were affected.
+    fn purge(&self, policy: &RetentionPolicy) -> impl Future<Output = Result<u64>> + Send;
 }
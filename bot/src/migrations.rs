@@ -0,0 +1,234 @@
+//! Postgres schema versioning. The SQL files from `bot/migrations` are
+//! embedded into the binary, so the schema a build expects is always known
+//! and can be compared against the one of the database.
+use anyhow::{bail, Result};
+use sqlx::migrate::{AppliedMigration, Migrate, Migration, Migrator};
+use sqlx::PgPool;
+use std::fmt::{Display, Formatter};
+
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MigrationState {
+    Applied,
+    Pending,
+    /// The migration was applied, but its SQL has changed since.
+    ChecksumMismatch,
+}
+
+impl Display for MigrationState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MigrationState::Applied => f.write_str("applied"),
+            MigrationState::Pending => f.write_str("pending"),
+            MigrationState::ChecksumMismatch => f.write_str("checksum mismatch"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MigrationStatus {
+    pub version: i64,
+    pub description: String,
+    pub state: MigrationState,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaStatus {
+    pub migrations: Vec<MigrationStatus>,
+    /// Versions applied to the database which this build does not know about,
+    /// i.e. the database is ahead and would need to go down to match the build.
+    pub unknown: Vec<i64>,
+    /// A migration which has failed half way.
+    pub dirty: Option<i64>,
+}
+
+impl SchemaStatus {
+    fn new(migrations: &[Migration], applied: &[AppliedMigration], dirty: Option<i64>) -> Self {
+        let statuses = migrations
+            .iter()
+            .filter(|it| !it.migration_type.is_down_migration())
+            .map(|migration| {
+                let state = match applied.iter().find(|it| it.version == migration.version) {
+                    Some(it) if it.checksum == migration.checksum => MigrationState::Applied,
+                    Some(_) => MigrationState::ChecksumMismatch,
+                    None => MigrationState::Pending,
+                };
+
+                MigrationStatus {
+                    version: migration.version,
+                    description: migration.description.to_string(),
+                    state,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let unknown = applied
+            .iter()
+            .map(|it| it.version)
+            .filter(|version| !statuses.iter().any(|it| it.version == *version))
+            .collect();
+
+        Self {
+            migrations: statuses,
+            unknown,
+            dirty,
+        }
+    }
+
+    pub fn is_up_to_date(&self) -> bool {
+        self.dirty.is_none()
+            && self.unknown.is_empty()
+            && self
+                .migrations
+                .iter()
+                .all(|it| it.state == MigrationState::Applied)
+    }
+}
+
+impl Display for SchemaStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for migration in &self.migrations {
+            writeln!(
+                f,
+                "{:>4} {:<40} {}",
+                migration.version, migration.description, migration.state
+            )?;
+        }
+        for version in &self.unknown {
+            writeln!(f, "{version:>4} {:<40} unknown to this build", "?")?;
+        }
+        if let Some(version) = self.dirty {
+            writeln!(f, "migration {version} is dirty (failed half way)")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Compares the embedded migrations with the ones applied to the database,
+/// the database is not modified.
+pub async fn status(pool: &PgPool) -> Result<SchemaStatus> {
+    let mut conn = pool.acquire().await?;
+
+    let table_exists: bool = sqlx::query_scalar("SELECT to_regclass('_sqlx_migrations') IS NOT NULL")
+        .fetch_one(&mut *conn)
+        .await?;
+
+    let (applied, dirty) = if table_exists {
+        (
+            conn.list_applied_migrations().await?,
+            conn.dirty_version().await?,
+        )
+    } else {
+        (vec![], None)
+    };
+
+    Ok(SchemaStatus::new(
+        MIGRATOR.migrations.as_ref(),
+        &applied,
+        dirty,
+    ))
+}
+
+/// Applies all pending migrations.
+pub async fn up(pool: &PgPool) -> Result<()> {
+    MIGRATOR.run(pool).await?;
+
+    Ok(())
+}
+
+/// Fails unless the database schema matches the embedded migrations exactly.
+pub async fn verify(pool: &PgPool) -> Result<()> {
+    let status = status(pool).await?;
+
+    if !status.is_up_to_date() {
+        bail!("the database schema does not match this build, run the `migrate` binary:\n{status}");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::migrate::MigrationType;
+    use std::borrow::Cow;
+
+    fn migration(version: i64, sql: &'static str) -> Migration {
+        Migration::new(
+            version,
+            Cow::Owned(format!("migration {version}")),
+            MigrationType::Simple,
+            Cow::Borrowed(sql),
+        )
+    }
+
+    fn applied(migration: &Migration) -> AppliedMigration {
+        AppliedMigration {
+            version: migration.version,
+            checksum: migration.checksum.clone(),
+        }
+    }
+
+    #[test]
+    fn test_embedded_migrations_are_ordered() {
+        let versions = MIGRATOR.iter().map(|it| it.version).collect::<Vec<_>>();
+
+        assert!(!versions.is_empty());
+        assert!(versions.windows(2).all(|it| it[0] < it[1]));
+    }
+
+    #[test]
+    fn test_schema_status_up_to_date() {
+        let migrations = vec![migration(1, "SELECT 1"), migration(2, "SELECT 2")];
+        let status = SchemaStatus::new(
+            &migrations,
+            &[applied(&migrations[0]), applied(&migrations[1])],
+            None,
+        );
+
+        assert!(status.is_up_to_date());
+    }
+
+    #[test]
+    fn test_schema_status_pending() {
+        let migrations = vec![migration(1, "SELECT 1"), migration(2, "SELECT 2")];
+        let status = SchemaStatus::new(&migrations, &[applied(&migrations[0])], None);
+
+        assert!(!status.is_up_to_date());
+        assert_eq!(status.migrations[1].state, MigrationState::Pending);
+    }
+
+    #[test]
+    fn test_schema_status_ahead_of_build() {
+        let migrations = vec![migration(1, "SELECT 1")];
+        let newer = migration(2, "SELECT 2");
+        let status = SchemaStatus::new(
+            &migrations,
+            &[applied(&migrations[0]), applied(&newer)],
+            None,
+        );
+
+        assert!(!status.is_up_to_date());
+        assert_eq!(status.unknown, vec![2]);
+    }
+
+    #[test]
+    fn test_schema_status_checksum_mismatch() {
+        let migrations = vec![migration(1, "SELECT 1")];
+        let changed = migration(1, "SELECT 11");
+        let status = SchemaStatus::new(&migrations, &[applied(&changed)], None);
+
+        assert!(!status.is_up_to_date());
+        assert_eq!(status.migrations[0].state, MigrationState::ChecksumMismatch);
+    }
+
+    #[test]
+    fn test_schema_status_dirty() {
+        let migrations = vec![migration(1, "SELECT 1")];
+        let status = SchemaStatus::new(&migrations, &[applied(&migrations[0])], Some(1));
+
+        assert!(!status.is_up_to_date());
+    }
+}
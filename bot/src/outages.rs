@@ -0,0 +1,2 @@
+//! The outages are shared with the API, see [`electricity::outages`].
+pub use electricity::outages::*;
@@ -1,8 +1,10 @@
 use super::repository::Repository;
-use crate::preferences::{ChatPreference, Language};
+use crate::preferences::{ChatPreference, DeliveryMode, Language, LeadTime, NotificationPreference, QuietHours};
 use anyhow::{anyhow, Result};
 use aws_sdk_dynamodb::types::AttributeValue;
 use aws_sdk_dynamodb::Client;
+use electricity::db::table_spec::{KeySpec, TableSpec};
+use std::collections::HashMap;
 use std::str::FromStr;
 
 const TABLE_NAME: &str = "chat_preferences";
@@ -11,8 +13,22 @@ const CHAT_ID_FIELD: &str = "chat_id";
 
 const LANGUAGE_FIELD: &str = "lang";
 
+const LEAD_TIME_FIELD: &str = "lead_time";
+
+const DELIVERY_MODE_FIELD: &str = "delivery_mode";
+
+const QUIET_FROM_FIELD: &str = "quiet_from";
+
+const QUIET_TO_FIELD: &str = "quiet_to";
+
+const ACTIVE_FIELD: &str = "active";
+
 const VALUE_EXPR: &str = ":value";
 
+pub fn tables() -> Vec<TableSpec> {
+    vec![TableSpec::new(TABLE_NAME, KeySpec::n(CHAT_ID_FIELD))]
+}
+
 impl Repository for Client {
     async fn insert(&self, value: ChatPreference) -> Result<()> {
         let request = self
@@ -22,7 +38,30 @@ impl Repository for Client {
             .item(
                 LANGUAGE_FIELD,
                 AttributeValue::S(value.language.as_ref().to_string()),
-            );
+            )
+            .item(
+                LEAD_TIME_FIELD,
+                AttributeValue::S(value.notifications.lead_time.as_ref().to_string()),
+            )
+            .item(
+                DELIVERY_MODE_FIELD,
+                AttributeValue::S(value.notifications.delivery_mode.as_ref().to_string()),
+            )
+            .item(ACTIVE_FIELD, AttributeValue::Bool(value.active));
+
+        let request = if let Some(quiet_hours) = value.notifications.quiet_hours() {
+            request
+                .item(
+                    QUIET_FROM_FIELD,
+                    AttributeValue::N(quiet_hours.from.to_string()),
+                )
+                .item(
+                    QUIET_TO_FIELD,
+                    AttributeValue::N(quiet_hours.to.to_string()),
+                )
+        } else {
+            request
+        };
 
         let _ = request.send().await?;
 
@@ -42,6 +81,9 @@ impl Repository for Client {
                 Ok(Some(ChatPreference {
                     chat_id,
                     language: Language::from_str(attr_value)?,
+                    notifications: notifications_from_item(&item)?,
+                    // Items stored before the flag was introduced belong to active chats.
+                    active: !matches!(item.get(ACTIVE_FIELD), Some(AttributeValue::Bool(false))),
                 }))
             } else {
                 Err(anyhow!("language field is missing for {chat_id}"))
@@ -63,4 +105,90 @@ impl Repository for Client {
 
         Ok(())
     }
+
+    async fn update_notifications(&self, chat_id: i64, notifications: NotificationPreference) -> Result<()> {
+        let mut update_expression = format!("SET {LEAD_TIME_FIELD}=:lead_time, {DELIVERY_MODE_FIELD}=:delivery_mode");
+        let request = self
+            .update_item()
+            .table_name(TABLE_NAME)
+            .key(CHAT_ID_FIELD, AttributeValue::N(chat_id.to_string()))
+            .expression_attribute_values(
+                ":lead_time",
+                AttributeValue::S(notifications.lead_time.as_ref().to_string()),
+            )
+            .expression_attribute_values(
+                ":delivery_mode",
+                AttributeValue::S(notifications.delivery_mode.as_ref().to_string()),
+            );
+
+        let request = if let Some(quiet_hours) = notifications.quiet_hours() {
+            update_expression.push_str(&format!(
+                ", {QUIET_FROM_FIELD}=:quiet_from, {QUIET_TO_FIELD}=:quiet_to"
+            ));
+            request
+                .expression_attribute_values(
+                    ":quiet_from",
+                    AttributeValue::N(quiet_hours.from.to_string()),
+                )
+                .expression_attribute_values(":quiet_to", AttributeValue::N(quiet_hours.to.to_string()))
+        } else {
+            update_expression.push_str(&format!(" REMOVE {QUIET_FROM_FIELD}, {QUIET_TO_FIELD}"));
+            request
+        };
+
+        let _ = request.update_expression(update_expression).send().await?;
+
+        Ok(())
+    }
+
+    async fn update_active(&self, chat_id: i64, active: bool) -> Result<()> {
+        let request = self
+            .update_item()
+            .table_name(TABLE_NAME)
+            .key(CHAT_ID_FIELD, AttributeValue::N(chat_id.to_string()))
+            .update_expression(format!("SET {ACTIVE_FIELD}={VALUE_EXPR}"))
+            .expression_attribute_values(VALUE_EXPR, AttributeValue::Bool(active));
+
+        let _ = request.send().await?;
+
+        Ok(())
+    }
+
+    async fn delete(&self, chat_id: i64) -> Result<()> {
+        let request = self
+            .delete_item()
+            .table_name(TABLE_NAME)
+            .key(CHAT_ID_FIELD, AttributeValue::N(chat_id.to_string()));
+
+        let _ = request.send().await?;
+
+        Ok(())
+    }
+}
+
+/// Reads notification settings of a chat, items stored before the settings
+/// were introduced fall back to the defaults.
+fn notifications_from_item(item: &HashMap<String, AttributeValue>) -> Result<NotificationPreference> {
+    let defaults = NotificationPreference::default();
+
+    let lead_time = match item.get(LEAD_TIME_FIELD) {
+        Some(AttributeValue::S(value)) => LeadTime::from_str(value)?,
+        _ => defaults.lead_time,
+    };
+    let delivery_mode = match item.get(DELIVERY_MODE_FIELD) {
+        Some(AttributeValue::S(value)) => DeliveryMode::from_str(value)?,
+        _ => defaults.delivery_mode,
+    };
+    let quiet_hours = match (item.get(QUIET_FROM_FIELD), item.get(QUIET_TO_FIELD)) {
+        (Some(AttributeValue::N(from)), Some(AttributeValue::N(to))) => {
+            Some(QuietHours::new(from.parse()?, to.parse()?)?)
+        }
+        _ => None,
+    };
+
+    Ok(NotificationPreference::new(
+        lead_time,
+        delivery_mode,
+        quiet_hours,
+    ))
 }
@@ -0,0 +1,62 @@
+use super::models::{ChatPreference, Language, NotificationPreference};
+use super::repository::Repository;
+use anyhow::Result;
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+/// Chat preferences kept in memory, inserting a chat again replaces its preferences.
+#[derive(Default)]
+pub struct InMemoryPreferences {
+    preferences: RwLock<HashMap<i64, ChatPreference>>,
+}
+
+impl InMemoryPreferences {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn update(&self, chat_id: i64, update: impl FnOnce(&mut ChatPreference)) {
+        if let Some(preference) = self.preferences.write().unwrap().get_mut(&chat_id) {
+            update(preference);
+        }
+    }
+}
+
+impl Repository for InMemoryPreferences {
+    async fn insert(&self, value: ChatPreference) -> Result<()> {
+        self.preferences
+            .write()
+            .unwrap()
+            .insert(value.chat_id, value);
+
+        Ok(())
+    }
+
+    async fn find_one(&self, chat_id: i64) -> Result<Option<ChatPreference>> {
+        Ok(self.preferences.read().unwrap().get(&chat_id).cloned())
+    }
+
+    async fn update_language(&self, chat_id: i64, language: Language) -> Result<()> {
+        self.update(chat_id, |it| it.language = language);
+
+        Ok(())
+    }
+
+    async fn update_notifications(&self, chat_id: i64, notifications: NotificationPreference) -> Result<()> {
+        self.update(chat_id, |it| it.notifications = notifications);
+
+        Ok(())
+    }
+
+    async fn update_active(&self, chat_id: i64, active: bool) -> Result<()> {
+        self.update(chat_id, |it| it.active = active);
+
+        Ok(())
+    }
+
+    async fn delete(&self, chat_id: i64) -> Result<()> {
+        self.preferences.write().unwrap().remove(&chat_id);
+
+        Ok(())
+    }
+}
@@ -1,10 +1,10 @@
 mod dynamo;
+mod memory;
 mod models;
 mod pg;
 mod repository;
 
-#[allow(unused)]
-pub use dynamo::*;
+pub use dynamo::tables as dynamo_tables;
+pub use memory::InMemoryPreferences;
 pub use models::*;
-pub use pg::*;
 pub use repository::*;
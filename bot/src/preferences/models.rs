@@ -1,4 +1,6 @@
 use anyhow::anyhow;
+use chrono::{NaiveDate, NaiveDateTime, Timelike};
+use std::fmt::{Display, Formatter};
 use std::str::FromStr;
 
 #[derive(Debug, Clone, Copy, PartialEq, sqlx::Type)]
@@ -33,8 +35,274 @@ impl FromStr for Language {
     }
 }
 
+/// How far in advance a chat wants to be warned about an outage.
+#[derive(Debug, Clone, Copy, PartialEq, Default, sqlx::Type)]
+#[sqlx(type_name = "lead_time_type")]
+#[sqlx(rename_all = "snake_case")]
+pub enum LeadTime {
+    /// Only outages happening today.
+    SameDay,
+    /// Outages happening today and tomorrow.
+    #[default]
+    OneDay,
+    /// Every outage published so far.
+    AllDays,
+}
+
+impl LeadTime {
+    /// Checks if an outage `days_ahead` days from today should be notified about.
+    pub fn covers(&self, days_ahead: i64) -> bool {
+        match self {
+            LeadTime::SameDay => days_ahead <= 0,
+            LeadTime::OneDay => days_ahead <= 1,
+            LeadTime::AllDays => true,
+        }
+    }
+}
+
+impl AsRef<str> for LeadTime {
+    fn as_ref(&self) -> &str {
+        match self {
+            LeadTime::SameDay => "same_day",
+            LeadTime::OneDay => "one_day",
+            LeadTime::AllDays => "all_days",
+        }
+    }
+}
+
+impl FromStr for LeadTime {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "same_day" => Ok(LeadTime::SameDay),
+            "one_day" => Ok(LeadTime::OneDay),
+            "all_days" => Ok(LeadTime::AllDays),
+            lead_time => Err(anyhow!("unknown lead time {lead_time}")),
+        }
+    }
+}
+
+/// Hour of the day when digests are delivered.
+pub const DIGEST_HOUR: u32 = 8;
+
+/// When a notification can be sent to a chat.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SendAt {
+    Now,
+    /// The chat doesn't want the message yet, it is kept for a later run.
+    Later,
+    /// The outage is over, the message is dropped.
+    Never,
+}
+
+/// How notifications are delivered to a chat.
+#[derive(Debug, Clone, Copy, PartialEq, Default, sqlx::Type)]
+#[sqlx(type_name = "delivery_mode_type")]
+#[sqlx(rename_all = "lowercase")]
+pub enum DeliveryMode {
+    /// A message is sent as soon as an outage is found.
+    #[default]
+    Instant,
+    /// All outages are collected into a single morning message.
+    Digest,
+}
+
+impl AsRef<str> for DeliveryMode {
+    fn as_ref(&self) -> &str {
+        match self {
+            DeliveryMode::Instant => "instant",
+            DeliveryMode::Digest => "digest",
+        }
+    }
+}
+
+impl FromStr for DeliveryMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "instant" => Ok(DeliveryMode::Instant),
+            "digest" => Ok(DeliveryMode::Digest),
+            mode => Err(anyhow!("unknown delivery mode {mode}")),
+        }
+    }
+}
+
+/// Hours of the day (0-23) when no messages should be sent. The interval may
+/// wrap around midnight, e.g. from 22 to 7.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QuietHours {
+    pub from: i16,
+    pub to: i16,
+}
+
+impl QuietHours {
+    pub fn new(from: i16, to: i16) -> anyhow::Result<Self> {
+        if !(0..24).contains(&from) || !(0..24).contains(&to) {
+            return Err(anyhow!("invalid quiet hours {from}-{to}"));
+        }
+
+        Ok(Self { from, to })
+    }
+
+    pub fn contains(&self, hour: i16) -> bool {
+        if self.from <= self.to {
+            (self.from..self.to).contains(&hour)
+        } else {
+            hour >= self.from || hour < self.to
+        }
+    }
+}
+
+impl Display for QuietHours {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:02}:00-{:02}:00", self.from, self.to)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, sqlx::FromRow)]
+pub struct NotificationPreference {
+    pub lead_time: LeadTime,
+    pub delivery_mode: DeliveryMode,
+    quiet_from: Option<i16>,
+    quiet_to: Option<i16>,
+}
+
+impl NotificationPreference {
+    pub fn new(lead_time: LeadTime, delivery_mode: DeliveryMode, quiet_hours: Option<QuietHours>) -> Self {
+        Self {
+            lead_time,
+            delivery_mode,
+            quiet_from: quiet_hours.map(|it| it.from),
+            quiet_to: quiet_hours.map(|it| it.to),
+        }
+    }
+
+    pub fn quiet_hours(&self) -> Option<QuietHours> {
+        self.quiet_from
+            .zip(self.quiet_to)
+            .map(|(from, to)| QuietHours { from, to })
+    }
+
+    pub fn with_quiet_hours(self, quiet_hours: Option<QuietHours>) -> Self {
+        Self::new(self.lead_time, self.delivery_mode, quiet_hours)
+    }
+
+    /// Checks if a message may be sent at the given hour of the day.
+    pub fn allows_sending_at(&self, hour: i16) -> bool {
+        !self.quiet_hours().is_some_and(|it| it.contains(hour))
+    }
+
+    /// When a notification about an outage on `date` can be sent, `now` is
+    /// the local time. Outages beyond the lead time, quiet hours and digests
+    /// outside of [`DIGEST_HOUR`] postpone the message.
+    pub fn send_at(&self, date: NaiveDate, now: NaiveDateTime) -> SendAt {
+        let days_ahead = (date - now.date()).num_days();
+
+        if days_ahead < 0 {
+            SendAt::Never
+        } else if !self.lead_time.covers(days_ahead)
+            || !self.allows_sending_at(now.hour() as i16)
+            || (self.delivery_mode == DeliveryMode::Digest && now.hour() != DIGEST_HOUR)
+        {
+            SendAt::Later
+        } else {
+            SendAt::Now
+        }
+    }
+}
+
 #[derive(Clone, sqlx::FromRow)]
 pub struct ChatPreference {
     pub chat_id: i64,
     pub language: Language,
+    #[sqlx(flatten)]
+    pub notifications: NotificationPreference,
+    /// Cleared when the chat can no longer be reached, e.g. the user has
+    /// blocked the bot. Notifications are not sent to inactive chats.
+    pub active: bool,
+}
+
+impl ChatPreference {
+    pub fn new(chat_id: i64, language: Language) -> Self {
+        Self {
+            chat_id,
+            language,
+            notifications: NotificationPreference::default(),
+            active: true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quiet_hours_wrap_around_midnight() {
+        let quiet_hours = QuietHours::new(22, 7).unwrap();
+
+        assert!(quiet_hours.contains(23));
+        assert!(quiet_hours.contains(0));
+        assert!(quiet_hours.contains(6));
+        assert!(!quiet_hours.contains(7));
+        assert!(!quiet_hours.contains(12));
+    }
+
+    #[test]
+    fn test_quiet_hours_reject_invalid_hours() {
+        assert!(QuietHours::new(24, 7).is_err());
+        assert!(QuietHours::new(-1, 7).is_err());
+    }
+
+    #[test]
+    fn test_lead_time_covers_days() {
+        assert!(LeadTime::SameDay.covers(0));
+        assert!(!LeadTime::SameDay.covers(1));
+        assert!(LeadTime::OneDay.covers(1));
+        assert!(!LeadTime::OneDay.covers(2));
+        assert!(LeadTime::AllDays.covers(3));
+    }
+
+    #[test]
+    fn test_notification_preference_quiet_hours() {
+        let preference = NotificationPreference::default().with_quiet_hours(Some(QuietHours::new(23, 8).unwrap()));
+
+        assert!(!preference.allows_sending_at(2));
+        assert!(preference.allows_sending_at(9));
+        assert!(NotificationPreference::default().allows_sending_at(2));
+    }
+
+    #[test]
+    fn test_send_at() {
+        let today = NaiveDate::from_ymd_opt(2023, 11, 20).unwrap();
+        let at = |hour| today.and_hms_opt(hour, 30, 0).unwrap();
+        let instant = NotificationPreference::default();
+
+        assert_eq!(instant.send_at(today, at(12)), SendAt::Now);
+        assert_eq!(
+            instant.send_at(today.succ_opt().unwrap(), at(12)),
+            SendAt::Now
+        );
+        assert_eq!(
+            instant.send_at(today + chrono::Duration::days(2), at(12)),
+            SendAt::Later
+        );
+        assert_eq!(
+            instant.send_at(today.pred_opt().unwrap(), at(12)),
+            SendAt::Never
+        );
+
+        let quiet = instant.with_quiet_hours(Some(QuietHours::new(22, 7).unwrap()));
+        assert_eq!(quiet.send_at(today, at(23)), SendAt::Later);
+        assert_eq!(quiet.send_at(today, at(7)), SendAt::Now);
+
+        let digest = NotificationPreference::new(LeadTime::AllDays, DeliveryMode::Digest, None);
+        assert_eq!(digest.send_at(today, at(12)), SendAt::Later);
+        assert_eq!(
+            digest.send_at(today + chrono::Duration::days(5), at(DIGEST_HOUR)),
+            SendAt::Now
+        );
+    }
 }
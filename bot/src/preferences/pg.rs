@@ -1,24 +1,24 @@
-use super::models::{ChatPreference, Language};
+use super::models::{ChatPreference, Language, NotificationPreference};
 use super::repository::Repository;
 use anyhow::Result;
 use sqlx::postgres::PgPool;
 
-pub struct PgChatPreference<'a> {
-    client: &'a PgPool,
-}
-
-impl<'a> PgChatPreference<'a> {
-    pub fn new(client: &'a PgPool) -> Self {
-        Self { client }
-    }
-}
-
-impl<'a> Repository for PgChatPreference<'a> {
+impl Repository for PgPool {
     async fn insert(&self, value: ChatPreference) -> Result<()> {
-        sqlx::query("INSERT INTO preference (chat_id, language) VALUES ($1, $2)")
+        let query =
+            "INSERT INTO preference (chat_id, language, lead_time, delivery_mode, quiet_from, quiet_to, active) \
+            VALUES ($1, $2, $3, $4, $5, $6, $7)";
+        let quiet_hours = value.notifications.quiet_hours();
+
+        sqlx::query(query)
             .bind(value.chat_id)
             .bind(value.language)
-            .execute(self.client)
+            .bind(value.notifications.lead_time)
+            .bind(value.notifications.delivery_mode)
+            .bind(quiet_hours.map(|it| it.from))
+            .bind(quiet_hours.map(|it| it.to))
+            .bind(value.active)
+            .execute(self)
             .await?;
 
         Ok(())
@@ -28,7 +28,7 @@ impl<'a> Repository for PgChatPreference<'a> {
         let query = String::from("SELECT * FROM preference WHERE chat_id = $1");
         let subscriptions = sqlx::query_as::<_, ChatPreference>(query.as_str())
             .bind(chat_id)
-            .fetch_all(self.client)
+            .fetch_all(self)
             .await?;
 
         Ok(subscriptions.into_iter().next())
@@ -40,7 +40,45 @@ impl<'a> Repository for PgChatPreference<'a> {
         sqlx::query(query)
             .bind(language)
             .bind(chat_id)
-            .execute(self.client)
+            .execute(self)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn update_notifications(&self, chat_id: i64, notifications: NotificationPreference) -> Result<()> {
+        let query = "UPDATE preference SET lead_time = $1, delivery_mode = $2, quiet_from = $3, quiet_to = $4, \
+            updated_at = NOW() WHERE chat_id = $5";
+        let quiet_hours = notifications.quiet_hours();
+
+        sqlx::query(query)
+            .bind(notifications.lead_time)
+            .bind(notifications.delivery_mode)
+            .bind(quiet_hours.map(|it| it.from))
+            .bind(quiet_hours.map(|it| it.to))
+            .bind(chat_id)
+            .execute(self)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn update_active(&self, chat_id: i64, active: bool) -> Result<()> {
+        let query = "UPDATE preference SET active = $1, updated_at = NOW() WHERE chat_id = $2";
+
+        sqlx::query(query)
+            .bind(active)
+            .bind(chat_id)
+            .execute(self)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn delete(&self, chat_id: i64) -> Result<()> {
+        sqlx::query("DELETE FROM preference WHERE chat_id = $1")
+            .bind(chat_id)
+            .execute(self)
             .await?;
 
         Ok(())
@@ -1,10 +1,17 @@
-use super::models::ChatPreference;
+use super::models::{ChatPreference, NotificationPreference};
 use crate::preferences::Language;
 use anyhow::Result;
 use std::future::Future;
 
 pub trait Repository {
-    fn insert(&self, value: ChatPreference) -> impl Future<Output = Result<()>>;
-    fn find_one(&self, chat_id: i64) -> impl Future<Output = Result<Option<ChatPreference>>>;
-    fn update_language(&self, chat_id: i64, language: Language) -> impl Future<Output = Result<()>>;
+    fn insert(&self, value: ChatPreference) -> impl Future<Output = Result<()>> + Send;
+    fn find_one(&self, chat_id: i64) -> impl Future<Output = Result<Option<ChatPreference>>> + Send;
+    fn update_language(&self, chat_id: i64, language: Language) -> impl Future<Output = Result<()>> + Send;
+    fn update_notifications(
+        &self,
+        chat_id: i64,
+        notifications: NotificationPreference,
+    ) -> impl Future<Output = Result<()>> + Send;
+    fn update_active(&self, chat_id: i64, active: bool) -> impl Future<Output = Result<()>> + Send;
+    fn delete(&self, chat_id: i64) -> impl Future<Output = Result<()>> + Send;
 }
@@ -1 +0,0 @@
-pub mod subscription_repository;
@@ -0,0 +1,270 @@
+//! The storage layer of the bot. Every backend provides repositories for
+//! subscriptions, messages, chat preferences and parsed outages, handlers are generic over
+//! [`Storage`] and the backend itself is selected through configuration.
+use crate::calendar::{feed, FeedConfig, FeedResponse};
+use crate::delivery::DeliveryStats;
+use crate::message_handler::{handle_update, notify_addresses, notify_changes, ChangesDelivery};
+use crate::messages::{Repository as _, RetentionPolicy};
+use crate::preferences::Repository as _;
+use crate::subscriptions::Repository as _;
+use crate::{messages, migrations, outages, preferences, subscriptions};
+use anyhow::{anyhow, Result};
+use aws_sdk_dynamodb::Client;
+use chrono::{NaiveDate, NaiveDateTime};
+use electricity::changes::PendingChange;
+use electricity::db::table_spec::TableSpec;
+pub use electricity::db::Backend;
+use sqlx::postgres::{PgPool, PgPoolOptions};
+use teloxide_core::types::Update;
+use teloxide_core::Bot;
+
+pub trait Storage: Sync {
+    type Subscriptions: subscriptions::Repository + Sync;
+    type Messages: messages::Repository + Sync;
+    type Preferences: preferences::Repository + Sync;
+    type Outages: outages::Repository + Sync;
+
+    fn subscriptions(&self) -> &Self::Subscriptions;
+    fn messages(&self) -> &Self::Messages;
+    fn preferences(&self) -> &Self::Preferences;
+    fn outages(&self) -> &Self::Outages;
+}
+
+/// Postgres storage, the repositories share the connection pool.
+pub struct PgStorage {
+    pool: PgPool,
+    messages: messages::PgMessages,
+}
+
+impl PgStorage {
+    pub fn new(pool: PgPool, retention: RetentionPolicy) -> Self {
+        Self {
+            messages: messages::PgMessages::new(pool.clone(), retention),
+            pool,
+        }
+    }
+
+    pub fn pool(&self) -> &PgPool {
+        &self.pool
+    }
+}
+
+impl Storage for PgStorage {
+    type Subscriptions = PgPool;
+    type Messages = messages::PgMessages;
+    type Preferences = PgPool;
+    type Outages = PgPool;
+
+    fn subscriptions(&self) -> &Self::Subscriptions {
+        &self.pool
+    }
+
+    fn messages(&self) -> &Self::Messages {
+        &self.messages
+    }
+
+    fn preferences(&self) -> &Self::Preferences {
+        &self.pool
+    }
+
+    fn outages(&self) -> &Self::Outages {
+        &self.pool
+    }
+}
+
+/// DynamoDB storage, the repositories share the client.
+pub struct DynamoStorage {
+    client: Client,
+    messages: messages::DynamoMessages,
+}
+
+impl DynamoStorage {
+    pub fn new(client: Client, retention: RetentionPolicy) -> Self {
+        Self {
+            messages: messages::DynamoMessages::new(client.clone(), retention),
+            client,
+        }
+    }
+
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+}
+
+impl Storage for DynamoStorage {
+    type Subscriptions = Client;
+    type Messages = messages::DynamoMessages;
+    type Preferences = Client;
+    type Outages = Client;
+
+    fn subscriptions(&self) -> &Self::Subscriptions {
+        &self.client
+    }
+
+    fn messages(&self) -> &Self::Messages {
+        &self.messages
+    }
+
+    fn preferences(&self) -> &Self::Preferences {
+        &self.client
+    }
+
+    fn outages(&self) -> &Self::Outages {
+        &self.client
+    }
+}
+
+/// Every DynamoDB table used by the bot.
+pub fn dynamo_tables() -> Vec<TableSpec> {
+    [
+        subscriptions::dynamo_tables(),
+        messages::dynamo_tables(),
+        preferences::dynamo_tables(),
+        outages::dynamo_tables(),
+    ]
+    .concat()
+}
+
+/// A storage living in the process memory, for tests and local runs.
+#[derive(Default)]
+pub struct InMemoryStorage {
+    pub subscriptions: subscriptions::InMemorySubscriptions,
+    pub messages: messages::InMemoryMessages,
+    pub preferences: preferences::InMemoryPreferences,
+    pub outages: outages::InMemoryOutages,
+}
+
+impl Storage for InMemoryStorage {
+    type Subscriptions = subscriptions::InMemorySubscriptions;
+    type Messages = messages::InMemoryMessages;
+    type Preferences = preferences::InMemoryPreferences;
+    type Outages = outages::InMemoryOutages;
+
+    fn subscriptions(&self) -> &Self::Subscriptions {
+        &self.subscriptions
+    }
+
+    fn messages(&self) -> &Self::Messages {
+        &self.messages
+    }
+
+    fn preferences(&self) -> &Self::Preferences {
+        &self.preferences
+    }
+
+    fn outages(&self) -> &Self::Outages {
+        &self.outages
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StorageConfig {
+    pub backend: Backend,
+    /// Connection string of the Postgres database, required for [`Backend::Postgres`].
+    pub postgres_url: Option<String>,
+    pub retention: RetentionPolicy,
+}
+
+impl StorageConfig {
+    /// Reads the configuration from `STORAGE_BACKEND` (`postgres` by default)
+    /// and `POSTGRESQL_URL` environment variables. DynamoDB is configured
+    /// through the standard AWS environment. The message retention is read
+    /// by [`RetentionPolicy::from_env`].
+    pub fn from_env() -> Result<Self> {
+        Ok(Self {
+            backend: Backend::from_env()?,
+            postgres_url: dotenvy::var("POSTGRESQL_URL").ok(),
+            retention: RetentionPolicy::from_env()?,
+        })
+    }
+
+    pub async fn connect(&self) -> Result<AnyStorage> {
+        match self.backend {
+            Backend::Postgres => {
+                let url = self
+                    .postgres_url
+                    .as_deref()
+                    .ok_or_else(|| anyhow!("POSTGRESQL_URL is required for the postgres storage"))?;
+                let pool = PgPoolOptions::new().max_connections(5).connect(url).await?;
+                migrations::verify(&pool).await?;
+
+                Ok(AnyStorage::Postgres(PgStorage::new(pool, self.retention)))
+            }
+            Backend::DynamoDb => Ok(AnyStorage::DynamoDb(DynamoStorage::new(
+                electricity::db::init_client().await?,
+                self.retention,
+            ))),
+        }
+    }
+}
+
+/// A storage selected at runtime, dispatches the entry points to the
+/// handlers instantiated for the concrete backend.
+pub enum AnyStorage {
+    Postgres(PgStorage),
+    DynamoDb(DynamoStorage),
+}
+
+impl AnyStorage {
+    pub async fn handle_update(&self, update: &Update, bot: &Bot) -> Result<()> {
+        match self {
+            AnyStorage::Postgres(storage) => handle_update(update, storage, bot).await,
+            AnyStorage::DynamoDb(storage) => handle_update(update, storage, bot).await,
+        }
+    }
+
+    pub async fn notify_addresses(
+        &self,
+        bot: &Bot,
+        date: NaiveDate,
+        addresses: Vec<String>,
+        now: NaiveDateTime,
+    ) -> Result<DeliveryStats> {
+        match self {
+            AnyStorage::Postgres(storage) => notify_addresses(storage, bot, date, addresses, now).await,
+            AnyStorage::DynamoDb(storage) => notify_addresses(storage, bot, date, addresses, now).await,
+        }
+    }
+
+    pub async fn notify_changes(
+        &self,
+        bot: &Bot,
+        changes: &[PendingChange],
+        now: NaiveDateTime,
+    ) -> Result<ChangesDelivery> {
+        match self {
+            AnyStorage::Postgres(storage) => notify_changes(storage, bot, changes, now).await,
+            AnyStorage::DynamoDb(storage) => notify_changes(storage, bot, changes, now).await,
+        }
+    }
+
+    pub async fn calendar_feed(&self, config: &FeedConfig, path: &str) -> Result<FeedResponse> {
+        match self {
+            AnyStorage::Postgres(storage) => feed(storage, config, path).await,
+            AnyStorage::DynamoDb(storage) => feed(storage, config, path).await,
+        }
+    }
+
+    pub async fn purge_messages(&self, policy: &RetentionPolicy) -> Result<u64> {
+        match self {
+            AnyStorage::Postgres(storage) => storage.messages().purge(policy).await,
+            AnyStorage::DynamoDb(storage) => storage.messages().purge(policy).await,
+        }
+    }
+}
+
+/// Deletes everything the bot keeps about a chat: its messages, preferences
+/// and subscriptions.
+pub async fn forget_chat<S>(storage: &S, chat_id: i64) -> Result<()>
+where
+    S: Storage,
+{
+    storage
+        .subscriptions()
+        .delete_all_by_chat_id(chat_id)
+        .await?;
+    storage.messages().delete_all_by_chat_id(chat_id).await?;
+    storage.preferences().delete(chat_id).await?;
+
+    Ok(())
+}
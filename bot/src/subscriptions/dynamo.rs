@@ -1,24 +1,150 @@
 use super::models::{NewSubscription, Subscription};
 use super::Repository;
-use anyhow::{anyhow, bail, Result};
-use aws_sdk_dynamodb::types::{AttributeValue, KeysAndAttributes, TransactWriteItem, Update};
+use anyhow::{bail, Context, Result};
+use aws_sdk_dynamodb::types::{AttributeValue, TransactWriteItem, Update};
 use aws_sdk_dynamodb::Client;
-use std::collections::HashMap;
+use electricity::db::table_spec::{IndexSpec, KeySpec, TableSpec};
+use electricity::translit::{Naked, Translit};
+use std::collections::{BTreeSet, HashMap};
+use tracing::{event, Level};
 
 static TABLE_NAME: &str = "subscriptions";
 static TABLE_NAME_INV: &str = "subscriptions_inv";
 static CHAT_ID_FIELD: &str = "chat_id";
 static ADDRESSES_FIELD: &str = "addresses";
 static CHAT_IDS_FIELD: &str = "chat_ids";
+static STREET_KEY_FIELD: &str = "street_key";
+static STREET_INDEX: &str = "by_street";
+
+/// DynamoDB keeps subscriptions as sets of addresses, so the id of
+/// a subscription is derived from its address (FNV-1a hash). It is stable
+/// across requests and fits into callback data of inline keyboards.
+fn address_id(address: &str) -> i64 {
+    let hash = address
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        });
+
+    (hash >> 1) as i64
+}
+
+/// The address in lower case Latin script without diacritics, so that
+/// "Кнеза Милоша  5" and "kneza miloša 5" are the same.
+fn normalize(address: &str) -> String {
+    address
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .translit()
+        .naked()
+}
+
+/// The normalized street of an address, without the house number.
+fn street_key(address: &str) -> String {
+    let address = normalize(address);
+    let mut words = address.split(' ').collect::<Vec<_>>();
+    while words.len() > 1
+        && words
+            .last()
+            .is_some_and(|it| it.starts_with(|c: char| c.is_ascii_digit()))
+    {
+        words.pop();
+    }
+
+    words.join(" ")
+}
+
+/// The subscriptions are kept twice: addresses by chat id and chat ids by
+/// address, the latter is also indexed by the street of the address.
+pub fn tables() -> Vec<TableSpec> {
+    vec![
+        TableSpec::new(TABLE_NAME, KeySpec::n(CHAT_ID_FIELD)),
+        TableSpec::new(TABLE_NAME_INV, KeySpec::s(ADDRESSES_FIELD))
+            .with_index(IndexSpec::new(STREET_INDEX, KeySpec::s(STREET_KEY_FIELD))),
+    ]
+}
+
+/// Sets the street key of the addresses subscribed to before it was kept,
+/// returns the number of updated addresses.
+pub async fn index_streets(client: &Client) -> Result<usize> {
+    let mut updated = 0;
+    let mut start_key = None;
+
+    loop {
+        let response = client
+            .scan()
+            .table_name(TABLE_NAME_INV)
+            .set_exclusive_start_key(start_key)
+            .send()
+            .await?;
+
+        for item in response.items() {
+            let Some(AttributeValue::S(address)) = item.get(ADDRESSES_FIELD) else {
+                continue;
+            };
+            let key = street_key(address);
+            if key.is_empty() || item.contains_key(STREET_KEY_FIELD) {
+                continue;
+            }
+
+            client
+                .update_item()
+                .table_name(TABLE_NAME_INV)
+                .key(ADDRESSES_FIELD, AttributeValue::S(address.clone()))
+                .update_expression(format!("SET {STREET_KEY_FIELD} = :s"))
+                .expression_attribute_values(":s", AttributeValue::S(key))
+                .send()
+                .await?;
+            updated += 1;
+        }
+
+        start_key = response.last_evaluated_key;
+        if start_key.is_none() {
+            break;
+        }
+    }
+
+    Ok(updated)
+}
+
+/// The address of an item of the inverted table and the chats subscribed to it.
+fn to_subscribers(item: &HashMap<String, AttributeValue>) -> Result<(String, Vec<i64>)> {
+    let address = item
+        .get(ADDRESSES_FIELD)
+        .and_then(|it| it.as_s().ok())
+        .with_context(|| format!("\"{ADDRESSES_FIELD}\" field is missing in the table \"{TABLE_NAME_INV}\""))?;
+    // The set is missing when every chat has unsubscribed from the address.
+    let chat_ids = match item.get(CHAT_IDS_FIELD) {
+        Some(AttributeValue::Ns(chat_ids)) => chat_ids
+            .iter()
+            .map(|it| it.parse().with_context(|| format!("invalid chat id {it}")))
+            .collect::<Result<_>>()?,
+        Some(_) => bail!("\"{CHAT_IDS_FIELD}\" of \"{address}\" is not a number set"),
+        None => vec![],
+    };
+
+    Ok((address.clone(), chat_ids))
+}
 
 impl Repository for Client {
     async fn append(&self, value: NewSubscription) -> Result<()> {
+        let key = street_key(&value.address);
         let update_rev = Update::builder()
             .table_name(TABLE_NAME_INV)
             .key(ADDRESSES_FIELD, AttributeValue::S(value.address.clone()))
-            .update_expression(format!("ADD {CHAT_IDS_FIELD} :a"))
-            .expression_attribute_values(":a", AttributeValue::Ns(vec![value.chat_id.to_string()]))
-            .build()?;
+            .expression_attribute_values(":a", AttributeValue::Ns(vec![value.chat_id.to_string()]));
+        // An index key can't be empty, such an address is never found by street.
+        let update_rev = if key.is_empty() {
+            update_rev.update_expression(format!("ADD {CHAT_IDS_FIELD} :a"))
+        } else {
+            update_rev
+                .update_expression(format!(
+                    "ADD {CHAT_IDS_FIELD} :a SET {STREET_KEY_FIELD} = :s"
+                ))
+                .expression_attribute_values(":s", AttributeValue::S(key))
+        }
+        .build()?;
 
         let update = Update::builder()
             .table_name(TABLE_NAME)
@@ -52,83 +178,193 @@ impl Repository for Client {
             if let Some(AttributeValue::Ss(addresses)) = map.get(ADDRESSES_FIELD) {
                 let res = addresses
                     .iter()
-                    .enumerate()
-                    .map(|(i, it)| Subscription {
-                        id: i as i64,
+                    .map(|it| Subscription {
+                        id: address_id(it),
                         chat_id,
                         address: it.clone(),
                     })
                     .collect::<Vec<_>>();
-                return Ok(res);
+                Ok(res)
             } else {
-                Err(anyhow!(
-                    "\"{ADDRESSES_FIELD}\" attribute is missing in the table \"{TABLE_NAME}\" for ChatId({chat_id})"
-                ))
+                // DynamoDB drops a set attribute once its last element is deleted.
+                Ok(vec![])
             }
         } else {
-            Err(anyhow!(
-                "ChatId({chat_id}) is not found in the table \"{TABLE_NAME}\""
-            ))
+            Ok(vec![])
         }
     }
 
     async fn find_all_by_addresses(&self, addresses: Vec<String>) -> Result<Vec<Subscription>> {
-        // FIXME: DynamoDb does not allow more than 100 items.
-        // FIXME: there is also a limit in size.
-
-        let keys = KeysAndAttributes::builder()
-            .set_keys(Some(
-                addresses
-                    .into_iter()
-                    .map(|it| {
-                        let mut map = HashMap::new();
-                        map.insert(ADDRESSES_FIELD.to_string(), AttributeValue::S(it));
-                        map
-                    })
-                    .collect::<Vec<_>>(),
-            ))
-            .build()?;
+        let addresses = addresses.iter().map(|it| normalize(it)).collect::<Vec<_>>();
+        let keys = addresses
+            .iter()
+            .map(|it| street_key(it))
+            .filter(|it| !it.is_empty())
+            .collect::<BTreeSet<_>>();
+        let mut result = Vec::new();
 
-        let response = self
-            .batch_get_item()
-            .request_items(TABLE_NAME_INV, keys)
-            .send()
-            .await?;
+        for key in keys {
+            let mut start_key = None;
+
+            loop {
+                let response = self
+                    .query()
+                    .table_name(TABLE_NAME_INV)
+                    .index_name(STREET_INDEX)
+                    .key_condition_expression(format!("{STREET_KEY_FIELD} = :s"))
+                    .expression_attribute_values(":s", AttributeValue::S(key.clone()))
+                    .set_exclusive_start_key(start_key)
+                    .send()
+                    .await?;
+
+                for item in response.items() {
+                    // A malformed item doesn't hide the other subscribers.
+                    let (address, chat_ids) = match to_subscribers(item) {
+                        Ok(subscribers) => subscribers,
+                        Err(err) => {
+                            event!(Level::WARN, error = %err, "skipping subscription item");
+                            continue;
+                        }
+                    };
+                    // The house number of the address is checked here.
+                    let normalized = normalize(&address);
+                    if !addresses.iter().any(|it| normalized.contains(it.as_str())) {
+                        continue;
+                    }
+
+                    result.extend(chat_ids.into_iter().map(|chat_id| Subscription {
+                        id: address_id(&address),
+                        chat_id,
+                        address: address.clone(),
+                    }));
+                }
 
-        let items = response
-            .responses
-            .map(|x| x.get(TABLE_NAME_INV).cloned().unwrap_or_default())
-            .unwrap_or_default();
+                start_key = response.last_evaluated_key;
+                if start_key.is_none() {
+                    break;
+                }
+            }
+        }
+
+        Ok(result)
+    }
 
+    async fn find_all(&self) -> Result<Vec<Subscription>> {
         let mut result = Vec::new();
-        for item in items {
-            let address = if let Some(AttributeValue::S(address)) = item.get(ADDRESSES_FIELD) {
-                address.clone()
-            } else {
-                bail!("\"{ADDRESSES_FIELD}\" field is missing in the table \"{TABLE_NAME_INV}\"");
-            };
+        let mut start_key = None;
 
-            if let Some(AttributeValue::Ns(chat_ids)) = item.get(CHAT_IDS_FIELD) {
-                result.extend(
-                    chat_ids
-                        .iter()
-                        .flat_map(|it| it.parse::<i64>().ok())
-                        .enumerate()
-                        .map(|(i, it)| Subscription {
-                            id: it + i as i64,
-                            chat_id: it,
-                            address: address.clone(),
-                        }),
-                );
-            } else {
-                bail!("\"{CHAT_IDS_FIELD}\" field is missing in the table \"{TABLE_NAME_INV}\"");
+        loop {
+            let response = self
+                .scan()
+                .table_name(TABLE_NAME)
+                .set_exclusive_start_key(start_key)
+                .send()
+                .await?;
+
+            for item in response.items() {
+                let chat_id = match item.get(CHAT_ID_FIELD) {
+                    Some(AttributeValue::N(chat_id)) => chat_id.parse::<i64>()?,
+                    _ => bail!("\"{CHAT_ID_FIELD}\" field is missing in the table \"{TABLE_NAME}\""),
+                };
+
+                // The set is missing when the chat has unsubscribed from every address.
+                if let Some(AttributeValue::Ss(addresses)) = item.get(ADDRESSES_FIELD) {
+                    result.extend(addresses.iter().map(|it| Subscription {
+                        id: address_id(it),
+                        chat_id,
+                        address: it.clone(),
+                    }));
+                }
+            }
+
+            start_key = response.last_evaluated_key;
+            if start_key.is_none() {
+                break;
             }
         }
 
         Ok(result)
     }
 
-    async fn delete_by_ids(&self, ids: Vec<i64>) -> Result<()> {
-        todo!()
+    async fn delete_all_by_chat_id(&self, chat_id: i64) -> Result<()> {
+        let request = self
+            .get_item()
+            .table_name(TABLE_NAME)
+            .key(CHAT_ID_FIELD, AttributeValue::N(chat_id.to_string()));
+
+        let Some(item) = request.send().await?.item else {
+            return Ok(());
+        };
+
+        if let Some(AttributeValue::Ss(addresses)) = item.get(ADDRESSES_FIELD) {
+            for address in addresses {
+                self.update_item()
+                    .table_name(TABLE_NAME_INV)
+                    .key(ADDRESSES_FIELD, AttributeValue::S(address.clone()))
+                    .update_expression(format!("DELETE {CHAT_IDS_FIELD} :a"))
+                    .expression_attribute_values(":a", AttributeValue::Ns(vec![chat_id.to_string()]))
+                    .send()
+                    .await?;
+            }
+        }
+
+        self.delete_item()
+            .table_name(TABLE_NAME)
+            .key(CHAT_ID_FIELD, AttributeValue::N(chat_id.to_string()))
+            .send()
+            .await?;
+
+        Ok(())
+    }
+
+    async fn delete(&self, chat_id: i64, id: i64) -> Result<()> {
+        let subscriptions = self.find_all_by_chat_id(chat_id).await?;
+        let Some(subscription) = subscriptions.into_iter().find(|it| it.id == id) else {
+            return Ok(());
+        };
+
+        let update_rev = Update::builder()
+            .table_name(TABLE_NAME_INV)
+            .key(
+                ADDRESSES_FIELD,
+                AttributeValue::S(subscription.address.clone()),
+            )
+            .update_expression(format!("DELETE {CHAT_IDS_FIELD} :a"))
+            .expression_attribute_values(":a", AttributeValue::Ns(vec![chat_id.to_string()]))
+            .build()?;
+
+        let update = Update::builder()
+            .table_name(TABLE_NAME)
+            .key(CHAT_ID_FIELD, AttributeValue::N(chat_id.to_string()))
+            .update_expression(format!("DELETE {ADDRESSES_FIELD} :a"))
+            .expression_attribute_values(":a", AttributeValue::Ss(vec![subscription.address]))
+            .build()?;
+
+        let t1 = TransactWriteItem::builder().update(update_rev).build();
+        let t2 = TransactWriteItem::builder().update(update).build();
+
+        let request = self
+            .transact_write_items()
+            .transact_items(t1)
+            .transact_items(t2);
+
+        request.send().await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_street_key() {
+        assert_eq!(street_key("Kneza Miloša  5"), "kneza milosa");
+        assert_eq!(street_key("Кнеза Милоша 5/3"), "kneza milosa");
+        assert_eq!(street_key("Bulevar oslobođenja"), "bulevar oslobodjenja");
+        assert_eq!(street_key("27. marta 12"), "27. marta");
+        assert_eq!(street_key("5"), "5");
+        assert_eq!(street_key("  "), "");
     }
 }
@@ -0,0 +1,167 @@
+use super::models::{NewSubscription, Subscription};
+use super::repository::matches_any;
+use super::Repository;
+use anyhow::Result;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::RwLock;
+
+#[derive(Default)]
+struct State {
+    next_id: i64,
+    /// Subscriptions by id.
+    subscriptions: BTreeMap<i64, Subscription>,
+    /// Ids of the subscriptions to an address, mirrors `subscriptions_inv` of DynamoDB.
+    by_address: HashMap<String, BTreeSet<i64>>,
+}
+
+/// Subscriptions kept in memory. Like DynamoDB, a chat is subscribed to an
+/// address at most once.
+#[derive(Default)]
+pub struct InMemorySubscriptions {
+    state: RwLock<State>,
+}
+
+impl InMemorySubscriptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Repository for InMemorySubscriptions {
+    async fn append(&self, value: NewSubscription) -> Result<()> {
+        let mut state = self.state.write().unwrap();
+        let State {
+            next_id,
+            subscriptions,
+            by_address,
+        } = &mut *state;
+
+        let ids = by_address.entry(value.address.clone()).or_default();
+        if ids
+            .iter()
+            .any(|id| subscriptions[id].chat_id == value.chat_id)
+        {
+            return Ok(());
+        }
+
+        *next_id += 1;
+        ids.insert(*next_id);
+        subscriptions.insert(
+            *next_id,
+            Subscription {
+                id: *next_id,
+                chat_id: value.chat_id,
+                address: value.address,
+            },
+        );
+
+        Ok(())
+    }
+
+    async fn find_all_by_chat_id(&self, chat_id: i64) -> Result<Vec<Subscription>> {
+        let state = self.state.read().unwrap();
+
+        Ok(state
+            .subscriptions
+            .values()
+            .filter(|it| it.chat_id == chat_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn find_all_by_addresses(&self, addresses: Vec<String>) -> Result<Vec<Subscription>> {
+        let state = self.state.read().unwrap();
+        let addresses = addresses
+            .iter()
+            .map(|it| it.to_lowercase())
+            .collect::<Vec<_>>();
+
+        Ok(state
+            .by_address
+            .iter()
+            .filter(|(address, _)| matches_any(address, &addresses))
+            .flat_map(|(_, ids)| ids)
+            .map(|id| state.subscriptions[id].clone())
+            .collect())
+    }
+
+    async fn find_all(&self) -> Result<Vec<Subscription>> {
+        let state = self.state.read().unwrap();
+
+        Ok(state.subscriptions.values().cloned().collect())
+    }
+
+    async fn delete(&self, chat_id: i64, id: i64) -> Result<()> {
+        let mut state = self.state.write().unwrap();
+
+        if state
+            .subscriptions
+            .get(&id)
+            .is_some_and(|it| it.chat_id == chat_id)
+        {
+            remove(&mut state, id);
+        }
+
+        Ok(())
+    }
+
+    async fn delete_all_by_chat_id(&self, chat_id: i64) -> Result<()> {
+        let mut state = self.state.write().unwrap();
+
+        let ids = state
+            .subscriptions
+            .values()
+            .filter(|it| it.chat_id == chat_id)
+            .map(|it| it.id)
+            .collect::<Vec<_>>();
+        for id in ids {
+            remove(&mut state, id);
+        }
+
+        Ok(())
+    }
+}
+
+/// Removes the subscription together with its entry in the address index.
+fn remove(state: &mut State, id: i64) {
+    let Some(subscription) = state.subscriptions.remove(&id) else {
+        return;
+    };
+
+    if let Some(ids) = state.by_address.get_mut(&subscription.address) {
+        ids.remove(&id);
+        if ids.is_empty() {
+            state.by_address.remove(&subscription.address);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_address_index_is_cleaned_up() {
+        let repository = InMemorySubscriptions::new();
+        repository
+            .append(NewSubscription {
+                chat_id: 1,
+                address: "kneza miloša 5".to_string(),
+            })
+            .await
+            .unwrap();
+        repository
+            .append(NewSubscription {
+                chat_id: 1,
+                address: "kneza miloša 5".to_string(),
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(repository.find_all_by_chat_id(1).await.unwrap().len(), 1);
+
+        repository.delete_all_by_chat_id(1).await.unwrap();
+
+        assert!(repository.state.read().unwrap().by_address.is_empty());
+    }
+}
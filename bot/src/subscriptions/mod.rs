@@ -1,9 +1,10 @@
 mod dynamo;
+mod memory;
 mod models;
 mod pg;
 mod repository;
 
-pub use dynamo::*;
+pub use dynamo::{index_streets, tables as dynamo_tables};
+pub use memory::InMemorySubscriptions;
 pub use models::*;
-pub use pg::*;
 pub use repository::*;
@@ -8,7 +8,7 @@ pub struct NewSubscription {
 #[sqlx(transparent)]
 pub struct SubId(i64);
 
-#[derive(sqlx::FromRow, Debug)]
+#[derive(sqlx::FromRow, Debug, Clone)]
 pub struct Subscription {
     pub id: i64,
     pub chat_id: i64,
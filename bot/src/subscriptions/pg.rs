@@ -1,7 +1,6 @@
 use super::models::{NewSubscription, Subscription};
 use super::Repository;
 use anyhow::Result;
-use itertools::Itertools;
 use sqlx::PgPool;
 
 impl Repository for PgPool {
@@ -25,25 +24,72 @@ impl Repository for PgPool {
     }
 
     async fn find_all_by_addresses(&self, addresses: Vec<String>) -> Result<Vec<Subscription>> {
-        let addresses = addresses
+        // Lowercased here, as ILIKE can't fold non-ASCII letters such as "Š"
+        // in databases with the C locale.
+        let patterns = addresses
             .iter()
-            .map(|address| format!("'%{}%'", address))
-            .join(", ");
+            .map(|address| like_pattern(&address.to_lowercase()))
+            .collect::<Vec<_>>();
 
-        let query = format!("SELECT * FROM subscriptions WHERE address ilike any (array[{addresses}])");
-        let subscriptions = sqlx::query_as::<_, Subscription>(query.as_str())
-            .fetch_all(self)
-            .await?;
+        let subscriptions = sqlx::query_as::<_, Subscription>(
+            "SELECT id, chat_id, address FROM subscriptions WHERE address ILIKE ANY($1)",
+        )
+        .bind(patterns)
+        .fetch_all(self)
+        .await?;
+
+        Ok(subscriptions)
+    }
+
+    async fn find_all(&self) -> Result<Vec<Subscription>> {
+        let subscriptions =
+            sqlx::query_as::<_, Subscription>("SELECT id, chat_id, address FROM subscriptions ORDER BY id")
+                .fetch_all(self)
+                .await?;
 
         Ok(subscriptions)
     }
 
-    async fn delete_by_ids(&self, ids: Vec<i64>) -> Result<()> {
-        let ids_string = ids.into_iter().map(|it| it.to_string()).join(",");
-        let query = format!("DELETE FROM subscriptions WHERE ID in ({ids_string})");
+    async fn delete_all_by_chat_id(&self, chat_id: i64) -> Result<()> {
+        sqlx::query("DELETE FROM subscriptions WHERE chat_id = $1")
+            .bind(chat_id)
+            .execute(self)
+            .await?;
+
+        Ok(())
+    }
 
-        sqlx::query(&query).execute(self).await?;
+    async fn delete(&self, chat_id: i64, id: i64) -> Result<()> {
+        sqlx::query("DELETE FROM subscriptions WHERE chat_id = $1 AND id = $2")
+            .bind(chat_id)
+            .bind(id)
+            .execute(self)
+            .await?;
 
         Ok(())
     }
 }
+
+/// Makes an `ILIKE` pattern matching any text containing the address, the
+/// wildcards typed by a user are matched literally.
+fn like_pattern(address: &str) -> String {
+    let escaped = address
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+
+    format!("%{escaped}%")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_like_pattern_escapes_wildcards() {
+        assert_eq!(like_pattern("bulevar"), "%bulevar%");
+        assert_eq!(like_pattern("100%_ulica"), "%100\\%\\_ulica%");
+        assert_eq!(like_pattern("a\\b"), "%a\\\\b%");
+        assert_eq!(like_pattern("o'connell"), "%o'connell%");
+    }
+}
@@ -6,6 +6,21 @@ pub trait Repository {
     /// Appends new subscription to the user's list.
     fn append(&self, value: NewSubscription) -> impl Future<Output = Result<()>> + Send;
     fn find_all_by_chat_id(&self, chat_id: i64) -> impl Future<Output = Result<Vec<Subscription>>> + Send;
+    /// Subscriptions whose address contains any of the given addresses,
+    /// ignoring the letter case, e.g. "Kneza Miloša" finds "kneza miloša 5".
+    /// The addresses are whole streets, optionally with a house number.
     fn find_all_by_addresses(&self, addresses: Vec<String>) -> impl Future<Output = Result<Vec<Subscription>>> + Send;
-    fn delete_by_ids(&self, ids: Vec<i64>) -> impl Future<Output = Result<()>> + Send;
+    /// Every subscription of every chat.
+    fn find_all(&self) -> impl Future<Output = Result<Vec<Subscription>>> + Send;
+    /// Removes the chat's subscription with the given id, unknown ids are ignored.
+    fn delete(&self, chat_id: i64, id: i64) -> impl Future<Output = Result<()>> + Send;
+    /// Removes every subscription of the chat.
+    fn delete_all_by_chat_id(&self, chat_id: i64) -> impl Future<Output = Result<()>> + Send;
+}
+
+/// Whether the address contains any of the lowercase addresses.
+pub(super) fn matches_any(address: &str, addresses: &[String]) -> bool {
+    let address = address.to_lowercase();
+
+    addresses.iter().any(|it| address.contains(it.as_str()))
 }
@@ -0,0 +1,21 @@
+//! The Bot API client. Handlers receive it as an argument, so it can point
+//! to a local Bot API server or to a stand-in in tests.
+use anyhow::{Context, Result};
+use teloxide_core::Bot;
+
+/// Creates a client from `TELOXIDE_TOKEN`, requests go to `TELEGRAM_API_URL`
+/// when it is set and to `https://api.telegram.org` otherwise.
+pub fn bot_from_env() -> Result<Bot> {
+    let bot = Bot::from_env();
+
+    match dotenvy::var("TELEGRAM_API_URL") {
+        Ok(url) => {
+            let url = url
+                .parse()
+                .with_context(|| format!("invalid TELEGRAM_API_URL {url}"))?;
+
+            Ok(bot.set_api_url(url))
+        }
+        Err(_) => Ok(bot),
+    }
+}
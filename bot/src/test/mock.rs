@@ -1,7 +1,7 @@
 use chrono::Utc;
 use teloxide_core::types::{
-    Chat, ChatId, ChatKind, ChatPublic, MediaKind, MediaPhoto, Message, MessageCommon, MessageId, MessageKind,
-    PublicChatGroup, PublicChatKind, Update, UpdateKind, User, UserId,
+    CallbackQuery, Chat, ChatId, ChatKind, ChatPublic, MediaKind, MediaPhoto, MediaText, Message, MessageCommon,
+    MessageId, MessageKind, PublicChatGroup, PublicChatKind, Update, UpdateKind, User, UserId,
 };
 
 use crate::preferences::Language;
@@ -84,6 +84,42 @@ impl BotUpdateMock {
         }
     }
 
+    /// A text message sent by the user.
+    pub fn text(text: &str) -> Self {
+        let mut message = get_default_message();
+        if let MessageKind::Common(common_message) = &mut message.kind {
+            common_message.media_kind = MediaKind::Text(MediaText {
+                text: text.to_owned(),
+                entities: vec![],
+            });
+        }
+
+        Self {
+            update: Update {
+                id: 1,
+                kind: UpdateKind::Message(message),
+            },
+        }
+    }
+
+    /// A press of an inline keyboard button attached to a message of the bot.
+    pub fn callback(data: &str) -> Self {
+        Self {
+            update: Update {
+                id: 1,
+                kind: UpdateKind::CallbackQuery(CallbackQuery {
+                    id: String::from("1"),
+                    from: get_default_user(),
+                    message: Some(get_default_message()),
+                    inline_message_id: None,
+                    chat_instance: String::from("1"),
+                    data: Some(data.to_owned()),
+                    game_short_name: None,
+                }),
+            },
+        }
+    }
+
     pub fn get_update(&self) -> Update {
         self.update.clone()
     }
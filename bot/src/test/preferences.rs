@@ -1,4 +1,4 @@
-use crate::preferences::{ChatPreference, Language, Repository};
+use crate::preferences::{ChatPreference, Language, NotificationPreference, Repository};
 use anyhow::Result;
 use std::sync::RwLock;
 
@@ -30,6 +30,8 @@ impl Repository for TestChatPreference {
         let new_chat_preference = ChatPreference {
             chat_id: value.chat_id,
             language: value.language,
+            notifications: value.notifications,
+            active: value.active,
         };
 
         let mut chat_preferences = self.chat_preferences.write().unwrap();
@@ -58,8 +60,8 @@ impl Repository for TestChatPreference {
             .map(|chat_preference| {
                 if chat_preference.chat_id == chat_id {
                     ChatPreference {
-                        chat_id: chat_preference.chat_id,
                         language,
+                        ..chat_preference.clone()
                     }
                 } else {
                     chat_preference.clone()
@@ -71,4 +73,33 @@ impl Repository for TestChatPreference {
 
         Ok(())
     }
+
+    async fn update_notifications(&self, chat_id: i64, notifications: NotificationPreference) -> Result<()> {
+        let mut chat_preferences = self.chat_preferences.write().unwrap();
+
+        if let Some(chat_preference) = chat_preferences.iter_mut().find(|it| it.chat_id == chat_id) {
+            chat_preference.notifications = notifications;
+        }
+
+        Ok(())
+    }
+
+    async fn update_active(&self, chat_id: i64, active: bool) -> Result<()> {
+        let mut chat_preferences = self.chat_preferences.write().unwrap();
+
+        if let Some(chat_preference) = chat_preferences.iter_mut().find(|it| it.chat_id == chat_id) {
+            chat_preference.active = active;
+        }
+
+        Ok(())
+    }
+
+    async fn delete(&self, chat_id: i64) -> Result<()> {
+        self.chat_preferences
+            .write()
+            .unwrap()
+            .retain(|it| it.chat_id != chat_id);
+
+        Ok(())
+    }
 }
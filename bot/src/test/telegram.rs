@@ -0,0 +1,228 @@
+//! An in-process stand-in for the Telegram Bot API. It accepts the JSON and
+//! multipart requests of the bot client, records them and answers with a plain
+//! text message, so whole conversations can be checked without the network.
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+use teloxide_core::Bot;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+const TOKEN: &str = "123456:TEST";
+
+#[derive(Debug, Clone)]
+pub struct Call {
+    /// Lowercase name of the Bot API method, e.g. `sendmessage`.
+    pub method: String,
+    pub body: Value,
+}
+
+impl Call {
+    pub fn text(&self) -> &str {
+        self.body["text"].as_str().unwrap_or_default()
+    }
+
+    /// Name and content of the file uploaded as the multipart field. The
+    /// field refers to the part with the file as `attach://<part name>`.
+    pub fn file(&self, field: &str) -> Option<(&str, &str)> {
+        let part = self.body[field].as_str()?.strip_prefix("attach://")?;
+        let file = &self.body[part];
+
+        Some((file["file_name"].as_str()?, file["content"].as_str()?))
+    }
+
+    /// Callback data of every inline keyboard button attached to the message.
+    pub fn callback_data(&self) -> Vec<String> {
+        self.body["reply_markup"]["inline_keyboard"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .flat_map(|row| row.as_array().into_iter().flatten())
+            .filter_map(|button| button["callback_data"].as_str())
+            .map(ToOwned::to_owned)
+            .collect()
+    }
+
+    /// Texts of the buttons of the reply keyboard attached to the message.
+    pub fn keyboard(&self) -> Vec<String> {
+        self.body["reply_markup"]["keyboard"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .flat_map(|row| row.as_array().into_iter().flatten())
+            .filter_map(|button| button["text"].as_str())
+            .map(ToOwned::to_owned)
+            .collect()
+    }
+}
+
+pub struct FakeBotApi {
+    url: String,
+    calls: Arc<Mutex<Vec<Call>>>,
+    server: JoinHandle<()>,
+}
+
+impl FakeBotApi {
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let calls = Arc::new(Mutex::new(vec![]));
+
+        let recorded = calls.clone();
+        let server = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve(stream, recorded.clone()));
+            }
+        });
+
+        Self { url, calls, server }
+    }
+
+    /// A client sending its requests to this server.
+    pub fn bot(&self) -> Bot {
+        Bot::new(TOKEN).set_api_url(self.url.parse().unwrap())
+    }
+
+    /// Returns the calls recorded since the previous invocation.
+    pub fn take_calls(&self) -> Vec<Call> {
+        std::mem::take(&mut self.calls.lock().unwrap())
+    }
+}
+
+impl Drop for FakeBotApi {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+/// Serves the requests of a single keep-alive connection.
+async fn serve(mut stream: TcpStream, calls: Arc<Mutex<Vec<Call>>>) {
+    let mut buffer = vec![];
+
+    loop {
+        let Some((method, body)) = read_request(&mut stream, &mut buffer).await else {
+            return;
+        };
+
+        let result = answer(&method, &body);
+        calls.lock().unwrap().push(Call { method, body });
+
+        let response = json!({ "ok": true, "result": result }).to_string();
+        let head = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n",
+            response.len()
+        );
+
+        if stream
+            .write_all(format!("{head}{response}").as_bytes())
+            .await
+            .is_err()
+        {
+            return;
+        }
+    }
+}
+
+async fn read_request(stream: &mut TcpStream, buffer: &mut Vec<u8>) -> Option<(String, Value)> {
+    let header_end = loop {
+        if let Some(position) = buffer.windows(4).position(|it| it == b"\r\n\r\n") {
+            break position + 4;
+        }
+        read_more(stream, buffer).await?;
+    };
+
+    let head = String::from_utf8_lossy(&buffer[..header_end]).to_string();
+    let path = head.split_whitespace().nth(1)?;
+    let method = path.rsplit('/').next()?.to_lowercase();
+    let header = |header: &str| {
+        head.lines()
+            .filter_map(|line| line.split_once(':'))
+            .find(|(name, _)| name.eq_ignore_ascii_case(header))
+            .map(|(_, value)| value.trim().to_owned())
+    };
+    let content_length = header("content-length")
+        .and_then(|value| value.parse::<usize>().ok())
+        .unwrap_or(0);
+
+    while buffer.len() < header_end + content_length {
+        read_more(stream, buffer).await?;
+    }
+
+    let content = String::from_utf8_lossy(&buffer[header_end..header_end + content_length]).to_string();
+    let body = match header("content-type")
+        .as_deref()
+        .and_then(|value| value.split_once("boundary="))
+    {
+        Some((_, boundary)) => parse_multipart(&content, boundary.trim_matches('"')),
+        None => serde_json::from_str(&content).unwrap_or(Value::Null),
+    };
+    buffer.drain(..header_end + content_length);
+
+    Some((method, body))
+}
+
+async fn read_more(stream: &mut TcpStream, buffer: &mut Vec<u8>) -> Option<()> {
+    let mut chunk = [0; 4096];
+
+    match stream.read(&mut chunk).await {
+        Ok(0) | Err(_) => None,
+        Ok(read) => {
+            buffer.extend_from_slice(&chunk[..read]);
+            Some(())
+        }
+    }
+}
+
+/// Collects the fields of a `multipart/form-data` body into an object, the
+/// uploaded files become objects with `file_name` and `content`.
+fn parse_multipart(content: &str, boundary: &str) -> Value {
+    let mut fields = serde_json::Map::new();
+
+    for part in content.split(&format!("--{boundary}")) {
+        let Some((headers, value)) = part.split_once("\r\n\r\n") else {
+            continue;
+        };
+        let value = value.strip_suffix("\r\n").unwrap_or(value);
+        let attribute = |name: &str| {
+            headers
+                .split(';')
+                .filter_map(|it| it.trim().strip_prefix(&format!("{name}=\"")))
+                .map(|it| it.trim_end_matches('"').to_owned())
+                .next()
+        };
+
+        let Some(name) = attribute("name") else {
+            continue;
+        };
+        let value = match attribute("filename") {
+            Some(file_name) => json!({ "file_name": file_name, "content": value }),
+            // Nested objects, e.g. a reply markup, are sent as JSON.
+            None => serde_json::from_str(value).unwrap_or_else(|_| json!(value)),
+        };
+        fields.insert(name, value);
+    }
+
+    Value::Object(fields)
+}
+
+/// The sent or edited message as the Bot API would return it.
+fn answer(method: &str, body: &Value) -> Value {
+    let mut message = json!({
+        "message_id": body["message_id"].as_i64().unwrap_or(1),
+        "date": 0,
+        "chat": { "id": body["chat_id"], "type": "private", "first_name": "John" },
+    });
+
+    match method {
+        "sendmessage" | "editmessagetext" => {
+            message["text"] = body["text"].clone();
+            message
+        }
+        "senddocument" => {
+            message["document"] = json!({ "file_id": "file", "file_unique_id": "file" });
+            message["caption"] = body["caption"].clone();
+            message
+        }
+        _ => json!(true),
+    }
+}
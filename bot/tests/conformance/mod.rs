@@ -0,0 +1,427 @@
+//! Behaviour every storage backend has to share. Each suite expects the
+//! storage to be empty.
+use bot::messages::{self, Message, MessageType, RetentionPolicy};
+use bot::outages::{self, NewOutage};
+use bot::preferences::{self, ChatPreference, DeliveryMode, Language, LeadTime, NotificationPreference, QuietHours};
+use bot::storage::{forget_chat, Storage};
+use bot::subscriptions::{self, NewSubscription};
+use chrono::NaiveDate;
+use itertools::Itertools;
+
+const CHAT_ID_1: i64 = 1001;
+
+const CHAT_ID_2: i64 = 1002;
+
+const FIRST_ADDRESS: &str = "kneza miloša 5";
+
+const SECOND_ADDRESS: &str = "bulevar oslobođenja 10";
+
+async fn subscribe<R: subscriptions::Repository>(repository: &R, chat_id: i64, address: &str) {
+    repository
+        .append(NewSubscription {
+            chat_id,
+            address: address.to_string(),
+        })
+        .await
+        .expect("subscribe to an address");
+}
+
+async fn addresses_of<R: subscriptions::Repository>(repository: &R, chat_id: i64) -> Vec<String> {
+    repository
+        .find_all_by_chat_id(chat_id)
+        .await
+        .expect("find subscriptions of a chat")
+        .into_iter()
+        .map(|it| it.address)
+        .sorted()
+        .collect()
+}
+
+async fn subscribers_of<R: subscriptions::Repository>(repository: &R, address: &str) -> Vec<i64> {
+    repository
+        .find_all_by_addresses(vec![address.to_string()])
+        .await
+        .expect("find subscriptions by address")
+        .into_iter()
+        .map(|it| it.chat_id)
+        .sorted()
+        .collect()
+}
+
+async fn all_subscriptions<R: subscriptions::Repository>(repository: &R) -> Vec<(i64, String)> {
+    let mut subscriptions = repository
+        .find_all()
+        .await
+        .expect("find all subscriptions")
+        .into_iter()
+        .map(|it| (it.chat_id, it.address))
+        .collect::<Vec<_>>();
+    subscriptions.sort();
+
+    subscriptions
+}
+
+pub async fn subscriptions<R: subscriptions::Repository>(repository: &R) {
+    assert!(addresses_of(repository, CHAT_ID_1).await.is_empty());
+
+    subscribe(repository, CHAT_ID_1, FIRST_ADDRESS).await;
+    subscribe(repository, CHAT_ID_1, SECOND_ADDRESS).await;
+    subscribe(repository, CHAT_ID_2, FIRST_ADDRESS).await;
+
+    assert_eq!(
+        addresses_of(repository, CHAT_ID_1).await,
+        vec![SECOND_ADDRESS, FIRST_ADDRESS]
+    );
+    assert_eq!(
+        subscribers_of(repository, FIRST_ADDRESS).await,
+        vec![CHAT_ID_1, CHAT_ID_2]
+    );
+    assert!(subscribers_of(repository, "unknown street 1")
+        .await
+        .is_empty());
+    // Streets are matched with or without the house number, ignoring the letter case.
+    assert_eq!(
+        subscribers_of(repository, "KNEZA MILOŠA 5").await,
+        vec![CHAT_ID_1, CHAT_ID_2]
+    );
+    assert_eq!(
+        subscribers_of(repository, "Kneza Miloša").await,
+        vec![CHAT_ID_1, CHAT_ID_2]
+    );
+    assert_eq!(
+        subscribers_of(repository, "Bulevar Oslobođenja").await,
+        vec![CHAT_ID_1]
+    );
+    assert!(subscribers_of(repository, "kneza miloša 50")
+        .await
+        .is_empty());
+    assert_eq!(
+        repository
+            .find_all_by_addresses(vec![
+                "kneza miloša".to_string(),
+                "BULEVAR OSLOBOĐENJA".to_string(),
+            ])
+            .await
+            .expect("find subscriptions by addresses")
+            .into_iter()
+            .map(|it| (it.chat_id, it.address))
+            .sorted()
+            .collect::<Vec<_>>(),
+        vec![
+            (CHAT_ID_1, SECOND_ADDRESS.to_string()),
+            (CHAT_ID_1, FIRST_ADDRESS.to_string()),
+            (CHAT_ID_2, FIRST_ADDRESS.to_string()),
+        ]
+    );
+    assert_eq!(
+        all_subscriptions(repository).await,
+        vec![
+            (CHAT_ID_1, SECOND_ADDRESS.to_string()),
+            (CHAT_ID_1, FIRST_ADDRESS.to_string()),
+            (CHAT_ID_2, FIRST_ADDRESS.to_string()),
+        ]
+    );
+
+    let first = repository
+        .find_all_by_chat_id(CHAT_ID_1)
+        .await
+        .expect("find subscriptions of a chat")
+        .into_iter()
+        .find(|it| it.address == FIRST_ADDRESS)
+        .expect("the subscription is stored");
+
+    // Another chat cannot delete the subscription.
+    repository
+        .delete(CHAT_ID_2, first.id)
+        .await
+        .expect("delete a subscription of another chat");
+    assert_eq!(addresses_of(repository, CHAT_ID_1).await.len(), 2);
+
+    repository
+        .delete(CHAT_ID_1, first.id)
+        .await
+        .expect("delete a subscription");
+    assert_eq!(
+        addresses_of(repository, CHAT_ID_1).await,
+        vec![SECOND_ADDRESS]
+    );
+    assert_eq!(
+        subscribers_of(repository, FIRST_ADDRESS).await,
+        vec![CHAT_ID_2]
+    );
+
+    repository
+        .delete(CHAT_ID_1, first.id)
+        .await
+        .expect("deleting a missing subscription is a no-op");
+
+    repository
+        .delete_all_by_chat_id(CHAT_ID_2)
+        .await
+        .expect("delete all subscriptions of a chat");
+    assert!(addresses_of(repository, CHAT_ID_2).await.is_empty());
+    assert!(subscribers_of(repository, FIRST_ADDRESS).await.is_empty());
+    assert_eq!(
+        subscribers_of(repository, SECOND_ADDRESS).await,
+        vec![CHAT_ID_1]
+    );
+
+    repository
+        .delete_all_by_chat_id(CHAT_ID_1)
+        .await
+        .expect("delete all subscriptions of a chat");
+    assert!(all_subscriptions(repository).await.is_empty());
+}
+
+async fn append<R: messages::Repository>(repository: &R, chat_id: i64, text: &str, message_type: MessageType) {
+    repository
+        .append(Message {
+            chat_id,
+            text: text.to_string(),
+            message_type,
+        })
+        .await
+        .expect("append a message");
+}
+
+async fn last_text<R: messages::Repository>(repository: &R, chat_id: i64, message_type: MessageType) -> Option<String> {
+    repository
+        .find_last(chat_id, message_type)
+        .await
+        .expect("find the last message")
+        .map(|it| it.text)
+}
+
+pub async fn messages<R: messages::Repository>(repository: &R) {
+    assert_eq!(
+        last_text(repository, CHAT_ID_1, MessageType::Text).await,
+        None
+    );
+
+    append(repository, CHAT_ID_1, "first", MessageType::Text).await;
+    append(repository, CHAT_ID_1, "/start", MessageType::Command).await;
+    append(repository, CHAT_ID_1, "second", MessageType::Text).await;
+    append(repository, CHAT_ID_2, "other", MessageType::Text).await;
+
+    assert_eq!(
+        last_text(repository, CHAT_ID_1, MessageType::Text).await,
+        Some("second".to_string())
+    );
+    assert_eq!(
+        last_text(repository, CHAT_ID_1, MessageType::Command).await,
+        Some("/start".to_string())
+    );
+
+    repository
+        .purge(&RetentionPolicy::default())
+        .await
+        .expect("purge messages");
+    assert_eq!(
+        last_text(repository, CHAT_ID_1, MessageType::Text).await,
+        Some("second".to_string())
+    );
+
+    repository
+        .delete_all_by_chat_id(CHAT_ID_1)
+        .await
+        .expect("delete messages of a chat");
+    assert_eq!(
+        last_text(repository, CHAT_ID_1, MessageType::Text).await,
+        None
+    );
+    assert_eq!(
+        last_text(repository, CHAT_ID_1, MessageType::Command).await,
+        None
+    );
+    assert_eq!(
+        last_text(repository, CHAT_ID_2, MessageType::Text).await,
+        Some("other".to_string())
+    );
+
+    repository
+        .delete_all_by_chat_id(CHAT_ID_2)
+        .await
+        .expect("delete messages of a chat");
+}
+
+pub async fn preferences<R: preferences::Repository>(repository: &R) {
+    assert!(repository
+        .find_one(CHAT_ID_1)
+        .await
+        .expect("find preferences")
+        .is_none());
+
+    repository
+        .insert(ChatPreference::new(CHAT_ID_1, Language::Ru))
+        .await
+        .expect("insert preferences");
+
+    let found = repository
+        .find_one(CHAT_ID_1)
+        .await
+        .expect("find preferences")
+        .expect("preferences are stored");
+    assert_eq!(found.language, Language::Ru);
+    assert_eq!(found.notifications, NotificationPreference::default());
+    assert!(found.active);
+
+    let notifications = NotificationPreference::new(
+        LeadTime::SameDay,
+        DeliveryMode::Digest,
+        Some(QuietHours::new(23, 8).unwrap()),
+    );
+    repository
+        .update_language(CHAT_ID_1, Language::Rs)
+        .await
+        .expect("update language");
+    repository
+        .update_notifications(CHAT_ID_1, notifications)
+        .await
+        .expect("update notifications");
+    repository
+        .update_active(CHAT_ID_1, false)
+        .await
+        .expect("update active");
+
+    let found = repository
+        .find_one(CHAT_ID_1)
+        .await
+        .expect("find preferences")
+        .expect("preferences are stored");
+    assert_eq!(found.language, Language::Rs);
+    assert_eq!(found.notifications, notifications);
+    assert!(!found.active);
+
+    repository
+        .delete(CHAT_ID_1)
+        .await
+        .expect("delete preferences");
+    assert!(repository
+        .find_one(CHAT_ID_1)
+        .await
+        .expect("find preferences")
+        .is_none());
+}
+
+pub async fn outages<R: outages::Repository>(repository: &R) {
+    let day = |day| NaiveDate::from_ymd_opt(2023, 11, day).unwrap();
+    let outage = |date, street: &str, settlement: Option<&str>| NewOutage {
+        date,
+        time: "08:00 - 16:00".to_string(),
+        region: "Palilula".to_string(),
+        settlement: settlement.map(ToOwned::to_owned),
+        street: street.to_string(),
+    };
+
+    assert!(repository
+        .find_since(day(1))
+        .await
+        .expect("find outages")
+        .is_empty());
+
+    let outages = vec![
+        outage(day(19), "takovska", None),
+        outage(day(20), "kneza miloša", None),
+        outage(day(21), "bulevar oslobođenja", Some("Krnjača")),
+    ];
+    for outage in &outages {
+        repository
+            .append(outage.clone())
+            .await
+            .expect("append an outage");
+    }
+
+    let found = repository
+        .find_since(day(20))
+        .await
+        .expect("find outages")
+        .into_iter()
+        .sorted_by_key(|it| it.date)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        found
+            .iter()
+            .map(|it| NewOutage {
+                date: it.date,
+                time: it.time.to_owned(),
+                region: it.region.to_owned(),
+                settlement: it.settlement.to_owned(),
+                street: it.street.to_owned(),
+            })
+            .collect::<Vec<_>>(),
+        outages[1..]
+    );
+
+    let on_day = repository
+        .find_on(day(20))
+        .await
+        .expect("find outages of a day");
+    assert_eq!(on_day, found[..1]);
+    assert!(repository
+        .find_on(day(22))
+        .await
+        .expect("find outages of a day")
+        .is_empty());
+
+    for outage in &found {
+        assert_eq!(
+            repository
+                .find_by_id(&outage.id)
+                .await
+                .expect("find an outage by id")
+                .as_ref(),
+            Some(outage)
+        );
+    }
+    assert!(repository
+        .find_by_id("unknown")
+        .await
+        .expect("find an outage by id")
+        .is_none());
+}
+
+/// Runs every suite and checks that forgetting a chat leaves others intact.
+pub async fn storage<S: Storage>(storage: &S) {
+    subscriptions(storage.subscriptions()).await;
+    messages(storage.messages()).await;
+    preferences(storage.preferences()).await;
+    outages(storage.outages()).await;
+
+    for chat_id in [CHAT_ID_1, CHAT_ID_2] {
+        subscribe(storage.subscriptions(), chat_id, FIRST_ADDRESS).await;
+        append(storage.messages(), chat_id, "hello", MessageType::Text).await;
+        preferences::Repository::insert(
+            storage.preferences(),
+            ChatPreference::new(chat_id, Language::En),
+        )
+        .await
+        .expect("insert preferences");
+    }
+
+    forget_chat(storage, CHAT_ID_1)
+        .await
+        .expect("forget a chat");
+
+    assert!(addresses_of(storage.subscriptions(), CHAT_ID_1)
+        .await
+        .is_empty());
+    assert_eq!(
+        last_text(storage.messages(), CHAT_ID_1, MessageType::Text).await,
+        None
+    );
+    assert!(
+        preferences::Repository::find_one(storage.preferences(), CHAT_ID_1)
+            .await
+            .expect("find preferences")
+            .is_none()
+    );
+
+    assert_eq!(
+        subscribers_of(storage.subscriptions(), FIRST_ADDRESS).await,
+        vec![CHAT_ID_2]
+    );
+    assert_eq!(
+        last_text(storage.messages(), CHAT_ID_2, MessageType::Text).await,
+        Some("hello".to_string())
+    );
+}
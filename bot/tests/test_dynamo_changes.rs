@@ -0,0 +1,87 @@
+#![cfg(feature = "dyndb_int")]
+use aws_config::ConfigLoader;
+use aws_sdk_dynamodb::types::AttributeValue;
+use aws_sdk_dynamodb::Client;
+use chrono::NaiveDate;
+use electricity::changes;
+use electricity::db::changes_table_name;
+use electricity::db::table_spec::{ensure_tables, Mode};
+use electricity::diff::{Change, Place};
+use std::collections::BTreeSet;
+use testcontainers::core::WaitFor;
+use testcontainers::*;
+
+fn removed(street: &str) -> Change {
+    Change::Removed {
+        place: Place {
+            date: NaiveDate::from_ymd_opt(2023, 11, 20).unwrap(),
+            region: "Palilula".to_string(),
+            settlement: None,
+            street: street.to_string(),
+        },
+        time: "08:00 - 16:00".to_string(),
+        buildings: vec!["7".to_string()],
+    }
+}
+
+#[tokio::test]
+async fn testing_dynamodb_changes_queue() {
+    let docker = clients::Cli::default();
+    let image = GenericImage::new("amazon/dynamodb-local", "2.0.0")
+        .with_exposed_port(8000)
+        .with_wait_for(WaitFor::message_on_stdout("Initializing DynamoDB Local"));
+    let node = docker.run(image);
+    let dynamodb_port = node.get_host_port_ipv4(8000);
+
+    let config = ConfigLoader::default()
+        .endpoint_url(format!("http://localhost:{dynamodb_port}"))
+        .load()
+        .await;
+
+    let client = Client::new(&config);
+    let table = changes_table_name();
+
+    ensure_tables(&client, &electricity::db::tables(), Mode::Provision)
+        .await
+        .expect("create DB tables");
+
+    let (takovska, cara_dusana) = (removed("takovska"), removed("cara dušana"));
+    changes::publish(&client, &table, &[&takovska, &cara_dusana])
+        .await
+        .expect("publish changes");
+    // A malformed item doesn't hold back the other changes.
+    client
+        .put_item()
+        .table_name(&table)
+        .item("id", AttributeValue::S("malformed".to_string()))
+        .item("change", AttributeValue::S("not a change".to_string()))
+        .send()
+        .await
+        .expect("put a malformed change");
+
+    let pending = changes::pending(&client, &table)
+        .await
+        .expect("read changes");
+    assert_eq!(
+        pending.iter().map(|it| &it.change).collect::<Vec<_>>(),
+        vec![&takovska, &cara_dusana]
+    );
+    assert!(pending.iter().all(|it| it.notified.is_empty()));
+
+    changes::mark_notified(&client, &table, &pending[0].id, &[5, 6])
+        .await
+        .expect("mark chats notified");
+    changes::mark_notified(&client, &table, &pending[0].id, &[7])
+        .await
+        .expect("mark another chat notified");
+    changes::delete(&client, &table, &pending[1].id)
+        .await
+        .expect("delete a change");
+
+    let left = changes::pending(&client, &table)
+        .await
+        .expect("read changes");
+    assert_eq!(left.len(), 1);
+    assert_eq!(left[0].change, takovska);
+    assert_eq!(left[0].notified, BTreeSet::from([5, 6, 7]));
+}
@@ -0,0 +1,72 @@
+#![cfg(feature = "dyndb_int")]
+
+mod conformance;
+
+use aws_config::ConfigLoader;
+use aws_sdk_dynamodb::types::AttributeValue;
+use aws_sdk_dynamodb::Client;
+use bot::messages::RetentionPolicy;
+use bot::outages::{NewOutage, Repository as _};
+use bot::storage::{dynamo_tables, DynamoStorage};
+use chrono::NaiveDate;
+use electricity::db::data_table_name;
+use electricity::db::table_spec::{ensure_tables, Mode};
+use testcontainers::core::WaitFor;
+use testcontainers::*;
+
+#[tokio::test]
+async fn testing_dynamodb_storage_conformance() {
+    let docker = clients::Cli::default();
+    let image = GenericImage::new("amazon/dynamodb-local", "2.0.0")
+        .with_exposed_port(8000)
+        .with_wait_for(WaitFor::message_on_stdout("Initializing DynamoDB Local"));
+    let node = docker.run(image);
+    let dynamodb_port = node.get_host_port_ipv4(8000);
+
+    let config = ConfigLoader::default()
+        .endpoint_url(format!("http://localhost:{dynamodb_port}"))
+        .load()
+        .await;
+
+    let client = Client::new(&config);
+
+    ensure_tables(&client, &dynamo_tables(), Mode::Provision)
+        .await
+        .expect("create DB tables");
+
+    conformance::storage(&DynamoStorage::new(
+        client.clone(),
+        RetentionPolicy::default(),
+    ))
+    .await;
+
+    // A malformed item of the parser's table doesn't hide the other outages.
+    let date = NaiveDate::from_ymd_opt(2030, 1, 1).unwrap();
+    client
+        .put_item()
+        .table_name(data_table_name())
+        .item("id", AttributeValue::S("malformed".to_string()))
+        .item("date", AttributeValue::S("not a date".to_string()))
+        .send()
+        .await
+        .expect("put a malformed outage");
+    client
+        .append(NewOutage {
+            date,
+            time: "08:00 - 16:00".to_string(),
+            region: "Palilula".to_string(),
+            settlement: None,
+            street: "takovska".to_string(),
+        })
+        .await
+        .expect("append an outage");
+
+    let outages = client.find_since(date).await.expect("find outages");
+    assert_eq!(
+        outages
+            .iter()
+            .map(|it| it.street.as_str())
+            .collect::<Vec<_>>(),
+        vec!["takovska"]
+    );
+}
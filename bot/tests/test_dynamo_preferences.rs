@@ -1,11 +1,9 @@
 #![cfg(feature = "dyndb_int")]
 use aws_config::ConfigLoader;
-use aws_sdk_dynamodb::types::{
-    AttributeDefinition, KeySchemaElement, KeyType, ProvisionedThroughput, ScalarAttributeType,
-};
 use aws_sdk_dynamodb::Client;
 use bot::preferences::Language;
 use bot::preferences::*;
+use electricity::db::table_spec::{ensure_tables, Mode};
 use testcontainers::core::WaitFor;
 use testcontainers::*;
 
@@ -27,40 +25,12 @@ async fn testing_dynamodb() {
 
     let client = Client::new(&config);
 
-    let request = client
-        .create_table()
-        .table_name("chat_preferences")
-        .key_schema(
-            KeySchemaElement::builder()
-                .attribute_name("chat_id") // partition key
-                .key_type(KeyType::Hash)
-                .build()
-                .unwrap(),
-        )
-        .attribute_definitions(
-            AttributeDefinition::builder()
-                .attribute_name("chat_id")
-                .attribute_type(ScalarAttributeType::N)
-                .build()
-                .unwrap(),
-        )
-        .provisioned_throughput(
-            ProvisionedThroughput::builder()
-                .read_capacity_units(5) // adjust as necessary
-                .write_capacity_units(5) // adjust as necessary
-                .build()
-                .unwrap(),
-        );
-    request
-        .send()
+    ensure_tables(&client, &dynamo_tables(), Mode::Provision)
         .await
         .expect("failed to create a dynamodb table");
 
     client
-        .insert(ChatPreference {
-            chat_id: CHAT_ID,
-            language: Language::Ru,
-        })
+        .insert(ChatPreference::new(CHAT_ID, Language::Ru))
         .await
         .expect("failed to insert a value into dynamodb table");
 
@@ -86,4 +56,38 @@ async fn testing_dynamodb() {
         panic!("failed to find chat preferences for the updated record")
     };
     assert_eq!(res.language, Language::Rs);
+    assert_eq!(res.notifications, NotificationPreference::default());
+
+    let notifications = NotificationPreference::new(
+        LeadTime::AllDays,
+        DeliveryMode::Digest,
+        Some(QuietHours::new(22, 7).unwrap()),
+    );
+    client
+        .update_notifications(CHAT_ID, notifications)
+        .await
+        .expect("failed to update notification preferences");
+
+    let Some(res) = client
+        .find_one(CHAT_ID)
+        .await
+        .expect("failed to connect to dynamodb to find notification preferences")
+    else {
+        panic!("failed to find chat preferences for the updated notifications")
+    };
+    assert_eq!(res.notifications, notifications);
+
+    client
+        .update_notifications(CHAT_ID, notifications.with_quiet_hours(None))
+        .await
+        .expect("failed to remove quiet hours");
+
+    let Some(res) = client
+        .find_one(CHAT_ID)
+        .await
+        .expect("failed to connect to dynamodb to find notification preferences")
+    else {
+        panic!("failed to find chat preferences without quiet hours")
+    };
+    assert_eq!(res.notifications.quiet_hours(), None);
 }
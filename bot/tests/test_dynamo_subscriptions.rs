@@ -1,13 +1,10 @@
 #![cfg(feature = "dyndb_int")]
 
 use aws_config::ConfigLoader;
-use aws_sdk_dynamodb::types::{
-    AttributeDefinition, KeySchemaElement, KeyType, ProvisionedThroughput, ScalarAttributeType,
-};
 use aws_sdk_dynamodb::Client;
 use bot::subscriptions::*;
+use electricity::db::table_spec::{ensure_tables, Mode};
 use itertools::Itertools;
-use std::error::Error;
 use testcontainers::clients::Cli;
 use testcontainers::core::WaitFor;
 use testcontainers::*;
@@ -32,7 +29,9 @@ async fn testing_subscriptions_dynamodb_access() {
 
     let client = Client::new(&config);
 
-    create_db_tables(&client).await.expect("create DB tables");
+    ensure_tables(&client, &dynamo_tables(), Mode::Provision)
+        .await
+        .expect("create DB tables");
 
     client
         .append(NewSubscription {
@@ -58,8 +57,11 @@ async fn testing_subscriptions_dynamodb_access() {
         .await
         .expect("add third address to DB");
 
-    let res = client.find_all_by_chat_id(CHAT_ID_1 + 10).await;
-    assert!(res.is_err());
+    let res = client
+        .find_all_by_chat_id(CHAT_ID_1 + 10)
+        .await
+        .expect("unknown chats have no subscriptions");
+    assert!(res.is_empty());
 
     let res = client
         .find_all_by_chat_id(CHAT_ID_1)
@@ -88,60 +90,52 @@ async fn testing_subscriptions_dynamodb_access() {
         .expect("receive address data");
     dbg!(&subs);
     assert_eq!(subs.len(), 3);
-}
 
-async fn create_db_tables(client: &Client) -> Result<(), Box<dyn Error>> {
-    let request = client
-        .create_table()
-        .table_name("subscriptions")
-        .key_schema(
-            KeySchemaElement::builder()
-                .attribute_name("chat_id") // partition key
-                .key_type(KeyType::Hash)
-                .build()?,
-        )
-        .attribute_definitions(
-            AttributeDefinition::builder()
-                .attribute_name("chat_id")
-                .attribute_type(ScalarAttributeType::N)
-                .build()?,
-        )
-        .provisioned_throughput(
-            ProvisionedThroughput::builder()
-                .read_capacity_units(5) // adjust as necessary
-                .write_capacity_units(5) // adjust as necessary
-                .build()?,
-        );
-    request
-        .send()
+    let first = res
+        .iter()
+        .find(|it| it.address == "first address")
+        .expect("find the first address subscription");
+    client
+        .delete(CHAT_ID_1, first.id)
+        .await
+        .expect("delete the first address subscription");
+
+    let res = client
+        .find_all_by_chat_id(CHAT_ID_1)
+        .await
+        .expect("failed to get CHAT_ID_1 addresses after deletion");
+    assert_eq!(
+        res.iter().map(|it| it.address.clone()).collect::<Vec<_>>(),
+        vec!["second address".to_string()]
+    );
+
+    let subs = client
+        .find_all_by_addresses(vec!["first address".to_string()])
         .await
-        .expect("failed to create subscriptions");
-
-    let request = client
-        .create_table()
-        .table_name("subscriptions_inv")
-        .key_schema(
-            KeySchemaElement::builder()
-                .attribute_name("addresses") // partition key
-                .key_type(KeyType::Hash)
-                .build()?,
-        )
-        .attribute_definitions(
-            AttributeDefinition::builder()
-                .attribute_name("addresses")
-                .attribute_type(ScalarAttributeType::S)
-                .build()?,
-        )
-        .provisioned_throughput(
-            ProvisionedThroughput::builder()
-                .read_capacity_units(5) // adjust as necessary
-                .write_capacity_units(5) // adjust as necessary
-                .build()?,
-        );
-    request
-        .send()
+        .expect("receive address data after deletion");
+    assert_eq!(
+        subs.iter().map(|it| it.chat_id).collect::<Vec<_>>(),
+        vec![CHAT_ID_2]
+    );
+
+    client
+        .delete(CHAT_ID_1, first.id)
         .await
-        .expect("failed to create subscriptions_inv");
+        .expect("deleting a missing subscription is a no-op");
 
-    Ok(())
+    client
+        .delete_all_by_chat_id(CHAT_ID_2)
+        .await
+        .expect("delete all subscriptions of CHAT_ID_2");
+
+    let subs = client
+        .find_all_by_addresses(vec!["first address".to_string()])
+        .await
+        .expect("receive address data after deleting all subscriptions");
+    assert!(subs.is_empty());
+
+    client
+        .delete_all_by_chat_id(CHAT_ID_2)
+        .await
+        .expect("deleting subscriptions of an unknown chat is a no-op");
 }
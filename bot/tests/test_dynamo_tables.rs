@@ -0,0 +1,52 @@
+#![cfg(feature = "dyndb_int")]
+use aws_config::ConfigLoader;
+use aws_sdk_dynamodb::Client;
+use bot::storage::dynamo_tables;
+use electricity::db::table_spec::{ensure_tables, KeySpec, Mode, TableSpec, TableState};
+use testcontainers::core::WaitFor;
+use testcontainers::*;
+
+#[tokio::test]
+async fn testing_dynamodb_tables_provisioning() {
+    let docker = clients::Cli::default();
+    let image = GenericImage::new("amazon/dynamodb-local", "2.0.0")
+        .with_exposed_port(8000)
+        .with_wait_for(WaitFor::message_on_stdout("Initializing DynamoDB Local"));
+    let node = docker.run(image);
+    let dynamodb_port = node.get_host_port_ipv4(8000);
+
+    let config = ConfigLoader::default()
+        .endpoint_url(format!("http://localhost:{dynamodb_port}"))
+        .load()
+        .await;
+
+    let client = Client::new(&config);
+    let specs = [dynamo_tables(), electricity::db::tables()].concat();
+
+    ensure_tables(&client, &specs, Mode::Verify)
+        .await
+        .expect_err("tables are not created yet");
+
+    let states = ensure_tables(&client, &specs, Mode::Provision)
+        .await
+        .expect("failed to provision tables");
+    assert!(states
+        .iter()
+        .all(|(_, state)| *state == TableState::Created));
+
+    let states = ensure_tables(&client, &specs, Mode::Provision)
+        .await
+        .expect("failed to provision tables for the second time");
+    assert!(states
+        .iter()
+        .all(|(_, state)| *state == TableState::UpToDate));
+
+    ensure_tables(&client, &specs, Mode::Verify)
+        .await
+        .expect("provisioned tables do not match their specs");
+
+    let conflicting = TableSpec::new("chat_preferences", KeySpec::s("chat_id"));
+    ensure_tables(&client, &[conflicting], Mode::Provision)
+        .await
+        .expect_err("key type mismatch is not reported");
+}
@@ -0,0 +1,8 @@
+mod conformance;
+
+use bot::storage::InMemoryStorage;
+
+#[tokio::test]
+async fn testing_in_memory_storage_conformance() {
+    conformance::storage(&InMemoryStorage::default()).await;
+}
@@ -1,48 +1,49 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use electricity::replay::html_files;
 use electricity::{parse_raw_data_to_data, ElectricityFailuresRawData};
+use std::fs;
+use std::path::Path;
+
+/// The pages of the regression corpus, see `tests/fixtures`.
+fn corpus() -> Vec<ElectricityFailuresRawData> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+
+    html_files(&[dir])
+        .expect("list the fixtures")
+        .into_iter()
+        .map(|path| ElectricityFailuresRawData {
+            id: path.display().to_string(),
+            date: String::from("01-01-2021"),
+            url: path.display().to_string(),
+            html: fs::read_to_string(&path).expect("read the fixture"),
+            hash: String::from("hash"),
+            version: 1,
+        })
+        .collect()
+}
 
 pub fn benchmark(c: &mut Criterion) {
-    let data = ElectricityFailuresRawData {
-        id: String::from("id"),
-        date: String::from("01-01-2021"),
-        url: String::from("url"),
-        html: String::from(
-            r#"
-            <html>
-                <body>
-                    <table>
-                        <tbody>
-                            <tr>
-                                <td>
-                                    <b>Скопје - Центар - 01.01.2021</b>
-                                </td>
-                            </tr>
-                        </tbody>
-                    </table>
-                    <table>
-                        <tbody>
-                            <tr>
-                                <td>Општина</td>
-                                <td>Време</td>
-                                <td>Улице</td>
-                            </tr>
-                            <tr>
-                                <td>Центар</td>
-                                <td>08:00 - 16:00</td>
-                                <td>Бул. Климент Охридски: 43-46</td>
-                            </tr>
-                        </tbody>
-                    </table>
-                </body>
-            </html>
-        "#,
-        ),
-        hash: String::from("hash"),
-        version: 1,
-    };
+    let corpus = corpus();
+
+    for data in &corpus {
+        let name = Path::new(&data.id)
+            .file_stem()
+            .unwrap()
+            .to_string_lossy()
+            .into_owned();
+
+        c.bench_function(&format!("parse_data/{name}"), |b| {
+            b.iter(|| parse_raw_data_to_data(black_box(data)))
+        });
+    }
 
-    c.bench_function("parse_data", |b| {
-        b.iter(|| parse_raw_data_to_data(black_box(&data)))
+    c.bench_function("parse_data/corpus", |b| {
+        b.iter(|| {
+            corpus
+                .iter()
+                .map(|data| parse_raw_data_to_data(black_box(data)))
+                .collect::<Vec<_>>()
+        })
     });
 }
 
@@ -0,0 +1,17 @@
+#![no_main]
+
+use electricity::{parse_raw_data_to_data, ElectricityFailuresRawData};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|html: &str| {
+    let data = ElectricityFailuresRawData {
+        id: String::from("id"),
+        date: String::from("01-01-2021"),
+        url: String::from("url"),
+        html: html.to_owned(),
+        hash: String::from("hash"),
+        version: 1,
+    };
+
+    let _ = parse_raw_data_to_data(&data);
+});
@@ -0,0 +1,8 @@
+#![no_main]
+
+use electricity::time_interval::TimeInterval;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: &str| {
+    let _ = TimeInterval::parse(input);
+});
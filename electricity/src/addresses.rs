@@ -12,12 +12,12 @@ use nom::error::VerboseError;
 use nom::multi::{many0, many1, separated_list1};
 use nom::sequence::{delimited, pair, preceded, separated_pair};
 use nom::{Err, IResult};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::fmt::Display;
 
 type AddrError<'a> = VerboseError<&'a str>;
 
-#[derive(Eq, PartialEq, Clone, Debug, Serialize)]
+#[derive(Eq, PartialEq, Clone, Debug, Serialize, Deserialize)]
 pub(crate) struct Number {
     value: usize,
     extension: Option<String>,
@@ -50,7 +50,7 @@ impl Display for Number {
     }
 }
 
-#[derive(Eq, PartialEq, Clone, Debug, Serialize)]
+#[derive(Eq, PartialEq, Clone, Debug, Serialize, Deserialize)]
 pub(crate) struct Range {
     from: Number,
     to: Number,
@@ -86,7 +86,7 @@ impl Display for Range {
     }
 }
 
-#[derive(Eq, PartialEq, Clone, Debug, Serialize)]
+#[derive(Eq, PartialEq, Clone, Debug, Serialize, Deserialize)]
 pub(crate) enum Building {
     /// A building without number (Bez Broj).
     Bb(Option<String>),
@@ -172,21 +172,43 @@ impl Display for Address {
     }
 }
 
+/// The addresses are not in the format the grammar understands.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AddressError {
+    /// The input doesn't match the grammar, with the trace of the parser.
+    Invalid(String),
+    /// The input ends in the middle of an address.
+    Incomplete,
+}
+
+impl Display for AddressError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AddressError::Invalid(trace) => write!(f, "invalid addresses: {trace}"),
+            AddressError::Incomplete => write!(f, "incomplete addresses"),
+        }
+    }
+}
+
+impl std::error::Error for AddressError {}
+
 #[derive(Clone, Debug)]
 pub(crate) struct AddressRow {
     items: Vec<Address>,
 }
 
 impl AddressRow {
-    pub fn parse(input: &str) -> anyhow::Result<Self> {
-        let r = address_row(input)
+    pub fn iter(&self) -> impl Iterator<Item = &Address> {
+        self.items.iter()
+    }
+
+    pub fn parse(input: &str) -> Result<Self, AddressError> {
+        address_row(input)
             .map(|(_, items)| Self { items })
             .map_err(|e| match e {
-                Err::Error(err) | Err::Failure(err) => anyhow::Error::msg(nom::error::convert_error(input, err)),
-                Err::Incomplete(_) => unreachable!("incomplete address string"),
-            })?;
-
-        Ok(r)
+                Err::Error(err) | Err::Failure(err) => AddressError::Invalid(nom::error::convert_error(input, err)),
+                Err::Incomplete(_) => AddressError::Incomplete,
+            })
     }
 }
 
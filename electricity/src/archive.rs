@@ -0,0 +1,140 @@
+//! Content-addressed archive of the raw pages. A page is stored under the
+//! SHA-256 of its content, so every distinct version is kept exactly once,
+//! and the DynamoDB table of the raw data only refers to it by the hash.
+//!
+//! Pages are kept on the local filesystem or in an S3-compatible store, the
+//! backend is selected by [`ArchiveConfig::from_env`].
+use anyhow::{anyhow, bail, Result};
+use sha2::{Digest, Sha256};
+use std::future::Future;
+
+mod fs;
+mod s3;
+
+pub use fs::FsArchive;
+pub use s3::S3Archive;
+
+pub trait Archive {
+    /// Stores the content unless it is already there, returns its hash.
+    fn put(&self, content: &[u8]) -> impl Future<Output = Result<String>> + Send;
+    fn get(&self, hash: &str) -> impl Future<Output = Result<Option<Vec<u8>>>> + Send;
+}
+
+/// Hex encoded SHA-256 of the content.
+pub fn content_hash(content: &[u8]) -> String {
+    hex::encode(Sha256::digest(content))
+}
+
+/// Location of the content relative to the root of the archive. Contents are
+/// spread over directories by the first byte of the hash, so none of them
+/// grows too large.
+fn key(hash: &str) -> Result<String> {
+    // The hash can come from the user, it must not escape the archive.
+    if !is_content_hash(hash) {
+        bail!("invalid content hash {hash}");
+    }
+
+    Ok(format!("{}/{hash}", &hash[..2]))
+}
+
+/// Whether the text is a hash made by [`content_hash`].
+pub fn is_content_hash(hash: &str) -> bool {
+    hash.len() == 64
+        && hash
+            .bytes()
+            .all(|it| it.is_ascii_digit() || (b'a'..=b'f').contains(&it))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArchiveConfig {
+    Fs {
+        root: String,
+    },
+    S3 {
+        bucket: String,
+        prefix: String,
+        /// Endpoint of an S3-compatible store, e.g. MinIO, AWS otherwise.
+        endpoint: Option<String>,
+    },
+}
+
+impl ArchiveConfig {
+    /// Reads `ARCHIVE_BACKEND`, which is `fs` (by default) or `s3`. The
+    /// filesystem archive is kept in `ARCHIVE_PATH` (`archive` by default),
+    /// the S3 one in `ARCHIVE_BUCKET` under `ARCHIVE_PREFIX` (`pages` by
+    /// default) and `ARCHIVE_ENDPOINT` points to an S3-compatible store.
+    pub fn from_env() -> Result<Self> {
+        let backend = dotenvy::var("ARCHIVE_BACKEND").unwrap_or("fs".to_owned());
+
+        match backend.to_lowercase().as_str() {
+            "fs" => Ok(ArchiveConfig::Fs {
+                root: dotenvy::var("ARCHIVE_PATH").unwrap_or("archive".to_owned()),
+            }),
+            "s3" => Ok(ArchiveConfig::S3 {
+                bucket: dotenvy::var("ARCHIVE_BUCKET")
+                    .map_err(|_| anyhow!("ARCHIVE_BUCKET is required for the s3 archive"))?,
+                prefix: dotenvy::var("ARCHIVE_PREFIX").unwrap_or("pages".to_owned()),
+                endpoint: dotenvy::var("ARCHIVE_ENDPOINT").ok(),
+            }),
+            backend => Err(anyhow!("unknown archive backend {backend}")),
+        }
+    }
+
+    pub async fn open(&self) -> Result<AnyArchive> {
+        match self {
+            ArchiveConfig::Fs { root } => Ok(AnyArchive::Fs(FsArchive::new(root))),
+            ArchiveConfig::S3 {
+                bucket,
+                prefix,
+                endpoint,
+            } => Ok(AnyArchive::S3(
+                S3Archive::connect(bucket, prefix, endpoint.as_deref()).await,
+            )),
+        }
+    }
+}
+
+/// An archive selected at runtime.
+pub enum AnyArchive {
+    Fs(FsArchive),
+    S3(S3Archive),
+}
+
+impl Archive for AnyArchive {
+    async fn put(&self, content: &[u8]) -> Result<String> {
+        match self {
+            AnyArchive::Fs(archive) => archive.put(content).await,
+            AnyArchive::S3(archive) => archive.put(content).await,
+        }
+    }
+
+    async fn get(&self, hash: &str) -> Result<Option<Vec<u8>>> {
+        match self {
+            AnyArchive::Fs(archive) => archive.get(hash).await,
+            AnyArchive::S3(archive) => archive.get(hash).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_content_hash() {
+        assert_eq!(
+            content_hash(b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+
+    #[test]
+    fn test_key() {
+        let hash = content_hash(b"page");
+
+        assert_eq!(key(&hash).unwrap(), format!("{}/{hash}", &hash[..2]));
+        assert!(key("../../etc/passwd").is_err());
+        assert!(key(&hash.to_uppercase()).is_err());
+        assert!(key(&hash[1..]).is_err());
+    }
+}
@@ -0,0 +1,85 @@
+use super::{content_hash, key, Archive};
+use anyhow::{Context, Result};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use uuid::Uuid;
+
+pub struct FsArchive {
+    root: PathBuf,
+}
+
+impl FsArchive {
+    pub fn new(root: impl AsRef<Path>) -> Self {
+        Self {
+            root: root.as_ref().to_owned(),
+        }
+    }
+}
+
+impl Archive for FsArchive {
+    async fn put(&self, content: &[u8]) -> Result<String> {
+        let hash = content_hash(content);
+        let path = self.root.join(key(&hash)?);
+
+        if fs::try_exists(&path).await? {
+            return Ok(hash);
+        }
+
+        let directory = path.parent().expect("the key has a directory");
+        fs::create_dir_all(directory)
+            .await
+            .with_context(|| format!("failed to create {}", directory.display()))?;
+
+        // Written aside and renamed, so a content is either complete or missing.
+        let temporary = directory.join(format!(".{}", Uuid::new_v4()));
+        fs::write(&temporary, content)
+            .await
+            .with_context(|| format!("failed to write {}", temporary.display()))?;
+        fs::rename(&temporary, &path).await?;
+
+        Ok(hash)
+    }
+
+    async fn get(&self, hash: &str) -> Result<Option<Vec<u8>>> {
+        let path = self.root.join(key(hash)?);
+
+        match fs::read(&path).await {
+            Ok(content) => Ok(Some(content)),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err).with_context(|| format!("failed to read {}", path.display())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_put_and_get() {
+        let root = tempfile::tempdir().unwrap();
+        let archive = FsArchive::new(root.path());
+
+        let hash = archive.put(b"page").await.unwrap();
+        assert_eq!(hash, content_hash(b"page"));
+        assert_eq!(archive.put(b"page").await.unwrap(), hash);
+        assert_eq!(archive.get(&hash).await.unwrap(), Some(b"page".to_vec()));
+
+        let other = archive.put(b"other page").await.unwrap();
+        assert_ne!(other, hash);
+        assert_eq!(
+            archive.get(&other).await.unwrap(),
+            Some(b"other page".to_vec())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_unknown() {
+        let root = tempfile::tempdir().unwrap();
+        let archive = FsArchive::new(root.path());
+
+        assert_eq!(archive.get(&content_hash(b"page")).await.unwrap(), None);
+        assert!(archive.get("../page").await.is_err());
+    }
+}
@@ -0,0 +1,184 @@
+use super::{content_hash, key, Archive};
+use anyhow::{Context, Result};
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::Client;
+
+pub struct S3Archive {
+    client: Client,
+    bucket: String,
+    prefix: String,
+}
+
+impl S3Archive {
+    pub fn new(client: Client, bucket: &str, prefix: &str) -> Self {
+        Self {
+            client,
+            bucket: bucket.to_owned(),
+            prefix: prefix.trim_matches('/').to_owned(),
+        }
+    }
+
+    /// Connects with the standard AWS configuration, to a custom endpoint
+    /// when it is given. Such stores are usually addressed by path rather
+    /// than by subdomains of the bucket.
+    pub async fn connect(bucket: &str, prefix: &str, endpoint: Option<&str>) -> Self {
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+        let mut builder = aws_sdk_s3::config::Builder::from(&config);
+        if let Some(endpoint) = endpoint {
+            builder = builder.endpoint_url(endpoint).force_path_style(true);
+        }
+
+        Self::new(Client::from_conf(builder.build()), bucket, prefix)
+    }
+
+    fn object_key(&self, hash: &str) -> Result<String> {
+        let key = key(hash)?;
+
+        Ok(if self.prefix.is_empty() {
+            key
+        } else {
+            format!("{}/{key}", self.prefix)
+        })
+    }
+}
+
+impl Archive for S3Archive {
+    async fn put(&self, content: &[u8]) -> Result<String> {
+        let hash = content_hash(content);
+        let key = self.object_key(&hash)?;
+
+        let exists = match self
+            .client
+            .head_object()
+            .bucket(&self.bucket)
+            .key(&key)
+            .send()
+            .await
+        {
+            Ok(_) => true,
+            Err(err) if err.as_service_error().is_some_and(|err| err.is_not_found()) => false,
+            Err(err) => return Err(err).with_context(|| format!("failed to check {key}")),
+        };
+
+        if !exists {
+            self.client
+                .put_object()
+                .bucket(&self.bucket)
+                .key(&key)
+                .content_type("text/html; charset=utf-8")
+                .body(ByteStream::from(content.to_vec()))
+                .send()
+                .await
+                .with_context(|| format!("failed to upload {key}"))?;
+        }
+
+        Ok(hash)
+    }
+
+    async fn get(&self, hash: &str) -> Result<Option<Vec<u8>>> {
+        let key = self.object_key(hash)?;
+
+        let response = match self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(&key)
+            .send()
+            .await
+        {
+            Ok(response) => response,
+            Err(err)
+                if err
+                    .as_service_error()
+                    .is_some_and(|err| err.is_no_such_key()) =>
+            {
+                return Ok(None)
+            }
+            Err(err) => return Err(err).with_context(|| format!("failed to download {key}")),
+        };
+        let content = response.body.collect().await?;
+
+        Ok(Some(content.into_bytes().to_vec()))
+    }
+}
+
+#[cfg(all(test, feature = "s3_int"))]
+mod tests {
+    use super::*;
+    use aws_sdk_s3::config::{BehaviorVersion, Credentials, Region};
+    use testcontainers::core::WaitFor;
+    use testcontainers::*;
+
+    const BUCKET: &str = "archive";
+
+    #[tokio::test]
+    async fn test_minio() {
+        let docker = clients::Cli::default();
+        let image = GenericImage::new("minio/minio", "RELEASE.2023-11-20T22-40-07Z")
+            .with_env_var("MINIO_ROOT_USER", "minio")
+            .with_env_var("MINIO_ROOT_PASSWORD", "minio_password")
+            .with_exposed_port(9000)
+            .with_wait_for(WaitFor::message_on_stdout("API:"));
+        let node = docker.run(RunnableImage::from((
+            image,
+            vec!["server".to_string(), "/data".to_string()],
+        )));
+        let port = node.get_host_port_ipv4(9000);
+
+        let config = aws_sdk_s3::config::Builder::new()
+            .behavior_version(BehaviorVersion::latest())
+            .region(Region::new("us-east-1"))
+            .credentials_provider(Credentials::new(
+                "minio",
+                "minio_password",
+                None,
+                None,
+                "test",
+            ))
+            .endpoint_url(format!("http://localhost:{port}"))
+            .force_path_style(true)
+            .build();
+        let client = Client::from_conf(config);
+        client
+            .create_bucket()
+            .bucket(BUCKET)
+            .send()
+            .await
+            .expect("create the bucket");
+        let archive = S3Archive::new(client.clone(), BUCKET, "/pages/");
+
+        let hash = archive.put(b"page").await.unwrap();
+        assert_eq!(hash, content_hash(b"page"));
+        assert_eq!(archive.put(b"page").await.unwrap(), hash);
+        assert_eq!(archive.get(&hash).await.unwrap(), Some(b"page".to_vec()));
+
+        let other = archive.put(b"other page").await.unwrap();
+        assert_eq!(
+            archive.get(&other).await.unwrap(),
+            Some(b"other page".to_vec())
+        );
+
+        // Every version is kept once, under the prefix.
+        let objects = client
+            .list_objects_v2()
+            .bucket(BUCKET)
+            .send()
+            .await
+            .expect("list the objects");
+        let mut keys = objects
+            .contents()
+            .iter()
+            .filter_map(|it| it.key())
+            .collect::<Vec<_>>();
+        keys.sort_unstable();
+        let mut expected = vec![
+            format!("pages/{}/{hash}", &hash[..2]),
+            format!("pages/{}/{other}", &other[..2]),
+        ];
+        expected.sort_unstable();
+        assert_eq!(keys, expected);
+
+        assert_eq!(archive.get(&content_hash(b"missing")).await.unwrap(), None);
+        assert!(archive.get("../page").await.is_err());
+    }
+}
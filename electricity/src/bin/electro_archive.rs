@@ -0,0 +1,56 @@
+//! Inspects the archive of the raw pages.
+//!
+//! Usage: `electro_archive [list [url]|get <hash>]`
+//!
+//! * `list` prints the stored versions of every page, or of the given one:
+//!   the page, its date, version, hash and the id of the item;
+//! * `get` prints the archived page with the hash.
+//!
+//! The archive is configured by [`electricity::archive::ArchiveConfig::from_env`].
+use anyhow::{bail, Context, Result};
+use dotenvy::dotenv;
+use electricity::archive::{Archive, ArchiveConfig};
+use electricity::db::{init_custom_client, raw_data_table_name};
+use electricity::list_raw_versions;
+use std::env;
+use std::io::{stdout, Write};
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let _ = dotenv();
+
+    let args = env::args().skip(1).collect::<Vec<_>>();
+
+    match args
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        [] | ["list"] | ["list", _] => {
+            let db_client = init_custom_client().await?;
+            let versions = list_raw_versions(
+                &db_client,
+                &raw_data_table_name(),
+                args.get(1).map(String::as_str),
+            )
+            .await?;
+
+            for version in versions {
+                println!("{version}");
+            }
+        }
+        ["get", hash] => {
+            let archive = ArchiveConfig::from_env()?.open().await?;
+            let content = archive
+                .get(hash)
+                .await?
+                .with_context(|| format!("page {hash} is not archived"))?;
+
+            stdout().write_all(&content)?;
+        }
+        _ => bail!("usage: electro_archive [list [url]|get <hash>]"),
+    }
+
+    Ok(())
+}
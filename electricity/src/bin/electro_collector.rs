@@ -1,7 +1,10 @@
 //! Lambda function that downloads electricity time table and
 //! stores it into the database.
-use anyhow::Result;
-use electricity::db::init_client;
+use anyhow::{bail, Result};
+use electricity::archive::{AnyArchive, ArchiveConfig};
+use electricity::changes;
+use electricity::db::{changes_table_name, init_client};
+use electricity::fetcher::{Fetcher, FetcherConfig};
 use electricity::BEOGRAD_ELECTRICITY_PAGES;
 use lambda_runtime::{service_fn, LambdaEvent};
 use serde_json::Value;
@@ -23,7 +26,14 @@ async fn main() -> Result<()> {
         .without_time()
         .init();
 
-    let func = service_fn(electro_handler);
+    // The fetcher remembers the validators of the pages, so it is kept while
+    // the function is warm.
+    let fetcher = Fetcher::new(FetcherConfig::default())?;
+    let archive = ArchiveConfig::from_env()?.open().await?;
+
+    let (fetcher, archive) = (&fetcher, &archive);
+    let func =
+        service_fn(move |event: LambdaEvent<Value>| async move { electro_handler(fetcher, archive, event).await });
     if let Err(e) = lambda_runtime::run(func).await {
         tracing::error!("Error: {}", e);
         std::process::exit(1);
@@ -32,8 +42,27 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
-async fn electro_handler(_: LambdaEvent<Value>) -> Result<()> {
+async fn electro_handler(fetcher: &Fetcher, archive: &AnyArchive, _: LambdaEvent<Value>) -> Result<()> {
     let db_client = init_client().await?;
     let raw_data_table_name = env::var("RAW_DATA_TABLE_NAME")?;
-    electricity::collect_data(&db_client, &raw_data_table_name, BEOGRAD_ELECTRICITY_PAGES).await
+    let summary = electricity::collect_data(
+        fetcher,
+        archive,
+        &db_client,
+        &raw_data_table_name,
+        BEOGRAD_ELECTRICITY_PAGES,
+    )
+    .await;
+
+    // The bot tells the subscribers, see the notify_changes binary.
+    let changes = summary.changes().collect::<Vec<_>>();
+    changes::publish(&db_client, &changes_table_name(), &changes).await?;
+
+    // Single pages are picked up by the next run, the invocation fails only
+    // when nothing could be collected, so the retry policy applies.
+    if summary.failed() == summary.pages.len() {
+        bail!("failed to collect every page:\n{summary}");
+    }
+
+    Ok(())
 }
@@ -1,7 +1,10 @@
 //! An utility to run electro collection right from the command line.
 use anyhow::{Ok, Result};
 use dotenvy::dotenv;
-use electricity::db::init_custom_client;
+use electricity::archive::ArchiveConfig;
+use electricity::changes;
+use electricity::db::{changes_table_name, init_custom_client};
+use electricity::fetcher::{Fetcher, FetcherConfig};
 use electricity::BEOGRAD_ELECTRICITY_PAGES;
 use std::env;
 
@@ -12,7 +15,21 @@ async fn main() -> Result<()> {
     let db_client = init_custom_client().await?;
     let raw_data_table_name = env::var("RAW_DATA_TABLE_NAME").unwrap_or("electricity_failures_raw".to_owned());
 
-    electricity::collect_data(&db_client, &raw_data_table_name, BEOGRAD_ELECTRICITY_PAGES).await?;
+    let fetcher = Fetcher::new(FetcherConfig::default())?;
+    let archive = ArchiveConfig::from_env()?.open().await?;
+
+    let summary = electricity::collect_data(
+        &fetcher,
+        &archive,
+        &db_client,
+        &raw_data_table_name,
+        BEOGRAD_ELECTRICITY_PAGES,
+    )
+    .await;
+    print!("{summary}");
+
+    let changes = summary.changes().collect::<Vec<_>>();
+    changes::publish(&db_client, &changes_table_name(), &changes).await?;
 
     Ok(())
 }
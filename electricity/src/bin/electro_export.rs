@@ -0,0 +1,50 @@
+//! Exports the parsed outages to the standard output.
+//!
+//! Usage: `electro_export <csv|geojson> <from> [to]`, dates are `YYYY-MM-DD`
+//! and `to` is the same as `from` by default.
+//!
+//! The outages are read from the storage selected by `STORAGE_BACKEND`, see
+//! [`electricity::export::Source::from_env`]. Rows are geocoded when
+//! `OSM_DATABASE_URL` points to an OSM database.
+use anyhow::{bail, Context, Result};
+use chrono::NaiveDate;
+use dotenvy::dotenv;
+use electricity::export::{to_geojson, write_csv, Geocoder, Source};
+use std::env;
+use std::io::{stdout, Write};
+
+fn parse_date(date: &str) -> Result<NaiveDate> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").with_context(|| format!("invalid date {date}, expected YYYY-MM-DD"))
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let _ = dotenv();
+
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let (format, from, to) = match args.as_slice() {
+        [format, from] => (format, parse_date(from)?, parse_date(from)?),
+        [format, from, to] => (format, parse_date(from)?, parse_date(to)?),
+        _ => bail!("usage: electro_export <csv|geojson> <from> [to]"),
+    };
+    if format != "csv" && format != "geojson" {
+        bail!("unknown format {format}, expected one of: csv, geojson");
+    }
+
+    let source = Source::from_env().await?;
+    let mut rows = source.rows(from, to).await?;
+
+    if let Some(mut geocoder) = Geocoder::from_env().await? {
+        geocoder.locate_all(&mut rows).await?;
+    }
+
+    let mut output = stdout().lock();
+    if format == "csv" {
+        write_csv(&rows, &mut output)?;
+    } else {
+        serde_json::to_writer_pretty(&mut output, &to_geojson(&rows))?;
+        writeln!(output)?;
+    }
+
+    Ok(())
+}
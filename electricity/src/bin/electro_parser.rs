@@ -7,7 +7,9 @@ use std::str::FromStr;
 
 use serde_json::Value;
 
+use electricity::archive::ArchiveConfig;
 use electricity::db::init_client;
+use electricity::db::outages;
 use electricity::parse_all_records;
 
 #[tokio::main]
@@ -36,13 +38,31 @@ async fn main() -> Result<()> {
 
 pub(crate) async fn my_handler(_: LambdaEvent<Value>) -> Result<()> {
     let db_client = init_client().await?;
+    let archive = ArchiveConfig::from_env()?.open().await?;
 
     let raw_data_table_name = env::var("RAW_DATA_TABLE_NAME")?;
     let data_table_name = env::var("DATA_TABLE_NAME")?;
+    let outages_pool = outages::connect_from_env().await?;
 
     // NOTE we need to scan the table for raw data, but something needs to check if
     // it is parsed so that it is not reparsed again.
-    parse_all_records(&db_client, &raw_data_table_name, &data_table_name).await?;
+    let result = parse_all_records(
+        &db_client,
+        &archive,
+        &raw_data_table_name,
+        &data_table_name,
+        outages_pool.as_ref(),
+    )
+    .await;
 
-    Ok(())
+    match result {
+        // The invocation fails, so the retry policy applies.
+        Err(err) if err.is_transient() => Err(err.into()),
+        // Retrying won't help, the error is logged for the alarm instead.
+        Err(err) => {
+            tracing::error!(error = %err, "failed to parse the pages");
+            Ok(())
+        }
+        _ => Ok(()),
+    }
 }
@@ -0,0 +1,58 @@
+//! Parses saved pages of the schedule without AWS, to debug the parser.
+//!
+//! Usage: `electro_replay [--json] [--streets] <file|directory>...`
+//!
+//! Prints the outages of every page as a table, or as JSON with `--json`,
+//! together with the rows the parser skips. `--streets` adds a report per
+//! day of the streets which are not found in `beograd_streets`, to stderr
+//! along with JSON. Directories are replayed by
+//! their `.htm` and `.html` files, e.g. pages taken with
+//! `electro_archive get <hash>`.
+use anyhow::{bail, Result};
+use electricity::replay::{html_files, replay_file, to_json, write_street_reports, write_table};
+use std::env;
+use std::io::{stderr, stdout, Write};
+use std::path::PathBuf;
+
+fn main() -> Result<()> {
+    let mut json = false;
+    let mut streets = false;
+    let mut paths = vec![];
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--json" => json = true,
+            "--streets" => streets = true,
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+    if paths.is_empty() {
+        bail!("usage: electro_replay [--json] [--streets] <file|directory>...");
+    }
+
+    let replays = html_files(&paths)?
+        .iter()
+        .map(|path| replay_file(path))
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut output = stdout().lock();
+    if json {
+        serde_json::to_writer_pretty(&mut output, &to_json(&replays))?;
+        writeln!(output)?;
+    } else {
+        write_table(&replays, &mut output)?;
+    }
+
+    if streets && json {
+        write_street_reports(&replays, stderr().lock())?;
+    } else if streets {
+        writeln!(output)?;
+        write_street_reports(&replays, &mut output)?;
+    }
+
+    let failed = replays.iter().filter(|it| it.error.is_some()).count();
+    if failed > 0 {
+        bail!("{failed} of {} pages could not be parsed", replays.len());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,158 @@
+//! Queue of the schedule changes found by the collector, read by the bot.
+//! Every change is an item of its own which remembers the chats told about
+//! it, so a chat deferred by its preferences gets only what it hasn't seen.
+use crate::diff::Change;
+use anyhow::{anyhow, Context, Result};
+use aws_sdk_dynamodb::types::AttributeValue;
+use aws_sdk_dynamodb::Client;
+use std::collections::{BTreeSet, HashMap};
+use tracing::{event, Level};
+use uuid::Uuid;
+
+const ID_FIELD: &str = "id";
+const CREATED_AT_FIELD: &str = "created_at";
+const POSITION_FIELD: &str = "position";
+const CHANGE_FIELD: &str = "change";
+const NOTIFIED_FIELD: &str = "notified";
+
+/// A change which is not delivered to every chat yet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingChange {
+    pub id: String,
+    pub change: Change,
+    /// Chats which have been told about the change.
+    pub notified: BTreeSet<i64>,
+}
+
+/// Stores the changes of a collector run, they are read back in this order.
+pub async fn publish(client: &Client, table_name: &str, changes: &[&Change]) -> Result<()> {
+    let created_at = chrono::Utc::now().timestamp();
+
+    for (position, change) in changes.iter().enumerate() {
+        client
+            .put_item()
+            .table_name(table_name)
+            .item(ID_FIELD, AttributeValue::S(Uuid::new_v4().to_string()))
+            .item(CREATED_AT_FIELD, AttributeValue::N(created_at.to_string()))
+            .item(POSITION_FIELD, AttributeValue::N(position.to_string()))
+            .item(
+                CHANGE_FIELD,
+                AttributeValue::S(serde_json::to_string(change)?),
+            )
+            .send()
+            .await
+            .with_context(|| format!("failed to publish change {change}"))?;
+    }
+
+    Ok(())
+}
+
+/// Changes waiting for delivery, the oldest first.
+pub async fn pending(client: &Client, table_name: &str) -> Result<Vec<PendingChange>> {
+    let mut changes = vec![];
+    let mut start_key = None;
+
+    loop {
+        let response = client
+            .scan()
+            .table_name(table_name)
+            .set_exclusive_start_key(start_key)
+            .send()
+            .await?;
+
+        for item in response.items() {
+            // A malformed item doesn't hold back the other changes.
+            match to_pending_change(item) {
+                Ok(change) => changes.push(change),
+                Err(err) => event!(Level::WARN, error = %err, "skipping change item"),
+            }
+        }
+
+        start_key = response.last_evaluated_key;
+        if start_key.is_none() {
+            break;
+        }
+    }
+
+    changes.sort_by_key(|(order, _)| *order);
+
+    Ok(changes.into_iter().map(|(_, change)| change).collect())
+}
+
+/// Remembers that the chats have been told about the change.
+pub async fn mark_notified(client: &Client, table_name: &str, id: &str, chats: &[i64]) -> Result<()> {
+    if chats.is_empty() {
+        return Ok(());
+    }
+
+    client
+        .update_item()
+        .table_name(table_name)
+        .key(ID_FIELD, AttributeValue::S(id.to_owned()))
+        .update_expression("ADD #notified :chats")
+        .expression_attribute_names("#notified", NOTIFIED_FIELD)
+        .expression_attribute_values(
+            ":chats",
+            AttributeValue::Ns(chats.iter().map(ToString::to_string).collect()),
+        )
+        .send()
+        .await
+        .with_context(|| format!("failed to update change {id}"))?;
+
+    Ok(())
+}
+
+/// Removes a change which no chat is waiting for anymore.
+pub async fn delete(client: &Client, table_name: &str, id: &str) -> Result<()> {
+    client
+        .delete_item()
+        .table_name(table_name)
+        .key(ID_FIELD, AttributeValue::S(id.to_owned()))
+        .send()
+        .await
+        .with_context(|| format!("failed to delete change {id}"))?;
+
+    Ok(())
+}
+
+fn to_pending_change(item: &HashMap<String, AttributeValue>) -> Result<((i64, i64), PendingChange)> {
+    let field = |name: &str| item.get(name).with_context(|| format!("{name} is missing"));
+    let number = |name: &str| -> Result<i64> {
+        let value = field(name)?
+            .as_n()
+            .map_err(|_| anyhow!("{name} is not a number"))?;
+
+        value
+            .parse()
+            .with_context(|| format!("invalid {name} {value}"))
+    };
+
+    let id = field(ID_FIELD)?
+        .as_s()
+        .map_err(|_| anyhow!("{ID_FIELD} is not a string"))?
+        .to_owned();
+    let change = field(CHANGE_FIELD)?
+        .as_s()
+        .map_err(|_| anyhow!("{CHANGE_FIELD} is not a string"))?;
+    let change = serde_json::from_str(change).with_context(|| format!("invalid change {change}"))?;
+    let notified = match item.get(NOTIFIED_FIELD) {
+        Some(chats) => chats
+            .as_ns()
+            .map_err(|_| anyhow!("{NOTIFIED_FIELD} is not a number set"))?
+            .iter()
+            .map(|it| it.parse().with_context(|| format!("invalid chat {it}")))
+            .collect::<Result<_>>()?,
+        None => BTreeSet::new(),
+    };
+
+    let order = (number(CREATED_AT_FIELD)?, number(POSITION_FIELD)?);
+
+    Ok((
+        order,
+        PendingChange {
+            id,
+            change,
+            notified,
+        },
+    ))
+}
@@ -1,10 +1,14 @@
 use anyhow::{Ok, Result};
 use aws_sdk_dynamodb::{
     config::{Config, Region},
-    types::{AttributeDefinition, KeySchemaElement, KeyType, ProvisionedThroughput, ScalarAttributeType},
     Client,
 };
 use std::env;
+use std::str::FromStr;
+use table_spec::{ensure_table, KeySpec, Mode, TableSpec};
+
+pub mod outages;
+pub mod table_spec;
 
 // TODO - this does not look right
 async fn make_custom_config() -> Result<Config> {
@@ -49,39 +53,80 @@ pub async fn check_table_exists(client: &Client, name: &str) -> Result<bool> {
     Ok(exists)
 }
 
-pub async fn create_table(client: &Client, table: &str, key: &str) -> Result<()> {
-    let table_exists = check_table_exists(client, table).await?;
+/// The parser keeps dates as `dd-mm-yyyy`.
+pub const DATE_FORMAT: &str = "%d-%m-%Y";
+
+/// Where the bot and the API keep their data.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Backend {
+    Postgres,
+    DynamoDb,
+}
 
-    if table_exists {
-        return Ok(());
+impl Backend {
+    /// Reads the backend from `STORAGE_BACKEND`, `postgres` by default.
+    pub fn from_env() -> Result<Self> {
+        dotenvy::var("STORAGE_BACKEND").map_or(Ok(Backend::Postgres), |backend| Backend::from_str(&backend))
     }
+}
+
+impl FromStr for Backend {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "postgres" | "postgresql" => Ok(Backend::Postgres),
+            "dynamodb" => Ok(Backend::DynamoDb),
+            backend => Err(anyhow::anyhow!("unknown storage backend {backend}")),
+        }
+    }
+}
+
+/// Name of the table with the raw pages, `RAW_DATA_TABLE_NAME` environment variable.
+pub fn raw_data_table_name() -> String {
+    env::var("RAW_DATA_TABLE_NAME").unwrap_or("electricity_failures_raw".to_owned())
+}
 
-    let a_name: String = key.into();
-    let table_name: String = table.into();
-
-    let ad = AttributeDefinition::builder()
-        .attribute_name(&a_name)
-        .attribute_type(ScalarAttributeType::S)
-        .build()?;
-
-    let ks = KeySchemaElement::builder()
-        .attribute_name(&a_name)
-        .key_type(KeyType::Hash)
-        .build()?;
-
-    let pt = ProvisionedThroughput::builder()
-        .read_capacity_units(10)
-        .write_capacity_units(5)
-        .build()?;
-
-    let _ = client
-        .create_table()
-        .table_name(table_name)
-        .key_schema(ks)
-        .attribute_definitions(ad)
-        .provisioned_throughput(pt)
-        .send()
-        .await?;
+/// Name of the table with the parsed outages, `DATA_TABLE_NAME` environment variable.
+pub fn data_table_name() -> String {
+    env::var("DATA_TABLE_NAME").unwrap_or("electricity_failures".to_owned())
+}
+
+/// Name of the queue of the schedule changes, `CHANGES_TABLE_NAME` environment variable.
+pub fn changes_table_name() -> String {
+    env::var("CHANGES_TABLE_NAME").unwrap_or("electricity_changes".to_owned())
+}
+
+/// Tables of the collector and the parser.
+pub fn tables() -> Vec<TableSpec> {
+    [
+        vec![TableSpec::new(&raw_data_table_name(), KeySpec::s("id"))],
+        crate::outages::dynamo_tables(),
+        vec![TableSpec::new(&changes_table_name(), KeySpec::s("id"))],
+    ]
+    .concat()
+}
+
+/// Creates a table with a single string hash key unless it already exists.
+pub async fn create_table(client: &Client, table: &str, key: &str) -> Result<()> {
+    ensure_table(
+        client,
+        &TableSpec::new(table, KeySpec::s(key)),
+        Mode::Provision,
+    )
+    .await?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_backend() {
+        assert_eq!(Backend::from_str("postgres").unwrap(), Backend::Postgres);
+        assert_eq!(Backend::from_str("DynamoDB").unwrap(), Backend::DynamoDb);
+        assert!(Backend::from_str("redis").is_err());
+    }
+}
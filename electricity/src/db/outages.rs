@@ -0,0 +1,51 @@
+//! The `outages` table of the bot's Postgres database, filled by the parser
+//! with a row per address. The table and its unique index are created by the
+//! bot's migrations.
+use crate::db::DATE_FORMAT;
+use crate::ElectricityFailuresData;
+use anyhow::{Context, Result};
+use chrono::NaiveDate;
+use sqlx::postgres::PgPoolOptions;
+use sqlx::PgPool;
+
+/// Connects to the database from `POSTGRESQL_URL`, `None` when the variable
+/// is not set and the outages are kept in DynamoDB only.
+pub async fn connect_from_env() -> Result<Option<PgPool>> {
+    let Ok(url) = dotenvy::var("POSTGRESQL_URL") else {
+        return Ok(None);
+    };
+    let pool = PgPoolOptions::new()
+        .max_connections(1)
+        .connect(&url)
+        .await?;
+
+    Ok(Some(pool))
+}
+
+/// Writes the addresses of the outages, rows which are already in the table
+/// are skipped. Returns how many rows were added.
+pub async fn save_outages(pool: &PgPool, outages: &[ElectricityFailuresData]) -> Result<u64> {
+    let mut added = 0;
+
+    for outage in outages {
+        let date = NaiveDate::parse_from_str(&outage.date, DATE_FORMAT)
+            .with_context(|| format!("invalid date {}", outage.date))?;
+
+        for address in outage.addresses.iter() {
+            let result = sqlx::query(
+                "INSERT INTO outages (date, time, region, settlement, street) VALUES ($1, $2, $3, $4, $5) \
+                 ON CONFLICT DO NOTHING",
+            )
+            .bind(date)
+            .bind(outage.time.trim())
+            .bind(outage.region.trim())
+            .bind(address.settlement.as_deref())
+            .bind(&address.street)
+            .execute(pool)
+            .await?;
+            added += result.rows_affected();
+        }
+    }
+
+    Ok(added)
+}
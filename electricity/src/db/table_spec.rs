@@ -0,0 +1,598 @@
+//! Declarative description of DynamoDB tables. A [`TableSpec`] lists the keys,
+//! global secondary indexes and the TTL attribute of a table, and
+//! [`ensure_table`] either provisions the table, adding the indexes an existing
+//! one lacks, or verifies that an existing one matches the spec. Tables are always created with on-demand billing,
+//! and an existing table with another billing mode is reported.
+use anyhow::{bail, Context, Result};
+use aws_sdk_dynamodb::types::{
+    AttributeDefinition, BillingMode, CreateGlobalSecondaryIndexAction, GlobalSecondaryIndex,
+    GlobalSecondaryIndexUpdate, IndexStatus, KeySchemaElement, KeyType, Projection, ProjectionType,
+    ScalarAttributeType, TableDescription, TableStatus, TimeToLiveSpecification, TimeToLiveStatus,
+};
+use aws_sdk_dynamodb::Client;
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+use tracing::{event, Level};
+
+/// How many times the table status is polled after its creation.
+const ACTIVE_POLL_ATTEMPTS: usize = 60;
+
+const ACTIVE_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeySpec {
+    pub name: String,
+    pub kind: ScalarAttributeType,
+}
+
+impl KeySpec {
+    /// A string key.
+    pub fn s(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            kind: ScalarAttributeType::S,
+        }
+    }
+
+    /// A number key.
+    pub fn n(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            kind: ScalarAttributeType::N,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexSpec {
+    pub name: String,
+    pub hash_key: KeySpec,
+    pub range_key: Option<KeySpec>,
+}
+
+impl IndexSpec {
+    pub fn new(name: &str, hash_key: KeySpec) -> Self {
+        Self {
+            name: name.to_owned(),
+            hash_key,
+            range_key: None,
+        }
+    }
+
+    pub fn with_range_key(self, range_key: KeySpec) -> Self {
+        Self {
+            range_key: Some(range_key),
+            ..self
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableSpec {
+    pub name: String,
+    pub hash_key: KeySpec,
+    pub range_key: Option<KeySpec>,
+    pub indexes: Vec<IndexSpec>,
+    /// Attribute holding the expiration time (epoch seconds) of an item.
+    pub ttl_attribute: Option<String>,
+}
+
+impl TableSpec {
+    pub fn new(name: &str, hash_key: KeySpec) -> Self {
+        Self {
+            name: name.to_owned(),
+            hash_key,
+            range_key: None,
+            indexes: vec![],
+            ttl_attribute: None,
+        }
+    }
+
+    pub fn with_range_key(self, range_key: KeySpec) -> Self {
+        Self {
+            range_key: Some(range_key),
+            ..self
+        }
+    }
+
+    pub fn with_index(mut self, index: IndexSpec) -> Self {
+        self.indexes.push(index);
+        self
+    }
+
+    pub fn with_ttl(self, attribute: &str) -> Self {
+        Self {
+            ttl_attribute: Some(attribute.to_owned()),
+            ..self
+        }
+    }
+
+    /// Every key attribute of the table and its indexes, each listed once.
+    fn key_attributes(&self) -> Vec<&KeySpec> {
+        let mut keys: Vec<&KeySpec> = vec![];
+        let all_keys = std::iter::once(&self.hash_key)
+            .chain(self.range_key.as_ref())
+            .chain(
+                self.indexes
+                    .iter()
+                    .flat_map(|it| std::iter::once(&it.hash_key).chain(it.range_key.as_ref())),
+            );
+
+        for key in all_keys {
+            if !keys.iter().any(|it| it.name == key.name) {
+                keys.push(key);
+            }
+        }
+
+        keys
+    }
+
+    /// Compares the spec with an existing table and lists the differences.
+    pub fn verify(&self, description: &TableDescription) -> Vec<String> {
+        let mut problems = vec![];
+
+        if key_schema(&self.hash_key, self.range_key.as_ref()) != description.key_schema() {
+            problems.push(format!(
+                "table \"{}\" has a different key schema",
+                self.name
+            ));
+        }
+
+        // Tables created before on-demand billing existed have no summary and
+        // are provisioned.
+        let billing_mode = description
+            .billing_mode_summary()
+            .and_then(|it| it.billing_mode())
+            .unwrap_or(&BillingMode::Provisioned);
+        if *billing_mode != BillingMode::PayPerRequest {
+            problems.push(format!(
+                "table \"{}\" has billing mode {}, expected {}",
+                self.name,
+                billing_mode.as_str(),
+                BillingMode::PayPerRequest.as_str()
+            ));
+        }
+
+        for index in &self.indexes {
+            let existing = description
+                .global_secondary_indexes()
+                .iter()
+                .find(|it| it.index_name() == Some(index.name.as_str()));
+
+            match existing {
+                None => problems.push(format!(
+                    "table \"{}\" is missing index \"{}\"",
+                    self.name, index.name
+                )),
+                Some(existing) if key_schema(&index.hash_key, index.range_key.as_ref()) != existing.key_schema() => {
+                    problems.push(format!(
+                        "index \"{}\" of table \"{}\" has a different key schema",
+                        index.name, self.name
+                    ))
+                }
+                Some(_) => {}
+            }
+        }
+
+        for definition in description.attribute_definitions() {
+            let expected = self
+                .key_attributes()
+                .into_iter()
+                .find(|it| it.name == definition.attribute_name());
+
+            if let Some(expected) = expected {
+                if expected.kind != *definition.attribute_type() {
+                    problems.push(format!(
+                        "attribute \"{}\" of table \"{}\" has type {}, expected {}",
+                        expected.name,
+                        self.name,
+                        definition.attribute_type().as_str(),
+                        expected.kind.as_str()
+                    ));
+                }
+            }
+        }
+
+        problems
+    }
+}
+
+fn key_schema(hash_key: &KeySpec, range_key: Option<&KeySpec>) -> Vec<KeySchemaElement> {
+    std::iter::once((hash_key, KeyType::Hash))
+        .chain(range_key.map(|it| (it, KeyType::Range)))
+        .map(|(key, key_type)| {
+            KeySchemaElement::builder()
+                .attribute_name(&key.name)
+                .key_type(key_type)
+                .build()
+                .expect("key schema element has all required fields")
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mode {
+    /// Create missing tables and indexes and enable missing TTL settings.
+    Provision,
+    /// Only report the differences, nothing is changed.
+    Verify,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TableState {
+    Created,
+    Updated,
+    UpToDate,
+}
+
+impl Display for TableState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TableState::Created => f.write_str("created"),
+            TableState::Updated => f.write_str("updated"),
+            TableState::UpToDate => f.write_str("up to date"),
+        }
+    }
+}
+
+async fn describe_table(client: &Client, name: &str) -> Result<Option<TableDescription>> {
+    match client.describe_table().table_name(name).send().await {
+        Ok(output) => Ok(output.table),
+        Err(err)
+            if err
+                .as_service_error()
+                .is_some_and(|it| it.is_resource_not_found_exception()) =>
+        {
+            Ok(None)
+        }
+        Err(err) => Err(err.into()),
+    }
+}
+
+fn global_secondary_index(index: &IndexSpec) -> Result<GlobalSecondaryIndex> {
+    Ok(GlobalSecondaryIndex::builder()
+        .index_name(&index.name)
+        .set_key_schema(Some(key_schema(&index.hash_key, index.range_key.as_ref())))
+        .projection(
+            Projection::builder()
+                .projection_type(ProjectionType::All)
+                .build(),
+        )
+        .build()?)
+}
+
+async fn create_table(client: &Client, spec: &TableSpec) -> Result<()> {
+    let attribute_definitions = spec
+        .key_attributes()
+        .into_iter()
+        .map(|key| {
+            AttributeDefinition::builder()
+                .attribute_name(&key.name)
+                .attribute_type(key.kind.clone())
+                .build()
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let indexes = spec
+        .indexes
+        .iter()
+        .map(global_secondary_index)
+        .collect::<Result<Vec<_>>>()?;
+
+    client
+        .create_table()
+        .table_name(&spec.name)
+        .billing_mode(BillingMode::PayPerRequest)
+        .set_key_schema(Some(key_schema(&spec.hash_key, spec.range_key.as_ref())))
+        .set_attribute_definitions(Some(attribute_definitions))
+        .set_global_secondary_indexes(if indexes.is_empty() {
+            None
+        } else {
+            Some(indexes)
+        })
+        .send()
+        .await?;
+
+    wait_until_active(client, spec).await
+}
+
+/// Polls the table until it and its indexes are active.
+async fn wait_until_active(client: &Client, spec: &TableSpec) -> Result<()> {
+    for _ in 0..ACTIVE_POLL_ATTEMPTS {
+        let description = describe_table(client, &spec.name).await?;
+        let active = description.is_some_and(|it| {
+            it.table_status == Some(TableStatus::Active)
+                && it
+                    .global_secondary_indexes()
+                    .iter()
+                    .all(|index| index.index_status == Some(IndexStatus::Active))
+        });
+
+        if active {
+            return Ok(());
+        }
+
+        tokio::time::sleep(ACTIVE_POLL_INTERVAL).await;
+    }
+
+    bail!("table \"{}\" has not become active", spec.name)
+}
+
+/// Indexes of the spec which the existing table doesn't have.
+fn missing_indexes<'a>(spec: &'a TableSpec, description: &TableDescription) -> Vec<&'a IndexSpec> {
+    spec.indexes
+        .iter()
+        .filter(|index| {
+            !description
+                .global_secondary_indexes()
+                .iter()
+                .any(|it| it.index_name() == Some(index.name.as_str()))
+        })
+        .collect()
+}
+
+/// Adds an index to an existing table, one at a time as DynamoDB requires.
+async fn create_index(client: &Client, spec: &TableSpec, index: &IndexSpec) -> Result<()> {
+    let attribute_definitions = spec
+        .key_attributes()
+        .into_iter()
+        .map(|key| {
+            AttributeDefinition::builder()
+                .attribute_name(&key.name)
+                .attribute_type(key.kind.clone())
+                .build()
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let index = global_secondary_index(index)?;
+
+    client
+        .update_table()
+        .table_name(&spec.name)
+        .set_attribute_definitions(Some(attribute_definitions))
+        .global_secondary_index_updates(
+            GlobalSecondaryIndexUpdate::builder()
+                .create(
+                    CreateGlobalSecondaryIndexAction::builder()
+                        .index_name(index.index_name())
+                        .set_key_schema(Some(index.key_schema().to_vec()))
+                        .set_projection(index.projection().cloned())
+                        .build()?,
+                )
+                .build(),
+        )
+        .send()
+        .await?;
+
+    wait_until_active(client, spec).await
+}
+
+/// Returns `true` when TTL is not enabled on the attribute from the spec.
+async fn is_ttl_missing(client: &Client, spec: &TableSpec, attribute: &str) -> Result<bool> {
+    let output = client
+        .describe_time_to_live()
+        .table_name(&spec.name)
+        .send()
+        .await?;
+    let enabled = output.time_to_live_description().is_some_and(|it| {
+        it.attribute_name() == Some(attribute)
+            && matches!(
+                it.time_to_live_status(),
+                Some(TimeToLiveStatus::Enabled | TimeToLiveStatus::Enabling)
+            )
+    });
+
+    Ok(!enabled)
+}
+
+async fn enable_ttl(client: &Client, spec: &TableSpec, attribute: &str) -> Result<()> {
+    client
+        .update_time_to_live()
+        .table_name(&spec.name)
+        .time_to_live_specification(
+            TimeToLiveSpecification::builder()
+                .enabled(true)
+                .attribute_name(attribute)
+                .build()?,
+        )
+        .send()
+        .await?;
+
+    Ok(())
+}
+
+/// Creates the table if it does not exist, otherwise checks that it matches
+/// the spec. Calling it repeatedly is safe.
+pub async fn ensure_table(client: &Client, spec: &TableSpec, mode: Mode) -> Result<TableState> {
+    let mut state = match describe_table(client, &spec.name).await? {
+        Some(description) if mode == Mode::Provision && !missing_indexes(spec, &description).is_empty() => {
+            for index in missing_indexes(spec, &description) {
+                create_index(client, spec, index).await.with_context(|| {
+                    format!(
+                        "failed to create index \"{}\" of \"{}\"",
+                        index.name, spec.name
+                    )
+                })?;
+                event!(
+                    Level::INFO,
+                    table = spec.name,
+                    index = index.name,
+                    "created index"
+                );
+            }
+
+            let problems = match describe_table(client, &spec.name).await? {
+                Some(description) => spec.verify(&description),
+                None => vec![format!("table \"{}\" does not exist", spec.name)],
+            };
+            if !problems.is_empty() {
+                bail!(problems.join("; "));
+            }
+
+            TableState::Updated
+        }
+        Some(description) => {
+            let problems = spec.verify(&description);
+            if !problems.is_empty() {
+                bail!(problems.join("; "));
+            }
+
+            TableState::UpToDate
+        }
+        None if mode == Mode::Verify => bail!("table \"{}\" does not exist", spec.name),
+        None => {
+            create_table(client, spec)
+                .await
+                .with_context(|| format!("failed to create table \"{}\"", spec.name))?;
+            event!(Level::INFO, table = spec.name, "created table");
+
+            TableState::Created
+        }
+    };
+
+    if let Some(attribute) = &spec.ttl_attribute {
+        if is_ttl_missing(client, spec, attribute).await? {
+            if mode == Mode::Verify {
+                bail!(
+                    "TTL is not enabled on \"{attribute}\" of table \"{}\"",
+                    spec.name
+                );
+            }
+
+            enable_ttl(client, spec, attribute).await?;
+            event!(Level::INFO, table = spec.name, attribute, "enabled TTL");
+
+            if state == TableState::UpToDate {
+                state = TableState::Updated;
+            }
+        }
+    }
+
+    Ok(state)
+}
+
+/// Applies [`ensure_table`] to every spec, stops at the first failure.
+pub async fn ensure_tables(client: &Client, specs: &[TableSpec], mode: Mode) -> Result<Vec<(String, TableState)>> {
+    let mut states = Vec::with_capacity(specs.len());
+
+    for spec in specs {
+        let state = ensure_table(client, spec, mode).await?;
+        states.push((spec.name.clone(), state));
+    }
+
+    Ok(states)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aws_sdk_dynamodb::types::{BillingModeSummary, GlobalSecondaryIndexDescription};
+
+    fn spec() -> TableSpec {
+        TableSpec::new("messages", KeySpec::s("id"))
+            .with_range_key(KeySpec::n("created_at"))
+            .with_index(IndexSpec::new("by_chat", KeySpec::n("chat_id")).with_range_key(KeySpec::n("created_at")))
+            .with_ttl("expires_at")
+    }
+
+    fn attribute(key: &KeySpec) -> AttributeDefinition {
+        AttributeDefinition::builder()
+            .attribute_name(&key.name)
+            .attribute_type(key.kind.clone())
+            .build()
+            .unwrap()
+    }
+
+    fn description(spec: &TableSpec) -> TableDescription {
+        TableDescription::builder()
+            .table_name(&spec.name)
+            .billing_mode_summary(
+                BillingModeSummary::builder()
+                    .billing_mode(BillingMode::PayPerRequest)
+                    .build(),
+            )
+            .set_key_schema(Some(key_schema(&spec.hash_key, spec.range_key.as_ref())))
+            .set_attribute_definitions(Some(
+                spec.key_attributes().into_iter().map(attribute).collect(),
+            ))
+            .set_global_secondary_indexes(Some(
+                spec.indexes
+                    .iter()
+                    .map(|index| {
+                        GlobalSecondaryIndexDescription::builder()
+                            .index_name(&index.name)
+                            .set_key_schema(Some(key_schema(&index.hash_key, index.range_key.as_ref())))
+                            .build()
+                    })
+                    .collect(),
+            ))
+            .build()
+    }
+
+    #[test]
+    fn test_key_attributes_are_unique() {
+        let spec = spec();
+        let names = spec
+            .key_attributes()
+            .into_iter()
+            .map(|it| it.name.as_str())
+            .collect::<Vec<_>>();
+
+        assert_eq!(names, vec!["id", "created_at", "chat_id"]);
+    }
+
+    #[test]
+    fn test_verify_matching_table() {
+        let spec = spec();
+
+        assert!(spec.verify(&description(&spec)).is_empty());
+    }
+
+    #[test]
+    fn test_verify_reports_key_schema_mismatch() {
+        let spec = spec();
+        let existing = TableSpec::new("messages", KeySpec::s("id"));
+
+        let problems = spec.verify(&description(&existing));
+
+        assert_eq!(
+            problems,
+            vec![
+                "table \"messages\" has a different key schema",
+                "table \"messages\" is missing index \"by_chat\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_verify_reports_attribute_type_mismatch() {
+        let spec = TableSpec::new("chat_preferences", KeySpec::n("chat_id"));
+        let existing = TableSpec::new("chat_preferences", KeySpec::s("chat_id"));
+
+        let problems = spec.verify(&description(&existing));
+
+        assert_eq!(
+            problems,
+            vec!["attribute \"chat_id\" of table \"chat_preferences\" has type S, expected N"]
+        );
+    }
+
+    #[test]
+    fn test_verify_reports_billing_mode_mismatch() {
+        let spec = TableSpec::new("chat_preferences", KeySpec::n("chat_id"));
+        let mut provisioned = description(&spec);
+        provisioned.billing_mode_summary = Some(
+            BillingModeSummary::builder()
+                .billing_mode(BillingMode::Provisioned)
+                .build(),
+        );
+        let mut legacy = description(&spec);
+        legacy.billing_mode_summary = None;
+
+        for existing in [provisioned, legacy] {
+            assert_eq!(
+                spec.verify(&existing),
+                vec!["table \"chat_preferences\" has billing mode PROVISIONED, expected PAY_PER_REQUEST"]
+            );
+        }
+    }
+}
@@ -0,0 +1,489 @@
+//! Differences between two parsed versions of a schedule page. The outages
+//! are compared per street and day, by their time windows: a window which
+//! appears is added, one which disappears is removed, and when both happen
+//! for the same buildings of the street the outage is considered moved to
+//! the new window. Within a window that stays, buildings which appear or
+//! disappear are reported as added or removed for those buildings only.
+use crate::db::DATE_FORMAT;
+use crate::outages::{building_includes, house_number};
+use crate::ElectricityFailuresData;
+use anyhow::{Context, Result};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Display;
+
+/// A street on a day of the schedule.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Place {
+    pub date: NaiveDate,
+    pub region: String,
+    pub settlement: Option<String>,
+    pub street: String,
+}
+
+impl Display for Place {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.date.format("%d.%m.%Y"), self.region)?;
+        if let Some(settlement) = &self.settlement {
+            write!(f, ", {settlement}")?;
+        }
+
+        write!(f, ", {}", self.street)
+    }
+}
+
+/// The buildings a change concerns, empty when it is the whole street.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Change {
+    Added {
+        place: Place,
+        time: String,
+        buildings: Vec<String>,
+    },
+    Removed {
+        place: Place,
+        time: String,
+        buildings: Vec<String>,
+    },
+    /// The outage is moved to another time window.
+    Modified {
+        place: Place,
+        from: String,
+        to: String,
+        buildings: Vec<String>,
+    },
+}
+
+impl Change {
+    pub fn place(&self) -> &Place {
+        match self {
+            Change::Added { place, .. } | Change::Removed { place, .. } | Change::Modified { place, .. } => place,
+        }
+    }
+
+    pub fn buildings(&self) -> &[String] {
+        match self {
+            Change::Added { buildings, .. }
+            | Change::Removed { buildings, .. }
+            | Change::Modified { buildings, .. } => buildings,
+        }
+    }
+
+    /// Whether the change concerns a subscribed address. The house number of
+    /// the address is compared with the buildings when both are known.
+    pub fn affects(&self, address: &str) -> bool {
+        let Some(house) = house_number(&self.place().street, address) else {
+            return false;
+        };
+
+        house.is_empty()
+            || self.buildings().is_empty()
+            || self
+                .buildings()
+                .iter()
+                .any(|it| building_includes(it, &house))
+    }
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Change::Added {
+                place,
+                time,
+                buildings,
+            } => write!(f, "+ {place}{} {time}", Buildings(buildings)),
+            Change::Removed {
+                place,
+                time,
+                buildings,
+            } => write!(f, "- {place}{} {time}", Buildings(buildings)),
+            Change::Modified {
+                place,
+                from,
+                to,
+                buildings,
+            } => write!(f, "~ {place}{} {from} -> {to}", Buildings(buildings)),
+        }
+    }
+}
+
+struct Buildings<'a>(&'a [String]);
+
+impl Display for Buildings<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !self.0.is_empty() {
+            write!(f, ": {}", self.0.join(","))?;
+        }
+        Ok(())
+    }
+}
+
+/// Changes from the old version of a page to the new one, ordered by place.
+pub fn diff(old: &[ElectricityFailuresData], new: &[ElectricityFailuresData]) -> Result<Vec<Change>> {
+    let old = windows(old)?;
+    let new = windows(new)?;
+    let empty = BTreeMap::new();
+
+    let places = old.keys().chain(new.keys()).collect::<BTreeSet<_>>();
+    let mut changes = vec![];
+
+    for place in places {
+        let old = old.get(place).unwrap_or(&empty);
+        let new = new.get(place).unwrap_or(&empty);
+
+        for (time, old_buildings) in old {
+            let Some(new_buildings) = new.get(time) else {
+                continue;
+            };
+            // A window without buildings covers the whole street, the
+            // buildings it lost or gained can't be told apart.
+            if old_buildings.is_empty() || new_buildings.is_empty() {
+                continue;
+            }
+
+            let removed = old_buildings
+                .difference(new_buildings)
+                .cloned()
+                .collect::<Vec<_>>();
+            if !removed.is_empty() {
+                changes.push(Change::Removed {
+                    place: place.clone(),
+                    time: time.clone(),
+                    buildings: removed,
+                });
+            }
+            let added = new_buildings
+                .difference(old_buildings)
+                .cloned()
+                .collect::<Vec<_>>();
+            if !added.is_empty() {
+                changes.push(Change::Added {
+                    place: place.clone(),
+                    time: time.clone(),
+                    buildings: added,
+                });
+            }
+        }
+
+        let mut new_windows = new
+            .iter()
+            .filter(|(time, _)| !old.contains_key(*time))
+            .collect::<Vec<_>>();
+
+        for (from, old_buildings) in old.iter().filter(|(time, _)| !new.contains_key(*time)) {
+            // Only a window sharing buildings is the same outage moved,
+            // otherwise one outage is cancelled and another one is new.
+            let moved_to = new_windows
+                .iter()
+                .position(|(_, new_buildings)| same_outage(old_buildings, new_buildings));
+            let Some((to, new_buildings)) = moved_to.map(|it| new_windows.remove(it)) else {
+                changes.push(Change::Removed {
+                    place: place.clone(),
+                    time: from.clone(),
+                    buildings: old_buildings.iter().cloned().collect(),
+                });
+                continue;
+            };
+
+            changes.push(Change::Modified {
+                place: place.clone(),
+                from: from.clone(),
+                to: to.clone(),
+                buildings: old_buildings.intersection(new_buildings).cloned().collect(),
+            });
+            let removed = old_buildings
+                .difference(new_buildings)
+                .cloned()
+                .collect::<Vec<_>>();
+            if !removed.is_empty() {
+                changes.push(Change::Removed {
+                    place: place.clone(),
+                    time: from.clone(),
+                    buildings: removed,
+                });
+            }
+            let added = new_buildings
+                .difference(old_buildings)
+                .cloned()
+                .collect::<Vec<_>>();
+            if !added.is_empty() {
+                changes.push(Change::Added {
+                    place: place.clone(),
+                    time: to.clone(),
+                    buildings: added,
+                });
+            }
+        }
+
+        for (time, buildings) in new_windows {
+            changes.push(Change::Added {
+                place: place.clone(),
+                time: time.clone(),
+                buildings: buildings.iter().cloned().collect(),
+            });
+        }
+    }
+
+    Ok(changes)
+}
+
+/// Windows share buildings, or both cover the whole street.
+fn same_outage(old: &BTreeSet<String>, new: &BTreeSet<String>) -> bool {
+    if old.is_empty() || new.is_empty() {
+        return old.is_empty() && new.is_empty();
+    }
+
+    !old.is_disjoint(new)
+}
+
+/// Time windows of the outages by street and day, with the buildings of each
+/// window. No buildings means the whole street.
+fn windows(data: &[ElectricityFailuresData]) -> Result<BTreeMap<Place, BTreeMap<String, BTreeSet<String>>>> {
+    let mut windows = BTreeMap::<_, BTreeMap<_, BTreeSet<_>>>::new();
+
+    for row in data {
+        let date =
+            NaiveDate::parse_from_str(&row.date, DATE_FORMAT).with_context(|| format!("invalid date {}", row.date))?;
+        // The same window is published with different spacing.
+        let time = row.time.split_whitespace().collect::<Vec<_>>().join(" ");
+
+        for address in row.addresses.iter() {
+            let place = Place {
+                date,
+                region: row.region.trim().to_owned(),
+                settlement: address.settlement.clone(),
+                street: address.street.clone(),
+            };
+            windows
+                .entry(place)
+                .or_default()
+                .entry(time.clone())
+                .or_default()
+                .extend(address.buildings.iter().map(|it| it.to_string()));
+        }
+    }
+
+    Ok(windows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::addresses::AddressRow;
+
+    fn data(time: &str, addresses: &str) -> ElectricityFailuresData {
+        ElectricityFailuresData {
+            city: "Beograd".to_string(),
+            region: "Palilula".to_string(),
+            time: time.to_string(),
+            date: "20-11-2023".to_string(),
+            addresses: AddressRow::parse(addresses).unwrap(),
+            note: None,
+            extra: Default::default(),
+        }
+    }
+
+    fn place(street: &str) -> Place {
+        Place {
+            date: NaiveDate::from_ymd_opt(2023, 11, 20).unwrap(),
+            region: "Palilula".to_string(),
+            settlement: None,
+            street: street.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_same_versions() {
+        let old = vec![data("09:00 - 13:00", "takovska: 5,7")];
+        let new = vec![data("09:00  -  13:00", "takovska: 7,5")];
+
+        assert_eq!(diff(&old, &new).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn test_buildings() {
+        let old = vec![data("09:00 - 13:00", "takovska: 5,7")];
+        let new = vec![data("09:00 - 13:00", "takovska: 5,9-11")];
+
+        assert_eq!(
+            diff(&old, &new).unwrap(),
+            vec![
+                Change::Removed {
+                    place: place("takovska"),
+                    time: "09:00 - 13:00".to_string(),
+                    buildings: vec!["7".to_string()],
+                },
+                Change::Added {
+                    place: place("takovska"),
+                    time: "09:00 - 13:00".to_string(),
+                    buildings: vec!["9-11".to_string()],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_modified() {
+        let old = vec![data("09:00 - 13:00", "takovska: 5, kneza miloša: 1")];
+        let new = vec![
+            data("09:00 - 13:00", "kneza miloša: 1"),
+            data("10:00 - 15:00", "takovska: 5"),
+        ];
+
+        assert_eq!(
+            diff(&old, &new).unwrap(),
+            vec![Change::Modified {
+                place: place("takovska"),
+                from: "09:00 - 13:00".to_string(),
+                to: "10:00 - 15:00".to_string(),
+                buildings: vec!["5".to_string()],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_modified_some_buildings() {
+        let old = vec![data("09:00 - 13:00", "takovska: 5,7")];
+        let new = vec![data("15:00 - 17:00", "takovska: 5,9")];
+
+        assert_eq!(
+            diff(&old, &new).unwrap(),
+            vec![
+                Change::Modified {
+                    place: place("takovska"),
+                    from: "09:00 - 13:00".to_string(),
+                    to: "15:00 - 17:00".to_string(),
+                    buildings: vec!["5".to_string()],
+                },
+                Change::Removed {
+                    place: place("takovska"),
+                    time: "09:00 - 13:00".to_string(),
+                    buildings: vec!["7".to_string()],
+                },
+                Change::Added {
+                    place: place("takovska"),
+                    time: "15:00 - 17:00".to_string(),
+                    buildings: vec!["9".to_string()],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_disjoint_buildings() {
+        let old = vec![data("09:00 - 13:00", "takovska: 5")];
+        let new = vec![data("15:00 - 17:00", "takovska: 99")];
+
+        assert_eq!(
+            diff(&old, &new).unwrap(),
+            vec![
+                Change::Removed {
+                    place: place("takovska"),
+                    time: "09:00 - 13:00".to_string(),
+                    buildings: vec!["5".to_string()],
+                },
+                Change::Added {
+                    place: place("takovska"),
+                    time: "15:00 - 17:00".to_string(),
+                    buildings: vec!["99".to_string()],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_affects() {
+        let change = Change::Removed {
+            place: place("takovska"),
+            time: "09:00 - 13:00".to_string(),
+            buildings: vec!["7".to_string(), "9-11".to_string()],
+        };
+
+        assert!(change.affects("Takovska 7"));
+        assert!(change.affects("Takovska 10"));
+        assert!(change.affects("Takovska"));
+        assert!(!change.affects("Takovska 99"));
+        assert!(!change.affects("Kneza Miloša 7"));
+    }
+
+    #[test]
+    fn test_added_and_removed() {
+        let old = vec![data("09:00 - 13:00", "takovska: 5")];
+        let new = vec![data("09:00 - 13:00", "kneza miloša: 1")];
+
+        assert_eq!(
+            diff(&old, &new).unwrap(),
+            vec![
+                Change::Added {
+                    place: place("kneza miloša"),
+                    time: "09:00 - 13:00".to_string(),
+                    buildings: vec!["1".to_string()],
+                },
+                Change::Removed {
+                    place: place("takovska"),
+                    time: "09:00 - 13:00".to_string(),
+                    buildings: vec!["5".to_string()],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_additional_window() {
+        let old = vec![data("09:00 - 13:00", "takovska: 5")];
+        let new = vec![
+            data("09:00 - 13:00", "takovska: 5"),
+            data("15:00 - 17:00", "takovska: 9"),
+        ];
+
+        assert_eq!(
+            diff(&old, &new).unwrap(),
+            vec![Change::Added {
+                place: place("takovska"),
+                time: "15:00 - 17:00".to_string(),
+                buildings: vec!["9".to_string()],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        let change = Change::Modified {
+            place: place("takovska"),
+            from: "09:00 - 13:00".to_string(),
+            to: "10:00 - 15:00".to_string(),
+            buildings: vec![],
+        };
+
+        assert_eq!(
+            change.to_string(),
+            "~ 20.11.2023 Palilula, takovska 09:00 - 13:00 -> 10:00 - 15:00"
+        );
+
+        let change = Change::Removed {
+            place: place("takovska"),
+            time: "09:00 - 13:00".to_string(),
+            buildings: vec!["7".to_string(), "9-11".to_string()],
+        };
+
+        assert_eq!(
+            change.to_string(),
+            "- 20.11.2023 Palilula, takovska: 7,9-11 09:00 - 13:00"
+        );
+    }
+
+    #[test]
+    fn test_json() {
+        let change = Change::Removed {
+            place: place("takovska"),
+            time: "09:00 - 13:00".to_string(),
+            buildings: vec!["7".to_string()],
+        };
+
+        let json = serde_json::to_string(&change).unwrap();
+
+        assert_eq!(serde_json::from_str::<Change>(&json).unwrap(), change);
+    }
+}
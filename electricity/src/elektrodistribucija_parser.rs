@@ -1,6 +1,9 @@
 use std::sync::OnceLock;
 
-use anyhow::{Context, Ok, Result};
+use crate::error::ElectricityError;
+use crate::layout::{find_outages_table, ColumnAliases};
+use crate::page_date::parse_page_date;
+use chrono::NaiveDate;
 use scraper::{ElementRef, Html, Selector};
 
 static TABLE_SELECTOR: OnceLock<Selector> = OnceLock::new();
@@ -15,44 +18,57 @@ fn header_selector() -> &'static Selector {
     HEADER_SELECTOR.get_or_init(|| Selector::parse("tbody > tr > td > b").expect("header selector initialized"))
 }
 
-pub fn get_page_header(page_html: &str) -> String {
-    let header_table = get_header_table_html(page_html);
-    String::from_iter(
-        header_table
-            .select(header_selector())
-            .next()
-            .into_iter()
-            .flat_map(|it| it.text())
-            .map(str::trim),
-    )
+/// The header with the date of the schedule, the first bold cell with a date,
+/// as a notice can come before it. Without such a cell the first bold one is
+/// taken, so that its date is reported as bad.
+pub fn get_page_header(page_html: &str) -> Result<String, ElectricityError> {
+    let document = Html::parse_document(page_html);
+
+    find_header(&document).ok_or_else(|| ElectricityError::LayoutChanged("the header is missing".to_owned()))
 }
 
-pub fn get_header_table_html(page_html: &str) -> Html {
+/// The table with the header, see [`get_page_header`].
+pub fn get_header_table_html(page_html: &str) -> Result<Html, ElectricityError> {
     let document = Html::parse_document(page_html);
-    let table_selector = table_selector();
-    let tables = document.select(table_selector).collect::<Vec<ElementRef>>();
+    let header = find_header(&document)
+        .ok_or_else(|| ElectricityError::LayoutChanged("the header table is missing".to_owned()))?;
+
+    let table = document
+        .select(table_selector())
+        .find(|table| {
+            table
+                .select(header_selector())
+                .any(|it| cell_text(it) == header)
+        })
+        .ok_or_else(|| ElectricityError::LayoutChanged("the header table is missing".to_owned()))?;
+
+    Ok(Html::parse_fragment(&table.html()))
+}
 
-    Html::parse_fragment(&tables.get(0).unwrap().html())
+/// The outages table, found by its heading row.
+pub fn get_content_table_html(page_html: &str) -> Result<Html, ElectricityError> {
+    let (table, _) = find_outages_table(page_html, &ColumnAliases::default())?;
+
+    Ok(table)
 }
 
-pub fn get_content_table_html(page_html: &str) -> Html {
-    let document = Html::parse_document(page_html);
-    let table_selector = table_selector();
-    let tables = document.select(table_selector).collect::<Vec<ElementRef>>();
+fn find_header(document: &Html) -> Option<String> {
+    let cells = document
+        .select(header_selector())
+        .map(cell_text)
+        .filter(|it| !it.is_empty())
+        .collect::<Vec<_>>();
+    let dated = cells.iter().find(|it| parse_page_date(it).is_ok()).cloned();
 
-    Html::parse_fragment(&tables.get(1).unwrap().html())
+    dated.or_else(|| cells.into_iter().next())
 }
 
-pub fn get_page_date(page_html: &str) -> Result<String> {
-    let header = get_page_header(page_html);
-    let date = header
-        .split(':')
-        .last()
-        .map(str::trim)
-        .map(ToOwned::to_owned)
-        .context("failed to extract date from header")?;
+fn cell_text(cell: ElementRef) -> String {
+    String::from_iter(cell.text().map(str::trim))
+}
 
-    Ok(date)
+pub fn get_page_date(page_html: &str) -> Result<NaiveDate, ElectricityError> {
+    parse_page_date(&get_page_header(page_html)?)
 }
 
 #[cfg(test)]
@@ -119,27 +135,59 @@ mod tests {
     fn test_get_page_date_extracts_date() {
         let date = get_page_date(TEST_PAGE_HTML).unwrap();
 
-        assert_eq!(date, "2021-01-01");
+        assert_eq!(date, NaiveDate::from_ymd_opt(2021, 1, 1).unwrap());
     }
 
     #[test]
     fn test_get_page_header_extracts_header() {
-        let header = get_page_header(TEST_PAGE_HTML);
+        let header = get_page_header(TEST_PAGE_HTML).unwrap();
 
         assert_eq!(header, "БЕОГРАД - Планирана искључења за датум: 2021-01-01");
     }
 
     #[test]
     fn test_get_header_table_html_extracts_header_table() {
-        let header_table = get_header_table_html(TEST_PAGE_HTML);
+        let header_table = get_header_table_html(TEST_PAGE_HTML).unwrap();
 
         assert_eq!(header_table.html(), "<html><table>\n                    <tbody>\n                        <tr>\n                            <td>\n                                <b>БЕОГРАД - Планирана искључења за датум: 2021-01-01</b>\n                            </td>\n                        </tr>\n                    </tbody>\n                </table></html>");
     }
 
     #[test]
     fn test_get_content_table_html_extracts_content_table() {
-        let content_table = get_content_table_html(TEST_PAGE_HTML);
+        let content_table = get_content_table_html(TEST_PAGE_HTML).unwrap();
 
         assert_eq!(content_table.html(), "<html><table>\n                    <tbody>\n                        <tr>\n                            <td>\n                                <b>Општина</b>\n                            </td>\n                            <td>\n                                <b>Време</b>\n                            </td>\n                            <td>\n                                <b>Улице</b>\n                            </td>\n                        </tr>\n                        <tr>\n                            <td>\n                                <b>Општина 1</b>\n                            </td>\n                            <td>\n                                <b>Време 1</b>\n                            </td>\n                            <td>\n                                <b>Улица 1</b>\n                            </td>\n                        </tr>\n                        <tr>\n                            <td>\n                                <b>Општина 2</b>\n                            </td>\n                            <td>\n                                <b>Време 2</b>\n                            </td>\n                            <td>\n                                <b>Улица 2</b>\n                            </td>\n                        </tr>\n                    </tbody>\n                </table></html>");
     }
+
+    #[test]
+    fn test_header_after_notice() {
+        let page = TEST_PAGE_HTML.replace(
+            "<body>",
+            "<body><table><tr><td><b>Обавештење за кориснике</b></td></tr></table>",
+        );
+
+        assert_eq!(
+            get_page_header(&page).unwrap(),
+            "БЕОГРАД - Планирана искључења за датум: 2021-01-01"
+        );
+        assert!(get_header_table_html(&page)
+            .unwrap()
+            .html()
+            .contains("2021-01-01"));
+    }
+
+    #[test]
+    fn test_missing_tables() {
+        let page = "<html><body><p>Нема искључења</p></body></html>";
+
+        assert!(matches!(
+            get_page_header(page),
+            Err(ElectricityError::LayoutChanged(_))
+        ));
+        assert_eq!(
+            get_content_table_html(page).unwrap_err().to_string(),
+            "page layout changed: the outages table is missing"
+        );
+        assert!(get_page_date(page).is_err());
+    }
 }
@@ -0,0 +1,222 @@
+//! Errors of the parser and the collector. They are classified, so the
+//! callers can decide whether to retry, alert or skip a page.
+use std::fmt::Display;
+
+#[derive(Debug)]
+pub enum ElectricityError {
+    /// The page doesn't look like the schedule anymore, e.g. a table or
+    /// a cell is missing.
+    LayoutChanged(String),
+    /// The outages table has no column with the title.
+    MissingColumn(&'static str),
+    /// The date of the page can't be read.
+    BadDate(String),
+    /// An item or an archived page is not stored.
+    NotFound(String),
+    /// A stored item or page is malformed, e.g. a field is missing, the
+    /// hash is invalid or the page is not UTF-8. Reading it again won't help.
+    CorruptItem(String),
+    /// Reading or writing the tables or the archive failed.
+    Storage(anyhow::Error),
+    /// The page couldn't be downloaded.
+    Fetch(anyhow::Error),
+}
+
+impl ElectricityError {
+    pub fn storage(error: impl Into<anyhow::Error>) -> Self {
+        Self::Storage(error.into())
+    }
+
+    pub fn fetch(error: impl Into<anyhow::Error>) -> Self {
+        Self::Fetch(error.into())
+    }
+
+    /// Whether trying again later can help. Storage and network failures
+    /// are usually temporary, while a changed page needs the parser to be
+    /// fixed.
+    pub fn is_transient(&self) -> bool {
+        matches!(self, Self::Storage(_) | Self::Fetch(_))
+    }
+}
+
+impl Display for ElectricityError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::LayoutChanged(what) => write!(f, "page layout changed: {what}"),
+            Self::MissingColumn(title) => write!(f, "column {title} is missing"),
+            Self::BadDate(date) => write!(f, "invalid date {date}"),
+            Self::NotFound(what) => write!(f, "{what} is not found"),
+            Self::CorruptItem(what) => write!(f, "corrupt item: {what}"),
+            Self::Storage(error) => write!(f, "storage failure: {error:#}"),
+            Self::Fetch(error) => write!(f, "fetch failure: {error:#}"),
+        }
+    }
+}
+
+impl std::error::Error for ElectricityError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Storage(error) | Self::Fetch(error) => Some(error.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::archive::{Archive, FsArchive};
+    use crate::fetcher::{Fetcher, FetcherConfig};
+    use crate::{load_raw_data, parse_page, parse_raw_item};
+    use aws_sdk_dynamodb::types::AttributeValue;
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    fn page(header: &str, columns: &str) -> String {
+        format!(
+            r#"
+            <table><tr><td><b>{header}</b></td></tr></table>
+            <table>
+                <tr>{columns}</tr>
+                <tr><td>Палилула</td><td>08:00 - 16:00</td><td>Таковска: 5</td></tr>
+            </table>
+            "#
+        )
+    }
+
+    const HEADER: &str = "БЕОГРАД - Планирана искључења за датум: 2023-11-20";
+    const COLUMNS: &str = "<td>Општина</td><td>Време</td><td>Улице</td>";
+
+    fn raw_item(hash: &str) -> HashMap<String, AttributeValue> {
+        HashMap::from([
+            ("id".to_string(), AttributeValue::S("id".to_string())),
+            (
+                "date".to_string(),
+                AttributeValue::S("20-11-2023".to_string()),
+            ),
+            ("url".to_string(), AttributeValue::S("url".to_string())),
+            ("hash".to_string(), AttributeValue::S(hash.to_string())),
+            ("version".to_string(), AttributeValue::N("1".to_string())),
+        ])
+    }
+
+    #[test]
+    fn test_layout_changed() {
+        let error = parse_page(&format!("<table><tr><td><b>{HEADER}</b></td></tr></table>")).unwrap_err();
+
+        assert!(matches!(error, ElectricityError::LayoutChanged(_)));
+        assert!(!error.is_transient());
+        assert_eq!(
+            error.to_string(),
+            "page layout changed: the outages table is missing"
+        );
+    }
+
+    #[test]
+    fn test_missing_column() {
+        let columns = "<td>Општина</td><td>Време</td><td>Адресе</td>";
+        let error = parse_page(&page(HEADER, columns)).unwrap_err();
+
+        assert!(matches!(error, ElectricityError::MissingColumn("Улице")));
+        assert!(!error.is_transient());
+    }
+
+    #[test]
+    fn test_bad_date() {
+        let header = "БЕОГРАД - Планирана искључења за датум: 2023-13-45";
+        let error = parse_page(&page(header, COLUMNS)).unwrap_err();
+
+        assert!(matches!(&error, ElectricityError::BadDate(date) if date == "2023-13-45"));
+        assert!(!error.is_transient());
+    }
+
+    #[tokio::test]
+    async fn test_not_found() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = FsArchive::new(dir.path());
+        let hash = "a".repeat(64);
+
+        let error = load_raw_data(&archive, &raw_item(&hash)).await.unwrap_err();
+
+        assert!(matches!(error, ElectricityError::NotFound(_)));
+        assert!(!error.is_transient());
+    }
+
+    #[tokio::test]
+    async fn test_corrupt_item() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = FsArchive::new(dir.path());
+        let mut missing_field = raw_item(&"a".repeat(64));
+        missing_field.remove("url");
+        let hash = archive.put(&[0xff, 0xfe, 0x00]).await.unwrap();
+
+        for item in [raw_item("not a hash"), missing_field, raw_item(&hash)] {
+            let error = load_raw_data(&archive, &item).await.unwrap_err();
+
+            assert!(matches!(error, ElectricityError::CorruptItem(_)), "{error}");
+            assert!(!error.is_transient());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_corrupt_item_is_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = FsArchive::new(dir.path());
+        let hash = archive.put(page(HEADER, COLUMNS).as_bytes()).await.unwrap();
+
+        assert!(parse_raw_item(&archive, &raw_item("not a hash"))
+            .await
+            .unwrap()
+            .is_none());
+        assert_eq!(
+            parse_raw_item(&archive, &raw_item(&hash))
+                .await
+                .unwrap()
+                .unwrap()
+                .len(),
+            1
+        );
+
+        let unreadable = "b".repeat(64);
+        std::fs::create_dir_all(dir.path().join("bb").join(&unreadable)).unwrap();
+        assert!(parse_raw_item(&archive, &raw_item(&unreadable))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_storage() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = FsArchive::new(dir.path());
+        // The archive can't be read where the page should be.
+        let hash = "a".repeat(64);
+        std::fs::create_dir_all(dir.path().join("aa").join(&hash)).unwrap();
+
+        let error = load_raw_data(&archive, &raw_item(&hash)).await.unwrap_err();
+
+        assert!(matches!(error, ElectricityError::Storage(_)));
+        assert!(error.is_transient());
+        assert!(std::error::Error::source(&error).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_fetch() {
+        // The connections wait in the backlog, so the page never comes.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let fetcher = Fetcher::new(FetcherConfig {
+            retries: 0,
+            timeout: Duration::from_millis(300),
+            ..FetcherConfig::default()
+        })
+        .unwrap();
+
+        let error = fetcher
+            .fetch(&format!("http://{address}/page.htm"))
+            .await
+            .unwrap_err();
+
+        assert!(matches!(error, ElectricityError::Fetch(_)));
+        assert!(error.is_transient());
+    }
+}
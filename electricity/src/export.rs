@@ -0,0 +1,520 @@
+//! Export of the parsed outages for maps and spreadsheets: CSV with a row per
+//! address and building, and GeoJSON with a feature per such row.
+//!
+//! Outages are read from the table the parser writes to (see
+//! [`crate::db::data_table_name`]) or from the `outages` table of the bot's
+//! Postgres database. Only the former keeps the building numbers.
+//!
+//! Coordinates come from an OSM database imported by `osm2pgsql` (see
+//! `docker/postgis`), buildings are looked up near the street by their house
+//! number, otherwise the middle of the street is used. Rows which can't be
+//! geocoded are exported without geometry.
+use crate::addresses::Building;
+use crate::db::{data_table_name, DATE_FORMAT};
+use crate::translit::Cyrillize;
+use anyhow::{anyhow, Context as _, Result};
+use aws_sdk_dynamodb::{types::AttributeValue, Client};
+use chrono::NaiveDate;
+use serde_json::{json, Value};
+use sqlx::postgres::PgPoolOptions;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::io::Write;
+use tracing::{event, Level};
+
+/// Buildings further from the street are not considered to be on it, in the
+/// units of the web mercator projection, which are about meters.
+const BUILDING_DISTANCE: f64 = 50.0;
+
+const CSV_HEADER: [&str; 8] = [
+    "date",
+    "time",
+    "municipality",
+    "settlement",
+    "street",
+    "building",
+    "longitude",
+    "latitude",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Point {
+    pub longitude: f64,
+    pub latitude: f64,
+}
+
+/// An address, and a building of it when known, without electricity.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Row {
+    pub date: NaiveDate,
+    /// The time window as published, e.g. `08:00 - 16:00`.
+    pub time: String,
+    pub region: String,
+    pub settlement: Option<String>,
+    pub street: String,
+    /// A house number, a range of them or `BB` for buildings without one.
+    pub building: Option<String>,
+    pub location: Option<Point>,
+}
+
+impl Row {
+    fn sort_key(&self) -> (NaiveDate, &str, &str, &str, Option<&str>) {
+        (
+            self.date,
+            &self.time,
+            &self.region,
+            &self.street,
+            self.building.as_deref(),
+        )
+    }
+
+    /// The house number to look the building up by, ranges and buildings
+    /// without a number have no single location.
+    fn house_number(&self) -> Option<&str> {
+        self.building
+            .as_deref()
+            .filter(|it| it.starts_with(|c: char| c.is_ascii_digit()) && !it.contains('-'))
+    }
+}
+
+pub enum Source {
+    DynamoDb(Client),
+    Postgres(PgPool),
+}
+
+impl Source {
+    /// Selects the backend by `STORAGE_BACKEND` (`postgres` by default), the
+    /// same way the bot does. Postgres is connected to with `POSTGRESQL_URL`.
+    pub async fn from_env() -> Result<Self> {
+        let backend = dotenvy::var("STORAGE_BACKEND").unwrap_or("postgres".to_owned());
+
+        match backend.to_lowercase().as_str() {
+            "postgres" | "postgresql" => {
+                let url =
+                    dotenvy::var("POSTGRESQL_URL").context("POSTGRESQL_URL is required for the postgres storage")?;
+                let pool = PgPoolOptions::new()
+                    .max_connections(1)
+                    .connect(&url)
+                    .await?;
+
+                Ok(Source::Postgres(pool))
+            }
+            "dynamodb" => Ok(Source::DynamoDb(crate::db::init_client().await?)),
+            backend => Err(anyhow!("unknown storage backend {backend}")),
+        }
+    }
+
+    /// Rows of the outages planned from `from` to `to` inclusive, ordered by
+    /// date, time window and address.
+    pub async fn rows(&self, from: NaiveDate, to: NaiveDate) -> Result<Vec<Row>> {
+        let mut rows = match self {
+            Source::DynamoDb(client) => dynamo_rows(client, from, to).await?,
+            Source::Postgres(pool) => postgres_rows(pool, from, to).await?,
+        };
+        rows.sort_by(|a, b| a.sort_key().cmp(&b.sort_key()));
+        // The parser can store the same page more than once.
+        rows.dedup();
+
+        Ok(rows)
+    }
+}
+
+// Dates are not sortable in the parser's format, so the table is scanned and
+// filtered here.
+async fn dynamo_rows(client: &Client, from: NaiveDate, to: NaiveDate) -> Result<Vec<Row>> {
+    let mut rows = vec![];
+    let mut start_key = None;
+
+    loop {
+        let response = client
+            .scan()
+            .table_name(data_table_name())
+            .set_exclusive_start_key(start_key)
+            .send()
+            .await?;
+
+        for item in response.items() {
+            // A malformed item doesn't hold back the rest of the export.
+            let item_rows = match dynamo_item_rows(item) {
+                Ok(item_rows) => item_rows,
+                Err(err) => {
+                    event!(Level::WARN, error = %err, "skipping outage item");
+                    continue;
+                }
+            };
+            rows.extend(
+                item_rows
+                    .into_iter()
+                    .filter(|row| from <= row.date && row.date <= to),
+            );
+        }
+
+        start_key = response.last_evaluated_key;
+        if start_key.is_none() {
+            break;
+        }
+    }
+
+    Ok(rows)
+}
+
+/// Rows of an item of the parser's table, one per building, or a single one
+/// when the buildings are not listed.
+fn dynamo_item_rows(item: &HashMap<String, AttributeValue>) -> Result<Vec<Row>> {
+    let string = |field: &str| {
+        item.get(field)
+            .and_then(|av| av.as_s().ok())
+            .map(ToOwned::to_owned)
+            .with_context(|| format!("{field} is missing"))
+    };
+
+    let date = string("date")?;
+    let row = Row {
+        date: NaiveDate::parse_from_str(&date, DATE_FORMAT).with_context(|| format!("invalid date {date}"))?,
+        time: string("time")?,
+        region: string("region")?,
+        settlement: string("settlement").ok(),
+        street: string("street")?,
+        building: None,
+        location: None,
+    };
+
+    let buildings = match string("buildings") {
+        Ok(buildings) => serde_json::from_str::<Vec<Building>>(&buildings)
+            .with_context(|| format!("invalid buildings {buildings}"))?,
+        Err(_) => vec![],
+    };
+
+    if buildings.is_empty() {
+        return Ok(vec![row]);
+    }
+
+    Ok(buildings
+        .into_iter()
+        .map(|building| Row {
+            building: Some(building.to_string()),
+            ..row.clone()
+        })
+        .collect())
+}
+
+async fn postgres_rows(pool: &PgPool, from: NaiveDate, to: NaiveDate) -> Result<Vec<Row>> {
+    let rows = sqlx::query_as::<_, (NaiveDate, String, String, Option<String>, String)>(
+        "SELECT date, time, region, settlement, street FROM outages WHERE date BETWEEN $1 AND $2",
+    )
+    .bind(from)
+    .bind(to)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(date, time, region, settlement, street)| Row {
+            date,
+            time,
+            region,
+            settlement,
+            street,
+            building: None,
+            location: None,
+        })
+        .collect())
+}
+
+/// Looks addresses up in an OSM database imported by `osm2pgsql` with the
+/// default style, the geometries are kept in the web mercator projection.
+pub struct Geocoder {
+    pool: PgPool,
+    streets: HashMap<String, Option<Point>>,
+    buildings: HashMap<(String, String), Option<Point>>,
+}
+
+impl Geocoder {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            pool,
+            streets: HashMap::new(),
+            buildings: HashMap::new(),
+        }
+    }
+
+    /// Connects to `OSM_DATABASE_URL`, there is no geocoding when it is not set.
+    pub async fn from_env() -> Result<Option<Self>> {
+        match dotenvy::var("OSM_DATABASE_URL") {
+            Ok(url) => {
+                let pool = PgPoolOptions::new()
+                    .max_connections(1)
+                    .connect(&url)
+                    .await?;
+
+                Ok(Some(Self::new(pool)))
+            }
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// Fills in the locations of the rows which can be found.
+    pub async fn locate_all(&mut self, rows: &mut [Row]) -> Result<()> {
+        for row in rows {
+            row.location = self.locate(row).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn locate(&mut self, row: &Row) -> Result<Option<Point>> {
+        // OSM names the streets in Cyrillic while the parser keeps them in Latin.
+        let street = row.street.cyrillize();
+
+        if let Some(number) = row.house_number() {
+            let key = (street.clone(), number.to_owned());
+            if !self.buildings.contains_key(&key) {
+                let point = self.find_building(&street, number).await?;
+                self.buildings.insert(key.clone(), point);
+            }
+            if let Some(point) = self.buildings[&key] {
+                return Ok(Some(point));
+            }
+        }
+
+        if !self.streets.contains_key(&street) {
+            let point = self.find_street(&street).await?;
+            self.streets.insert(street.clone(), point);
+        }
+
+        Ok(self.streets[&street])
+    }
+
+    async fn find_building(&self, street: &str, number: &str) -> Result<Option<Point>> {
+        let point = sqlx::query_as::<_, (f64, f64)>(
+            r#"SELECT ST_X(point), ST_Y(point) FROM (
+                SELECT ST_Transform(ST_Centroid(building.way), 4326) AS point
+                FROM planet_osm_polygon AS building
+                JOIN planet_osm_line AS street
+                  ON street.highway IS NOT NULL
+                 AND lower(street.name) = lower($1)
+                 AND ST_DWithin(building.way, street.way, $3)
+                WHERE building."addr:housenumber" = $2
+                LIMIT 1
+            ) AS found"#,
+        )
+        .bind(street)
+        .bind(number)
+        .bind(BUILDING_DISTANCE)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(point.map(|(longitude, latitude)| Point {
+            longitude,
+            latitude,
+        }))
+    }
+
+    async fn find_street(&self, street: &str) -> Result<Option<Point>> {
+        let point = sqlx::query_as::<_, (f64, f64)>(
+            r#"SELECT ST_X(point), ST_Y(point) FROM (
+                SELECT ST_Transform(ST_Centroid(ST_Collect(way)), 4326) AS point
+                FROM planet_osm_line
+                WHERE highway IS NOT NULL AND lower(name) = lower($1)
+            ) AS found
+            WHERE point IS NOT NULL"#,
+        )
+        .bind(street)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(point.map(|(longitude, latitude)| Point {
+            longitude,
+            latitude,
+        }))
+    }
+}
+
+pub fn write_csv<W: Write>(rows: &[Row], writer: W) -> Result<()> {
+    let mut writer = csv::Writer::from_writer(writer);
+    writer.write_record(CSV_HEADER)?;
+
+    for row in rows {
+        let (longitude, latitude) = match row.location {
+            Some(point) => (point.longitude.to_string(), point.latitude.to_string()),
+            None => (String::new(), String::new()),
+        };
+
+        writer.write_record([
+            row.date.format("%Y-%m-%d").to_string(),
+            row.time.trim().to_owned(),
+            row.region.to_owned(),
+            row.settlement.to_owned().unwrap_or_default(),
+            row.street.to_owned(),
+            row.building.to_owned().unwrap_or_default(),
+            longitude,
+            latitude,
+        ])?;
+    }
+
+    writer.flush()?;
+
+    Ok(())
+}
+
+pub fn to_geojson(rows: &[Row]) -> Value {
+    let features = rows
+        .iter()
+        .map(|row| {
+            json!({
+                "type": "Feature",
+                "geometry": row.location.map(|point| json!({
+                    "type": "Point",
+                    "coordinates": [point.longitude, point.latitude],
+                })),
+                "properties": {
+                    "date": row.date.format("%Y-%m-%d").to_string(),
+                    "time": row.time.trim(),
+                    "municipality": row.region,
+                    "settlement": row.settlement,
+                    "street": row.street,
+                    "building": row.building,
+                },
+            })
+        })
+        .collect::<Vec<_>>();
+
+    json!({
+        "type": "FeatureCollection",
+        "features": features,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(buildings: Option<&str>) -> HashMap<String, AttributeValue> {
+        let mut item = HashMap::from([
+            ("id".to_string(), AttributeValue::S("id".to_string())),
+            (
+                "date".to_string(),
+                AttributeValue::S("20-11-2023".to_string()),
+            ),
+            (
+                "time".to_string(),
+                AttributeValue::S("08:00 - 16:00".to_string()),
+            ),
+            (
+                "region".to_string(),
+                AttributeValue::S("Палилула".to_string()),
+            ),
+            ("settlement".to_string(), AttributeValue::Null(true)),
+            (
+                "street".to_string(),
+                AttributeValue::S("takovska".to_string()),
+            ),
+        ]);
+        if let Some(buildings) = buildings {
+            item.insert(
+                "buildings".to_string(),
+                AttributeValue::S(buildings.to_string()),
+            );
+        }
+
+        item
+    }
+
+    fn row(building: Option<&str>, location: Option<Point>) -> Row {
+        Row {
+            date: NaiveDate::from_ymd_opt(2023, 11, 20).unwrap(),
+            time: "08:00 - 16:00".to_string(),
+            region: "Палилула".to_string(),
+            settlement: None,
+            street: "takovska".to_string(),
+            building: building.map(ToOwned::to_owned),
+            location,
+        }
+    }
+
+    #[test]
+    fn test_dynamo_item_row_per_building() {
+        let buildings = r#"[
+            {"Number": {"value": 5, "extension": "a"}},
+            {"Range": {"from": {"value": 1, "extension": null}, "to": {"value": 7, "extension": null}}},
+            {"Bb": null}
+        ]"#;
+
+        let rows = dynamo_item_rows(&item(Some(buildings))).unwrap();
+
+        assert_eq!(
+            rows.iter()
+                .map(|it| it.building.as_deref())
+                .collect::<Vec<_>>(),
+            vec![Some("5a"), Some("1-7"), Some("BB")]
+        );
+        assert_eq!(rows[0].settlement, None);
+        assert_eq!(rows[0].house_number(), Some("5a"));
+        assert_eq!(rows[1].house_number(), None);
+        assert_eq!(rows[2].house_number(), None);
+    }
+
+    #[test]
+    fn test_dynamo_item_without_buildings() {
+        assert_eq!(
+            dynamo_item_rows(&item(None)).unwrap(),
+            vec![row(None, None)]
+        );
+        assert_eq!(
+            dynamo_item_rows(&item(Some("[]"))).unwrap(),
+            vec![row(None, None)]
+        );
+    }
+
+    #[test]
+    fn test_write_csv() {
+        let rows = vec![
+            row(
+                Some("5"),
+                Some(Point {
+                    longitude: 20.5,
+                    latitude: 44.75,
+                }),
+            ),
+            row(None, None),
+        ];
+        let mut output = vec![];
+
+        write_csv(&rows, &mut output).unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "date,time,municipality,settlement,street,building,longitude,latitude\n\
+             2023-11-20,08:00 - 16:00,Палилула,,takovska,5,20.5,44.75\n\
+             2023-11-20,08:00 - 16:00,Палилула,,takovska,,,\n"
+        );
+    }
+
+    #[test]
+    fn test_geojson() {
+        let rows = vec![
+            row(
+                Some("5"),
+                Some(Point {
+                    longitude: 20.5,
+                    latitude: 44.75,
+                }),
+            ),
+            row(None, None),
+        ];
+
+        let geojson = to_geojson(&rows);
+
+        assert_eq!(geojson["type"], "FeatureCollection");
+        let features = geojson["features"].as_array().unwrap();
+        assert_eq!(features.len(), 2);
+        assert_eq!(
+            features[0]["geometry"],
+            json!({ "type": "Point", "coordinates": [20.5, 44.75] })
+        );
+        assert_eq!(features[0]["properties"]["building"], "5");
+        assert_eq!(features[1]["geometry"], Value::Null);
+        assert_eq!(features[1]["properties"]["municipality"], "Палилула");
+        assert_eq!(features[1]["properties"]["building"], Value::Null);
+    }
+}
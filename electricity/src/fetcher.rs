@@ -0,0 +1,465 @@
+//! Downloads the schedule pages. A single client is shared by all the
+//! requests, failed requests are retried with an exponential backoff and
+//! pages which have not changed since the previously stored download are
+//! not transferred again, see [`Fetcher::fetch`] and [`Fetcher::commit`].
+use crate::error::ElectricityError;
+use anyhow::{anyhow, Result};
+use encoding_rs::{Encoding, UTF_8};
+use rand::Rng;
+use reqwest::header::{HeaderMap, CONTENT_TYPE, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::{Client, StatusCode};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::{event, Level};
+
+/// How many bytes of a page are searched for the charset declaration.
+const SNIFF_LENGTH: usize = 1024;
+
+#[derive(Debug, Clone)]
+pub struct FetcherConfig {
+    pub connect_timeout: Duration,
+    /// Limit for a whole request, including reading the body.
+    pub timeout: Duration,
+    /// How many times a failed request is repeated.
+    pub retries: u32,
+    /// Delay before the first retry, doubled for every next one. A random
+    /// part of up to the same length is added, so retries are spread.
+    pub backoff: Duration,
+}
+
+impl Default for FetcherConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Duration::from_secs(5),
+            timeout: Duration::from_secs(20),
+            retries: 3,
+            backoff: Duration::from_millis(500),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Fetched {
+    Page {
+        html: String,
+        /// To be passed to [`Fetcher::commit`] once the page is stored.
+        validators: Validators,
+    },
+    /// The page is the same as its last committed version.
+    NotModified,
+}
+
+/// `ETag` and `Last-Modified` of a downloaded version of a page.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Validators {
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+pub struct Fetcher {
+    client: Client,
+    config: FetcherConfig,
+    validators: Mutex<HashMap<String, Validators>>,
+}
+
+impl Fetcher {
+    pub fn new(config: FetcherConfig) -> Result<Self> {
+        let client = Client::builder()
+            .connect_timeout(config.connect_timeout)
+            .timeout(config.timeout)
+            .build()?;
+
+        Ok(Self {
+            client,
+            config,
+            validators: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Downloads a page and decodes it according to the charset it declares.
+    ///
+    /// Pages are asked for conditionally with the validators of their last
+    /// committed version, so the fetcher should be kept for the lifetime of
+    /// the process.
+    pub async fn fetch(&self, url: &str) -> Result<Fetched, ElectricityError> {
+        let mut attempt = 0;
+
+        loop {
+            match self.try_fetch(url).await {
+                Ok(fetched) => return Ok(fetched),
+                Err(err) if err.retryable && attempt < self.config.retries => {
+                    let delay = self.delay(attempt);
+                    event!(
+                        Level::WARN,
+                        url,
+                        attempt,
+                        error = %err.error,
+                        "failed to fetch page, retrying in {delay:?}"
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(err) => return Err(ElectricityError::Fetch(err.error)),
+            }
+        }
+    }
+
+    async fn try_fetch(&self, url: &str) -> Result<Fetched, FetchError> {
+        let validators = self.validators.lock().unwrap().get(url).cloned();
+
+        let mut request = self.client.get(url);
+        if let Some(validators) = validators {
+            if let Some(etag) = validators.etag {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = validators.last_modified {
+                request = request.header(IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+
+        let response = request.send().await.map_err(FetchError::from)?;
+        let status = response.status();
+
+        if status == StatusCode::NOT_MODIFIED {
+            return Ok(Fetched::NotModified);
+        }
+        if !status.is_success() {
+            return Err(FetchError {
+                retryable: status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS,
+                error: anyhow!("unexpected status {status}"),
+            });
+        }
+
+        let headers = response.headers().clone();
+        let body = response.bytes().await.map_err(FetchError::from)?;
+
+        let content_type = headers.get(CONTENT_TYPE).and_then(|it| it.to_str().ok());
+
+        Ok(Fetched::Page {
+            html: decode(content_type, &body),
+            validators: Validators::from_headers(&headers),
+        })
+    }
+
+    /// Records the validators of a fetched page once it is stored. Until then
+    /// the page is downloaded in full, so a version which failed to be stored
+    /// is not skipped as not modified.
+    pub fn commit(&self, url: &str, validators: Validators) {
+        self.validators
+            .lock()
+            .unwrap()
+            .insert(url.to_owned(), validators);
+    }
+
+    fn delay(&self, attempt: u32) -> Duration {
+        let backoff = self
+            .config
+            .backoff
+            .saturating_mul(2u32.saturating_pow(attempt));
+        let jitter = rand::thread_rng().gen_range(Duration::ZERO..=self.config.backoff);
+
+        backoff + jitter
+    }
+}
+
+impl Validators {
+    fn from_headers(headers: &HeaderMap) -> Self {
+        let header = |name| {
+            headers
+                .get(name)
+                .and_then(|it| it.to_str().ok())
+                .map(ToOwned::to_owned)
+        };
+
+        Self {
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+        }
+    }
+}
+
+struct FetchError {
+    retryable: bool,
+    error: anyhow::Error,
+}
+
+impl From<reqwest::Error> for FetchError {
+    fn from(error: reqwest::Error) -> Self {
+        Self {
+            // Timeouts, refused connections and connections closed while
+            // reading the body.
+            retryable: error.is_timeout() || error.is_connect() || error.is_request() || error.is_body(),
+            error: error.into(),
+        }
+    }
+}
+
+/// Decodes a page with the charset of the `Content-Type` header or, when it
+/// is not there, of the `<meta>` tag of the page. Pages without a known
+/// charset are decoded as UTF-8.
+pub(crate) fn decode(content_type: Option<&str>, body: &[u8]) -> String {
+    let sniffed = String::from_utf8_lossy(&body[..body.len().min(SNIFF_LENGTH)]);
+    let encoding = content_type
+        .and_then(charset)
+        .or_else(|| charset(&sniffed))
+        .and_then(|label| Encoding::for_label(label.as_bytes()))
+        .unwrap_or(UTF_8);
+
+    // A byte order mark takes precedence over the declared charset.
+    let (text, _, _) = encoding.decode(body);
+
+    text.into_owned()
+}
+
+/// The value of the first `charset=` parameter of a content type or of the
+/// attributes of `<meta>` tags.
+fn charset(text: &str) -> Option<&str> {
+    let lowercase = text.to_ascii_lowercase();
+    let start = lowercase.find("charset=")? + "charset=".len();
+    let value = text[start..].trim_start_matches(['"', '\'']);
+    let end = value
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+        .unwrap_or(value.len());
+
+    Some(&value[..end]).filter(|it| !it.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    struct Reply {
+        status: &'static str,
+        headers: Vec<&'static str>,
+        body: Vec<u8>,
+        /// The reply is never sent, only a timeout ends the request.
+        hangs: bool,
+    }
+
+    impl Reply {
+        fn ok(headers: Vec<&'static str>, body: &[u8]) -> Self {
+            Self {
+                status: "200 OK",
+                headers,
+                body: body.to_vec(),
+                hangs: false,
+            }
+        }
+
+        fn status(status: &'static str) -> Self {
+            Self {
+                status,
+                headers: vec![],
+                body: vec![],
+                hangs: false,
+            }
+        }
+    }
+
+    /// Serves the replies in order, one per connection, and records the heads
+    /// of the requests.
+    async fn serve(replies: Vec<Reply>) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/page.htm", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(vec![]));
+
+        let recorded = requests.clone();
+        tokio::spawn(async move {
+            for reply in replies {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut buffer = vec![0; 4096];
+                let read = stream.read(&mut buffer).await.unwrap();
+                recorded
+                    .lock()
+                    .unwrap()
+                    .push(String::from_utf8_lossy(&buffer[..read]).to_lowercase());
+
+                // Hanging replies must not hold up the next connections.
+                tokio::spawn(async move {
+                    if reply.hangs {
+                        // The connection stays open until the test ends.
+                        std::future::pending::<()>().await;
+                    }
+                    let mut response = format!(
+                        "HTTP/1.1 {}\r\ncontent-length: {}\r\nconnection: close\r\n",
+                        reply.status,
+                        reply.body.len()
+                    );
+                    for header in reply.headers {
+                        response.push_str(header);
+                        response.push_str("\r\n");
+                    }
+                    response.push_str("\r\n");
+
+                    let _ = stream.write_all(response.as_bytes()).await;
+                    let _ = stream.write_all(&reply.body).await;
+                    let _ = stream.shutdown().await;
+                });
+            }
+        });
+
+        (url, requests)
+    }
+
+    fn html(fetched: Fetched) -> String {
+        match fetched {
+            Fetched::Page { html, .. } => html,
+            Fetched::NotModified => panic!("the page is not modified"),
+        }
+    }
+
+    fn fetcher() -> Fetcher {
+        Fetcher::new(FetcherConfig {
+            connect_timeout: Duration::from_secs(1),
+            // Only a hanging reply takes this long, the others are local.
+            timeout: Duration::from_secs(2),
+            retries: 2,
+            backoff: Duration::from_millis(10),
+        })
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_retries_server_errors() {
+        let (url, requests) = serve(vec![
+            Reply::status("503 Service Unavailable"),
+            Reply::status("500 Internal Server Error"),
+            Reply::ok(vec![], b"page"),
+        ])
+        .await;
+
+        assert_eq!(html(fetcher().fetch(&url).await.unwrap()), "page");
+        assert_eq!(requests.lock().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_retries() {
+        let (url, requests) = serve(vec![
+            Reply::status("503 Service Unavailable"),
+            Reply::status("503 Service Unavailable"),
+            Reply::status("503 Service Unavailable"),
+        ])
+        .await;
+
+        let err = fetcher().fetch(&url).await.unwrap_err();
+
+        assert!(err.to_string().contains("503"), "{err}");
+        assert_eq!(requests.lock().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_does_not_retry_client_errors() {
+        let (url, requests) = serve(vec![
+            Reply::status("404 Not Found"),
+            Reply::ok(vec![], b"page"),
+        ])
+        .await;
+
+        assert!(fetcher().fetch(&url).await.is_err());
+        assert_eq!(requests.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_retries_timeouts() {
+        let hanging = Reply {
+            hangs: true,
+            ..Reply::ok(vec![], b"hanging")
+        };
+        let (url, requests) = serve(vec![hanging, Reply::ok(vec![], b"page")]).await;
+
+        assert_eq!(html(fetcher().fetch(&url).await.unwrap()), "page");
+        assert_eq!(requests.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_conditional_get() {
+        let (url, requests) = serve(vec![
+            Reply::ok(
+                vec![
+                    "etag: \"v1\"",
+                    "last-modified: Mon, 20 Nov 2023 08:00:00 GMT",
+                ],
+                b"page",
+            ),
+            Reply::status("304 Not Modified"),
+        ])
+        .await;
+        let fetcher = fetcher();
+
+        let Fetched::Page { validators, .. } = fetcher.fetch(&url).await.unwrap() else {
+            panic!("the page is fetched");
+        };
+        fetcher.commit(&url, validators);
+        assert_eq!(fetcher.fetch(&url).await.unwrap(), Fetched::NotModified);
+
+        let requests = requests.lock().unwrap();
+        assert!(!requests[0].contains("if-none-match"));
+        assert!(requests[1].contains("if-none-match: \"v1\""));
+        assert!(requests[1].contains("if-modified-since: mon, 20 nov 2023 08:00:00 gmt"));
+    }
+
+    #[tokio::test]
+    async fn test_uncommitted_page_is_fetched_again() {
+        let etag = vec!["etag: \"v1\""];
+        let (url, requests) = serve(vec![
+            Reply::ok(etag.clone(), b"page"),
+            Reply::ok(etag, b"page"),
+        ])
+        .await;
+        let fetcher = fetcher();
+
+        // The first version failed to be stored, so it is not committed.
+        assert_eq!(html(fetcher.fetch(&url).await.unwrap()), "page");
+        assert_eq!(html(fetcher.fetch(&url).await.unwrap()), "page");
+
+        let requests = requests.lock().unwrap();
+        assert!(!requests[1].contains("if-none-match"));
+    }
+
+    #[tokio::test]
+    async fn test_decodes_declared_charset() {
+        let (page, _, _) = encoding_rs::WINDOWS_1251.encode("<td>Улице</td>");
+        let (url, _) = serve(vec![Reply::ok(
+            vec!["content-type: text/html; charset=windows-1251"],
+            &page,
+        )])
+        .await;
+
+        assert_eq!(html(fetcher().fetch(&url).await.unwrap()), "<td>Улице</td>");
+    }
+
+    #[test]
+    fn test_decode() {
+        let (cyrillic, _, _) = encoding_rs::WINDOWS_1251.encode("Општина");
+        let mut page = br#"<meta http-equiv="Content-Type" content="text/html; charset=windows-1251">"#.to_vec();
+        page.extend_from_slice(&cyrillic);
+
+        assert!(decode(None, &page).ends_with("Општина"));
+        assert!(decode(Some("text/html"), &page).ends_with("Општина"));
+        assert_eq!(decode(None, "Општина".as_bytes()), "Општина");
+        assert_eq!(
+            decode(Some("text/html; charset=utf-8"), "Општина".as_bytes()),
+            "Општина"
+        );
+        assert_eq!(
+            decode(Some("text/html; charset=unknown"), "Општина".as_bytes()),
+            "Општина"
+        );
+    }
+
+    #[test]
+    fn test_charset() {
+        assert_eq!(
+            charset("text/html; charset=windows-1251"),
+            Some("windows-1251")
+        );
+        assert_eq!(charset("text/html; Charset=\"UTF-8\""), Some("UTF-8"));
+        assert_eq!(charset("<meta charset='utf-8'>"), Some("utf-8"));
+        assert_eq!(charset("text/html"), None);
+        assert_eq!(charset("text/html; charset="), None);
+    }
+}
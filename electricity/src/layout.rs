@@ -0,0 +1,249 @@
+//! Detection of the outages table and its columns. The table is found by its
+//! heading row rather than by its position on the page, and the titles are
+//! matched against aliases, so a renamed or an added column doesn't break
+//! the parser.
+use crate::error::ElectricityError;
+use crate::translit;
+use scraper::{Html, Selector};
+use std::sync::OnceLock;
+
+static TABLE_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static TR_SELECTOR: OnceLock<Selector> = OnceLock::new();
+static CELL_SELECTOR: OnceLock<Selector> = OnceLock::new();
+
+fn table_selector() -> &'static Selector {
+    TABLE_SELECTOR.get_or_init(|| Selector::parse("table").expect("table selector initialized"))
+}
+
+fn tr_selector() -> &'static Selector {
+    TR_SELECTOR.get_or_init(|| Selector::parse("tr").expect("tr selector initialized"))
+}
+
+fn cell_selector() -> &'static Selector {
+    CELL_SELECTOR.get_or_init(|| Selector::parse("td, th").expect("cell selector initialized"))
+}
+
+/// A column of the outages table the parser knows about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    Region,
+    Time,
+    Streets,
+    Note,
+}
+
+impl Column {
+    /// Columns without which a row can't be parsed.
+    pub const REQUIRED: [Column; 3] = [Column::Streets, Column::Time, Column::Region];
+
+    /// The title used on the page.
+    pub fn title(&self) -> &'static str {
+        match self {
+            Column::Region => "Општина",
+            Column::Time => "Време",
+            Column::Streets => "Улице",
+            Column::Note => "Напомена",
+        }
+    }
+}
+
+/// Titles of the columns. Titles are compared in Latin script, without
+/// diacritics, letter case and extra whitespace, so "Улице", "Ulice" and
+/// "ULICE" are the same title.
+#[derive(Debug, Clone)]
+pub struct ColumnAliases {
+    aliases: Vec<(Column, String)>,
+}
+
+impl Default for ColumnAliases {
+    fn default() -> Self {
+        Self { aliases: vec![] }
+            .with(Column::Region, "Општина")
+            .with(Column::Region, "Општине")
+            .with(Column::Time, "Време")
+            .with(Column::Time, "Време искључења")
+            .with(Column::Streets, "Улице")
+            .with(Column::Streets, "Улица")
+            .with(Column::Note, "Напомена")
+            .with(Column::Note, "Напомене")
+    }
+}
+
+impl ColumnAliases {
+    pub fn with(mut self, column: Column, alias: &str) -> Self {
+        self.aliases.push((column, normalize(alias)));
+        self
+    }
+
+    pub fn column(&self, title: &str) -> Option<Column> {
+        let title = normalize(title);
+
+        self.aliases
+            .iter()
+            .find(|(_, alias)| *alias == title)
+            .map(|(column, _)| *column)
+    }
+}
+
+fn normalize(title: &str) -> String {
+    translit::normalize(title).trim_end_matches(':').to_owned()
+}
+
+/// Positions of the columns in a row of the outages table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableLayout {
+    pub region: usize,
+    pub time: usize,
+    pub streets: usize,
+    pub note: Option<usize>,
+    /// Unknown columns with their titles, kept as extra fields of an outage.
+    pub extra: Vec<(usize, String)>,
+}
+
+impl TableLayout {
+    pub fn from_titles<T: AsRef<str>>(titles: &[T], aliases: &ColumnAliases) -> Result<Self, ElectricityError> {
+        let mut columns: Vec<(Column, usize)> = vec![];
+        let mut extra = vec![];
+
+        for (index, title) in titles.iter().enumerate() {
+            let title = title.as_ref().trim();
+
+            match aliases.column(title) {
+                Some(column) if !columns.iter().any(|(it, _)| *it == column) => columns.push((column, index)),
+                _ if title.is_empty() => {}
+                _ => extra.push((index, title.to_owned())),
+            }
+        }
+
+        let position = |column: Column| {
+            columns
+                .iter()
+                .find(|(it, _)| *it == column)
+                .map(|(_, index)| *index)
+        };
+        let required = |column: Column| position(column).ok_or(ElectricityError::MissingColumn(column.title()));
+
+        Ok(Self {
+            streets: required(Column::Streets)?,
+            time: required(Column::Time)?,
+            region: required(Column::Region)?,
+            note: position(Column::Note),
+            extra,
+        })
+    }
+}
+
+/// Finds the outages table of the page, the first table whose heading row has
+/// all the required columns.
+pub fn find_outages_table(page_html: &str, aliases: &ColumnAliases) -> Result<(Html, TableLayout), ElectricityError> {
+    let document = Html::parse_document(page_html);
+    let mut partial_match = None;
+
+    for table in document.select(table_selector()) {
+        let Some(heading) = table.select(tr_selector()).next() else {
+            continue;
+        };
+        let titles = heading
+            .select(cell_selector())
+            .map(|cell| cell.text().collect::<String>())
+            .collect::<Vec<_>>();
+
+        match TableLayout::from_titles(&titles, aliases) {
+            Ok(layout) => return Ok((Html::parse_fragment(&table.html()), layout)),
+            Err(error) if partial_match.is_none() && titles.iter().any(|it| aliases.column(it).is_some()) => {
+                partial_match = Some(error)
+            }
+            Err(_) => {}
+        }
+    }
+
+    Err(partial_match.unwrap_or_else(|| ElectricityError::LayoutChanged("the outages table is missing".to_owned())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn titles(titles: &[&str]) -> Result<TableLayout, ElectricityError> {
+        TableLayout::from_titles(titles, &ColumnAliases::default())
+    }
+
+    #[test]
+    fn test_default_titles() {
+        assert_eq!(
+            titles(&["Општина", "Време", "Улице"]).unwrap(),
+            TableLayout {
+                region: 0,
+                time: 1,
+                streets: 2,
+                note: None,
+                extra: vec![],
+            }
+        );
+    }
+
+    #[test]
+    fn test_latin_and_reordered_titles() {
+        let layout = titles(&[" ULICE ", "Opština", "Vreme  isključenja:"]).unwrap();
+
+        assert_eq!((layout.region, layout.time, layout.streets), (1, 2, 0));
+    }
+
+    #[test]
+    fn test_note_and_unknown_columns() {
+        let layout = titles(&["Општина", "Трафостаница", "Време", "Улице", "Napomena", ""]).unwrap();
+
+        assert_eq!(layout.note, Some(4));
+        assert_eq!(layout.extra, vec![(1, "Трафостаница".to_string())]);
+    }
+
+    #[test]
+    fn test_custom_alias() {
+        let aliases = ColumnAliases::default().with(Column::Streets, "Адресе");
+        let layout = TableLayout::from_titles(&["Општина", "Време", "Адресе"], &aliases).unwrap();
+
+        assert_eq!(layout.streets, 2);
+    }
+
+    #[test]
+    fn test_missing_column() {
+        assert!(matches!(
+            titles(&["Општина", "Улице"]),
+            Err(ElectricityError::MissingColumn("Време"))
+        ));
+    }
+
+    #[test]
+    fn test_find_outages_table_by_content() {
+        let page = r#"
+            <table><tr><td>Обавештење</td></tr></table>
+            <table><tr><td><b>БЕОГРАД - Планирана искључења за датум: 2023-11-20</b></td></tr></table>
+            <table>
+                <tr><th>Улице</th><th>Општина</th><th>Време</th></tr>
+                <tr><td>Таковска: 5</td><td>Палилула</td><td>08:00 - 16:00</td></tr>
+            </table>
+        "#;
+
+        let (table, layout) = find_outages_table(page, &ColumnAliases::default()).unwrap();
+
+        assert_eq!((layout.region, layout.time, layout.streets), (1, 2, 0));
+        assert!(table.html().contains("Таковска"));
+    }
+
+    #[test]
+    fn test_find_outages_table_errors() {
+        let aliases = ColumnAliases::default();
+        let partial = "<table><tr><td>Општина</td><td>Време</td><td>Адресе</td></tr></table>";
+
+        assert!(matches!(
+            find_outages_table(partial, &aliases),
+            Err(ElectricityError::MissingColumn("Улице"))
+        ));
+        assert_eq!(
+            find_outages_table("<table><tr><td>Нема искључења</td></tr></table>", &aliases)
+                .unwrap_err()
+                .to_string(),
+            "page layout changed: the outages table is missing"
+        );
+    }
+}
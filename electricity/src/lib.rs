@@ -1,22 +1,52 @@
 use crate::translit::Translit;
 use addresses::Address;
-use anyhow::{anyhow, Context as _, Result};
+use anyhow::{Context as _, Result};
+use archive::{content_hash, is_content_hash, Archive};
 use aws_sdk_dynamodb::{types::AttributeValue, Client};
 use chrono::NaiveDate;
-use elektrodistribucija_parser::{get_content_table_html, get_page_date, get_page_header};
+use db::DATE_FORMAT;
+use diff::{diff, Change};
+use elektrodistribucija_parser::{get_page_date, get_page_header};
+use fetcher::{Fetched, Fetcher};
+use layout::{find_outages_table, ColumnAliases};
+use page_date::parse_page_date;
 use scraper::Selector;
-use std::collections::hash_map::DefaultHasher;
+use sqlx::PgPool;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::Display;
-use std::hash::{Hash, Hasher};
 use std::sync::OnceLock;
 use tracing::{event, span, Level};
 use uuid::Uuid;
+use validation::{daily_reports, street_index, validate};
 
 mod addresses;
+pub mod archive;
+pub mod changes;
 pub mod db;
+pub mod diff;
 pub mod elektrodistribucija_parser;
+pub mod error;
+pub mod export;
+pub mod fetcher;
+pub mod layout;
+pub mod outages;
+pub mod page_date;
+pub mod replay;
 pub mod time_interval;
 pub mod translit;
+pub mod validation;
+
+pub use addresses::AddressError;
+pub use error::ElectricityError;
+
+/// Entry points of the fuzz targets, see `fuzz/`.
+#[doc(hidden)]
+pub mod fuzzing {
+    /// Parses a row of addresses, returning the number of addresses in it.
+    pub fn parse_address_row(input: &str) -> Result<usize, crate::AddressError> {
+        Ok(crate::addresses::AddressRow::parse(input)?.iter().count())
+    }
+}
 
 pub static BEOGRAD_ELECTRICITY_PAGES: &[&str] = &[
     "https://elektrodistribucija.rs/planirana-iskljucenja-beograd/Dan_0_Iskljucenja.htm",
@@ -51,6 +81,9 @@ pub struct ElectricityFailuresData {
     time: String,
     date: String,
     addresses: addresses::AddressRow,
+    note: Option<String>,
+    /// Columns the parser doesn't know, by their titles.
+    extra: BTreeMap<String, String>,
 }
 
 impl Display for ElectricityFailuresData {
@@ -83,74 +116,223 @@ impl Display for ElectricityFailuresRawData {
     }
 }
 
-async fn fetch_page(url: &str) -> Result<String> {
-    let response = reqwest::get(url).await?;
-    Ok(response.text().await?)
+#[derive(Debug)]
+pub struct ParsedPage {
+    pub outages: Vec<ElectricityFailuresData>,
+    pub failures: Vec<RowFailure>,
 }
 
-pub async fn collect_data(db_client: &Client, table_name: &str, pages: &[&str]) -> Result<()> {
-    let span = span!(Level::TRACE, "collect_raw_data");
-    let _guard = span.enter();
+/// A row of the outages table with addresses the parser doesn't understand.
+#[derive(Debug)]
+pub struct RowFailure {
+    /// Index of the row in the table, the heading is the row 0.
+    pub row: usize,
+    /// The addresses as published.
+    pub text: String,
+    pub error: anyhow::Error,
+}
 
-    let requests = pages.iter().map(|page| {
-        let p = page.to_string();
-        tokio::task::spawn_blocking(move || {
-            tokio::task::spawn(async move {
-                let html = fetch_page(&p).await;
+impl Display for RowFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "row {}: {}: {:#}",
+            self.row,
+            self.text.trim(),
+            self.error
+        )
+    }
+}
 
-                (p.clone(), html)
-            })
-        })
-    });
+/// What happened to a page during the collection.
+#[derive(Debug)]
+pub enum PageStatus {
+    /// A new version of the page is stored, with the changes of the
+    /// schedule since the previous one.
+    Stored {
+        changes: Vec<Change>,
+    },
+    /// The page is downloaded, but it is the same as the last stored version.
+    Unchanged,
+    /// The server reports the page has not changed since the last download.
+    NotModified,
+    Failed(ElectricityError),
+}
 
-    let results = futures::future::join_all(requests).await;
+#[derive(Debug)]
+pub struct PageResult {
+    pub url: String,
+    pub status: PageStatus,
+}
 
-    event!(Level::INFO, "Finished collecting raw data");
+impl Display for PageResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.status {
+            PageStatus::Stored { changes } => write!(f, "{}: stored, {} changes", self.url, changes.len()),
+            PageStatus::Unchanged => write!(f, "{}: unchanged", self.url),
+            PageStatus::NotModified => write!(f, "{}: not modified", self.url),
+            PageStatus::Failed(err) => write!(f, "{}: failed: {err:#}", self.url),
+        }
+    }
+}
 
-    for result in results {
-        let (page, html) = result.unwrap().await?;
+#[derive(Debug)]
+pub struct CollectSummary {
+    pub pages: Vec<PageResult>,
+}
 
-        add_electricity_failure_raw_item(db_client, table_name, &html?, &page).await?;
+impl CollectSummary {
+    pub fn failed(&self) -> usize {
+        self.pages
+            .iter()
+            .filter(|it| matches!(it.status, PageStatus::Failed(_)))
+            .count()
     }
 
-    event!(Level::INFO, "Finished adding raw data to dynamodb");
+    /// Changes of the schedule on every page.
+    pub fn changes(&self) -> impl Iterator<Item = &Change> {
+        self.pages.iter().flat_map(|it| match &it.status {
+            PageStatus::Stored { changes } => changes.as_slice(),
+            _ => &[],
+        })
+    }
+}
 
-    Ok(())
+impl Display for CollectSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for page in &self.pages {
+            writeln!(f, "{page}")?;
+        }
+
+        Ok(())
+    }
 }
 
-fn format_date(date: String) -> Result<String> {
-    let mut naive_date = NaiveDate::parse_from_str(&date, "%Y-%m-%d");
+/// Downloads the pages and stores their new versions. Every page is handled
+/// on its own, so a page which fails does not affect the others.
+pub async fn collect_data<A: Archive>(
+    fetcher: &Fetcher,
+    archive: &A,
+    db_client: &Client,
+    table_name: &str,
+    pages: &[&str],
+) -> CollectSummary {
+    let span = span!(Level::TRACE, "collect_raw_data");
+    let _guard = span.enter();
 
-    if naive_date.is_err() {
-        naive_date = NaiveDate::parse_from_str(&date, "%d-%m-%Y");
+    let results = futures::future::join_all(pages.iter().map(|&page| async move {
+        let status = match collect_page(fetcher, archive, db_client, table_name, page).await {
+            Ok(status) => status,
+            Err(err) => PageStatus::Failed(err),
+        };
+
+        PageResult {
+            url: page.to_owned(),
+            status,
+        }
+    }))
+    .await;
+
+    let summary = CollectSummary { pages: results };
+    for page in &summary.pages {
+        match &page.status {
+            // The next run fetches the page again.
+            PageStatus::Failed(err) if err.is_transient() => event!(Level::WARN, "{page}"),
+            PageStatus::Failed(_) => event!(Level::ERROR, "{page}"),
+            _ => event!(Level::INFO, "{page}"),
+        }
     }
 
-    Ok(naive_date?.format("%d-%m-%Y").to_string())
+    summary
+}
+
+async fn collect_page<A: Archive>(
+    fetcher: &Fetcher,
+    archive: &A,
+    db_client: &Client,
+    table_name: &str,
+    page: &str,
+) -> Result<PageStatus, ElectricityError> {
+    let (html, validators) = match fetcher.fetch(page).await? {
+        Fetched::Page { html, validators } => (html, validators),
+        Fetched::NotModified => return Ok(PageStatus::NotModified),
+    };
+
+    let raw_item = add_electricity_failure_raw_item(archive, db_client, table_name, &html, page).await?;
+    // Only a stored page may be skipped as not modified next time.
+    fetcher.commit(page, validators);
+
+    let previous_hash = match raw_item {
+        RawItem::Unchanged => return Ok(PageStatus::Unchanged),
+        RawItem::Stored { previous_hash } => previous_hash,
+    };
+
+    // The new version is stored already, so failing to compare it is only reported.
+    let changes = match previous_hash {
+        Some(previous_hash) => page_changes(archive, &previous_hash, &html)
+            .await
+            .unwrap_or_else(|err| {
+                event!(Level::WARN, page, error = %err, "failed to compare the page with its previous version");
+                vec![]
+            }),
+        None => vec![],
+    };
+
+    Ok(PageStatus::Stored { changes })
+}
+
+/// Changes of the schedule since the archived version of the page.
+async fn page_changes<A: Archive>(archive: &A, previous_hash: &str, html: &str) -> Result<Vec<Change>> {
+    // Versions stored before the archive are not there.
+    let Some(previous) = archive.get(previous_hash).await? else {
+        return Ok(vec![]);
+    };
+    let previous = String::from_utf8(previous).with_context(|| format!("page {previous_hash} is not UTF-8"))?;
+
+    diff(&parse_page(&previous)?, &parse_page(html)?)
+}
+
+enum RawItem {
+    /// The page is the same as its last stored version.
+    Unchanged,
+    Stored {
+        /// Hash of the last stored version of the page for the same day.
+        previous_hash: Option<String>,
+    },
 }
 
-async fn add_electricity_failure_raw_item(client: &Client, table_name: &str, html: &str, page: &str) -> Result<()> {
+/// Stores the page unless it is the same as its last stored version. The
+/// page goes to the archive, the item only refers to it by the hash.
+async fn add_electricity_failure_raw_item<A: Archive>(
+    archive: &A,
+    client: &Client,
+    table_name: &str,
+    html: &str,
+    page: &str,
+) -> Result<RawItem, ElectricityError> {
     let id = Uuid::new_v4().to_string();
-    let date = get_page_date(html).and_then(format_date)?;
+    let date = get_page_date(html)?.format(DATE_FORMAT).to_string();
     let page = page.to_owned();
-    let hash = {
-        let mut hasher = DefaultHasher::new();
-        html.hash(&mut hasher);
-        hasher.finish().to_string()
-    };
+    // Versions stored before the archive were hashed differently, so the
+    // first collected version of a page is stored again.
+    let hash = content_hash(html.as_bytes());
 
     let (last_version, last_version_hash) =
         find_last_electricity_failure_raw_version(client, table_name, page.to_owned(), date.to_owned()).await?;
 
-    if let Some(last_version_hash) = last_version_hash {
-        if last_version_hash == hash {
-            return Ok(());
-        }
+    if last_version_hash.as_ref() == Some(&hash) {
+        return Ok(RawItem::Unchanged);
     }
 
+    // Archived first, so an item never refers to a missing page.
+    archive
+        .put(html.as_bytes())
+        .await
+        .map_err(ElectricityError::Storage)?;
+
     let id_av = AttributeValue::S(id);
     let date_av = AttributeValue::S(date);
     let page_av = AttributeValue::S(page);
-    let html_av = AttributeValue::S(html.to_owned());
     let hash_av = AttributeValue::S(hash);
     let version_av = AttributeValue::N((last_version + 1).to_string());
 
@@ -160,13 +342,14 @@ async fn add_electricity_failure_raw_item(client: &Client, table_name: &str, htm
         .item("id", id_av)
         .item("date", date_av)
         .item("url", page_av)
-        .item("html", html_av)
         .item("hash", hash_av)
         .item("version", version_av);
 
-    let _ = request.send().await?;
+    let _ = request.send().await.map_err(ElectricityError::storage)?;
 
-    Ok(())
+    Ok(RawItem::Stored {
+        previous_hash: last_version_hash,
+    })
 }
 
 async fn find_last_electricity_failure_raw_version(
@@ -174,7 +357,7 @@ async fn find_last_electricity_failure_raw_version(
     table_name: &str,
     url: String,
     date: String,
-) -> Result<(i32, Option<String>)> {
+) -> Result<(i32, Option<String>), ElectricityError> {
     let url_av = AttributeValue::S(url);
     let date_av = AttributeValue::S(date);
 
@@ -187,19 +370,14 @@ async fn find_last_electricity_failure_raw_version(
         .expression_attribute_values(":url", url_av)
         .expression_attribute_values(":date", date_av)
         .send()
-        .await?;
+        .await
+        .map_err(ElectricityError::storage)?;
 
     let mut last_version = 0;
     let mut last_version_hash = None;
 
-    for items in results.items() {
-        let version = items
-            .get("version")
-            .and_then(|av| av.as_n().ok())
-            .map(|n| n.parse::<i32>().expect("failed to parse version"))
-            .context("version is missing")
-            .unwrap();
-        let hash = items.get("hash").unwrap().as_s().unwrap().to_owned();
+    for item in results.items() {
+        let RawPageVersion { version, hash, .. } = RawPageVersion::try_from(item).map_err(corrupt_item)?;
         if version > last_version {
             last_version = version;
             last_version_hash = Some(hash);
@@ -209,74 +387,239 @@ async fn find_last_electricity_failure_raw_version(
     Ok((last_version, last_version_hash))
 }
 
-pub async fn parse_all_records(client: &Client, raw_data_table_name: &str, data_table_name: &str) -> Result<()> {
-    let results = client.scan().table_name(raw_data_table_name).send().await?;
+/// Parses every stored page into the data table, and into the bot's
+/// Postgres `outages` table when `outages_pool` is given.
+pub async fn parse_all_records<A: Archive>(
+    client: &Client,
+    archive: &A,
+    raw_data_table_name: &str,
+    data_table_name: &str,
+    outages_pool: Option<&PgPool>,
+) -> Result<(), ElectricityError> {
+    let results = client
+        .scan()
+        .table_name(raw_data_table_name)
+        .send()
+        .await
+        .map_err(ElectricityError::storage)?;
 
     for item in results.items() {
-        let id = item
-            .get("id")
-            .and_then(|av| av.as_s().ok())
-            .map(ToOwned::to_owned)
-            .context("id is missing")?;
-        let date = item
-            .get("date")
-            .and_then(|av| av.as_s().ok())
-            .map(ToOwned::to_owned)
-            .context("date is missing")?;
-        let url = item
-            .get("url")
-            .and_then(|av| av.as_s().ok())
-            .map(ToOwned::to_owned)
-            .context("url is missing")?;
-        let html = item
-            .get("html")
-            .and_then(|av| av.as_s().ok())
-            .map(ToOwned::to_owned)
-            .context("html is missing")?;
-        let hash = item
-            .get("hash")
-            .and_then(|av| av.as_s().ok())
-            .map(ToOwned::to_owned)
-            .context("hash is missing")?;
-        let version = item
-            .get("version")
-            .and_then(|av| av.as_n().ok())
-            .map(|n| n.parse::<i32>().expect("failed to parse version"))
-            .context("version is missing")?;
-
-        let raw_data = ElectricityFailuresRawData {
-            id,
-            date,
-            url,
-            html,
-            hash,
-            version,
-        };
+        if let Some(data) = parse_raw_item(archive, item).await? {
+            save_parsed_data(client, data_table_name, outages_pool, &data).await?;
+        }
+    }
 
-        let data = parse_raw_data_to_data(&raw_data)?;
+    Ok(())
+}
 
-        for d in data {
-            save_electricity_failure_data(client, data_table_name, &d).await?;
+/// Outages of a stored page, `None` when the page can't be parsed or its item
+/// is corrupt, so it doesn't stop the other pages.
+async fn parse_raw_item<A: Archive>(
+    archive: &A,
+    item: &HashMap<String, AttributeValue>,
+) -> Result<Option<Vec<ElectricityFailuresData>>, ElectricityError> {
+    let data = load_raw_data(archive, item)
+        .await
+        .and_then(|raw_data| parse_raw_data_to_data(&raw_data));
+
+    match data {
+        Ok(data) => Ok(Some(data)),
+        Err(err) if !err.is_transient() => {
+            event!(Level::ERROR, error = %err, "skipping raw item");
+            Ok(None)
         }
+        Err(err) => Err(err),
+    }
+}
+
+async fn save_parsed_data(
+    client: &Client,
+    data_table_name: &str,
+    outages_pool: Option<&PgPool>,
+    data: &[ElectricityFailuresData],
+) -> Result<(), ElectricityError> {
+    report_streets(data);
+    for d in data {
+        save_electricity_failure_data(client, data_table_name, d).await?;
+    }
+    if let Some(pool) = outages_pool {
+        let added = db::outages::save_outages(pool, data)
+            .await
+            .map_err(ElectricityError::storage)?;
+        event!(Level::INFO, added, "saved outages to postgres");
     }
 
     Ok(())
 }
 
-pub async fn parse_and_save_raw_data(
+/// Logs the streets of the outages which are not found in `beograd_streets`.
+fn report_streets(outages: &[ElectricityFailuresData]) {
+    for report in daily_reports(&validate(street_index(), outages)) {
+        if report.is_clean() {
+            event!(
+                Level::INFO,
+                date = report.date,
+                matched = report.matched,
+                "all streets are known"
+            );
+        } else {
+            event!(Level::WARN, date = report.date, "unknown streets\n{report}");
+        }
+    }
+}
+
+pub async fn parse_and_save_raw_data<A: Archive>(
     client: &Client,
+    archive: &A,
     raw_data_table_name: &str,
     data_table_name: &str,
+    outages_pool: Option<&PgPool>,
     id: &str,
-) -> Result<()> {
-    let raw_data = find_electricity_failure_raw_data_by_id(client, raw_data_table_name, id).await?;
+) -> Result<(), ElectricityError> {
+    let raw_data = find_electricity_failure_raw_data_by_id(client, archive, raw_data_table_name, id).await?;
     let data = parse_raw_data_to_data(&raw_data)?;
 
-    for d in data {
-        save_electricity_failure_data(client, data_table_name, &d).await?;
+    save_parsed_data(client, data_table_name, outages_pool, &data).await
+}
+
+/// A stored version of a page, the page itself is in the archive under the hash.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawPageVersion {
+    pub id: String,
+    pub date: String,
+    pub url: String,
+    pub hash: String,
+    pub version: i32,
+}
+
+impl Display for RawPageVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} v{} {} {}",
+            self.url, self.date, self.version, self.hash, self.id
+        )
     }
+}
 
-    Ok(())
+impl TryFrom<&HashMap<String, AttributeValue>> for RawPageVersion {
+    type Error = anyhow::Error;
+
+    fn try_from(item: &HashMap<String, AttributeValue>) -> Result<Self> {
+        let string = |field: &str| {
+            item.get(field)
+                .and_then(|av| av.as_s().ok())
+                .map(ToOwned::to_owned)
+                .with_context(|| format!("{field} is missing"))
+        };
+        let version = item
+            .get("version")
+            .and_then(|av| av.as_n().ok())
+            .context("version is missing")?;
+
+        Ok(Self {
+            id: string("id")?,
+            date: string("date")?,
+            url: string("url")?,
+            hash: string("hash")?,
+            version: version
+                .parse()
+                .with_context(|| format!("invalid version {version}"))?,
+        })
+    }
+}
+
+/// Versions of the pages, or of a single one, ordered by page, date and version.
+pub async fn list_raw_versions(
+    client: &Client,
+    table_name: &str,
+    url: Option<&str>,
+) -> Result<Vec<RawPageVersion>, ElectricityError> {
+    let mut versions = vec![];
+    let mut start_key = None;
+
+    loop {
+        let response = client
+            .scan()
+            .table_name(table_name)
+            .set_exclusive_start_key(start_key)
+            .send()
+            .await
+            .map_err(ElectricityError::storage)?;
+
+        for item in response.items() {
+            let version = RawPageVersion::try_from(item).map_err(corrupt_item)?;
+            if url.is_none_or(|url| url == version.url) {
+                versions.push(version);
+            }
+        }
+
+        start_key = response.last_evaluated_key;
+        if start_key.is_none() {
+            break;
+        }
+    }
+
+    versions.sort_by(|a, b| {
+        (
+            &a.url,
+            NaiveDate::parse_from_str(&a.date, DATE_FORMAT).ok(),
+            a.version,
+        )
+            .cmp(&(
+                &b.url,
+                NaiveDate::parse_from_str(&b.date, DATE_FORMAT).ok(),
+                b.version,
+            ))
+    });
+
+    Ok(versions)
+}
+
+fn corrupt_item(error: anyhow::Error) -> ElectricityError {
+    ElectricityError::CorruptItem(format!("{error:#}"))
+}
+
+async fn load_raw_data<A: Archive>(
+    archive: &A,
+    item: &HashMap<String, AttributeValue>,
+) -> Result<ElectricityFailuresRawData, ElectricityError> {
+    let RawPageVersion {
+        id,
+        date,
+        url,
+        hash,
+        version,
+    } = RawPageVersion::try_from(item).map_err(corrupt_item)?;
+
+    // Items stored before the archive keep the page inline.
+    let html = match item.get("html").and_then(|av| av.as_s().ok()) {
+        Some(html) => html.to_owned(),
+        None => {
+            if !is_content_hash(&hash) {
+                return Err(ElectricityError::CorruptItem(format!(
+                    "invalid hash {hash} of item {id}"
+                )));
+            }
+            let content = archive
+                .get(&hash)
+                .await
+                .map_err(ElectricityError::Storage)?
+                .ok_or_else(|| ElectricityError::NotFound(format!("page {hash} in the archive")))?;
+
+            String::from_utf8(content)
+                .map_err(|_| ElectricityError::CorruptItem(format!("page {hash} is not UTF-8")))?
+        }
+    };
+
+    Ok(ElectricityFailuresRawData {
+        id,
+        date,
+        url,
+        html,
+        hash,
+        version,
+    })
 }
 
 async fn add_electricity_failure_record(
@@ -284,7 +627,7 @@ async fn add_electricity_failure_record(
     table_name: &str,
     data: &ElectricityFailuresData,
     record: Address,
-) -> Result<()> {
+) -> Result<(), ElectricityError> {
     let id = AttributeValue::S(Uuid::new_v4().to_string());
     let city_av = AttributeValue::S(data.city.to_owned());
     let region_av = AttributeValue::S(data.region.to_owned());
@@ -292,7 +635,7 @@ async fn add_electricity_failure_record(
     let street_av = AttributeValue::S(record.street);
     let date_av = AttributeValue::S(data.date.to_owned());
 
-    let request = client
+    let mut request = client
         .put_item()
         .table_name(table_name)
         .item("id", id)
@@ -311,10 +654,20 @@ async fn add_electricity_failure_record(
         .item("street", street_av)
         .item(
             "buildings",
-            AttributeValue::S(serde_json::to_string(&record.buildings)?),
+            AttributeValue::S(serde_json::to_string(&record.buildings).map_err(ElectricityError::storage)?),
+        );
+
+    if let Some(note) = &data.note {
+        request = request.item("note", AttributeValue::S(note.to_owned()));
+    }
+    if !data.extra.is_empty() {
+        request = request.item(
+            "extra",
+            AttributeValue::S(serde_json::to_string(&data.extra).map_err(ElectricityError::storage)?),
         );
+    }
 
-    let _ = request.send().await?;
+    let _ = request.send().await.map_err(ElectricityError::storage)?;
 
     Ok(())
 }
@@ -323,7 +676,7 @@ pub async fn save_electricity_failure_data(
     client: &Client,
     table_name: &str,
     data: &ElectricityFailuresData,
-) -> Result<()> {
+) -> Result<(), ElectricityError> {
     let addresses = data.addresses.clone();
 
     for address in addresses.into_iter() {
@@ -333,11 +686,12 @@ pub async fn save_electricity_failure_data(
     Ok(())
 }
 
-async fn find_electricity_failure_raw_data_by_id(
+async fn find_electricity_failure_raw_data_by_id<A: Archive>(
     client: &Client,
+    archive: &A,
     table_name: &str,
     id: &str,
-) -> Result<ElectricityFailuresRawData> {
+) -> Result<ElectricityFailuresRawData, ElectricityError> {
     let id_av = AttributeValue::S(id.to_owned());
 
     let results = client
@@ -347,154 +701,125 @@ async fn find_electricity_failure_raw_data_by_id(
         .expression_attribute_names("#id", "id")
         .expression_attribute_values(":id", id_av)
         .send()
-        .await?;
+        .await
+        .map_err(ElectricityError::storage)?;
 
-    let item = results.items().first();
+    match results.items().first() {
+        Some(item) => load_raw_data(archive, item).await,
+        None => Err(ElectricityError::NotFound(format!("raw item {id}"))),
+    }
+}
 
-    if let Some(attributes) = item {
-        let id = attributes
-            .get("id")
-            .and_then(|av| av.as_s().ok())
-            .map(ToOwned::to_owned)
-            .context("id is missing")?;
-        let date = attributes
-            .get("date")
-            .and_then(|av| av.as_s().ok())
-            .map(ToOwned::to_owned)
-            .context("date is missing")?;
-        let url = attributes
-            .get("url")
-            .and_then(|av| av.as_s().ok())
-            .map(ToOwned::to_owned)
-            .context("url is missing")?;
-        let html = attributes
-            .get("html")
-            .and_then(|av| av.as_s().ok())
-            .map(ToOwned::to_owned)
-            .context("html is missing")?;
-        let hash = attributes
-            .get("hash")
-            .and_then(|av| av.as_s().ok())
-            .map(ToOwned::to_owned)
-            .context("hash is missing")?;
-        let version = attributes
-            .get("version")
-            .and_then(|av| av.as_n().ok())
-            .map(|n| n.parse::<i32>().expect("failed to parse version"))
-            .context("version is missing")?;
+pub fn parse_raw_data_to_data(
+    data: &ElectricityFailuresRawData,
+) -> Result<Vec<ElectricityFailuresData>, ElectricityError> {
+    parse_page(&data.html)
+}
 
-        return Ok(ElectricityFailuresRawData {
-            id,
-            date,
-            url,
-            html,
-            hash,
-            version,
-        });
-    }
+pub fn parse_page(page_html: &str) -> Result<Vec<ElectricityFailuresData>, ElectricityError> {
+    Ok(parse_page_with_failures(page_html)?.outages)
+}
 
-    Err(anyhow!("Item not found"))
+/// The outages of a page, along with the rows of the table which could not
+/// be parsed and are skipped.
+pub fn parse_page_with_failures(page_html: &str) -> Result<ParsedPage, ElectricityError> {
+    parse_page_with_aliases(page_html, &ColumnAliases::default())
 }
 
-pub fn parse_raw_data_to_data(data: &ElectricityFailuresRawData) -> Result<Vec<ElectricityFailuresData>> {
-    let page_html = data.html.to_owned();
-    let header: String = get_page_header(&page_html);
-    let date = header
-        .split(' ')
-        .last()
-        .ok_or(anyhow!("Cell is missing"))?
-        .to_owned()
-        .split('.')
-        .filter(|&str| !str.is_empty())
-        .collect::<Vec<_>>()
-        .join("-");
+/// Parses a page whose outages table may use other column titles.
+pub fn parse_page_with_aliases(page_html: &str, aliases: &ColumnAliases) -> Result<ParsedPage, ElectricityError> {
+    let header: String = get_page_header(page_html)?;
+    let date = parse_page_date(&header)?.format(DATE_FORMAT).to_string();
     let city = header
         .split(" - ")
         .collect::<Vec<_>>()
         .first()
-        .ok_or(anyhow!("Cell is missing"))?
+        .ok_or_else(|| ElectricityError::LayoutChanged("header is missing".to_owned()))?
         .to_string();
 
-    let table = get_content_table_html(&page_html);
+    let (table, layout) = find_outages_table(page_html, aliases)?;
     let tr_selector = tr_selector();
     let td_selector = td_selector();
 
     let mut table_rows: Vec<ElectricityFailuresData> = vec![];
+    let mut failures: Vec<RowFailure> = vec![];
 
     let rows = table.select(tr_selector).collect::<Vec<_>>();
-    let heading_row = rows.get(0).ok_or(anyhow!("Heading row is missing"))?;
-    let heading_td = heading_row.select(td_selector);
-
-    let street_index = heading_td
-        .clone()
-        .position(|cell| {
-            let title: String = cell.text().collect::<Vec<_>>().into_iter().collect();
 
-            title == "Улице"
-        })
-        .unwrap();
-    let time_index = heading_td
-        .clone()
-        .position(|cell| {
-            let title: String = cell.text().collect::<Vec<_>>().into_iter().collect();
-
-            title == "Време"
-        })
-        .unwrap();
-    let region_index = heading_td
-        .clone()
-        .position(|cell| {
-            let title: String = cell.text().collect::<Vec<_>>().into_iter().collect();
-
-            title == "Општина"
-        })
-        .unwrap();
-
-    for row in rows.iter().skip(1) {
+    for (index, row) in rows.iter().enumerate().skip(1) {
         let cells = row.select(td_selector).collect::<Vec<_>>();
 
         let region = cells
-            .get(region_index)
-            .ok_or(anyhow!("Cell is missing"))?
+            .get(layout.region)
+            .ok_or_else(|| ElectricityError::LayoutChanged(format!("a cell of row {index} is missing")))?
             .text()
             .collect::<Vec<_>>()
             .into_iter()
             .collect::<String>();
         let time = cells
-            .get(time_index)
-            .ok_or(anyhow!("Cell is missing"))?
+            .get(layout.time)
+            .ok_or_else(|| ElectricityError::LayoutChanged(format!("a cell of row {index} is missing")))?
             .text()
             .collect::<Vec<_>>()
             .into_iter()
             .collect::<String>();
         let street = cells
-            .get(street_index)
-            .ok_or(anyhow!("Cell is missing"))?
+            .get(layout.streets)
+            .ok_or_else(|| ElectricityError::LayoutChanged(format!("a cell of row {index} is missing")))?
             .text()
             .collect::<Vec<_>>()
             .into_iter()
             .collect::<String>();
+        let note = layout
+            .note
+            .and_then(|index| cells.get(index))
+            .map(|cell| cell.text().collect::<String>().trim().to_owned())
+            .filter(|it| !it.is_empty());
+        let extra = layout
+            .extra
+            .iter()
+            .filter_map(|(index, title)| {
+                let value = cells
+                    .get(*index)?
+                    .text()
+                    .collect::<String>()
+                    .trim()
+                    .to_owned();
+
+                (!value.is_empty()).then(|| (title.to_owned(), value))
+            })
+            .collect::<BTreeMap<_, _>>();
         let translited_street = street.translit();
 
         let addresses = addresses::AddressRow::parse(translited_street.trim_end());
 
-        if addresses.is_ok() {
-            table_rows.push(ElectricityFailuresData {
+        match addresses {
+            Ok(addresses) => table_rows.push(ElectricityFailuresData {
                 city: city.to_owned(),
                 region,
                 time,
-                date: format_date(date.to_owned())?,
-                addresses: addresses.unwrap(),
-            });
+                date: date.to_owned(),
+                addresses,
+                note,
+                extra,
+            }),
+            Err(error) => failures.push(RowFailure {
+                row: index,
+                text: street,
+                error: error.into(),
+            }),
         }
     }
 
-    Ok(table_rows)
+    Ok(ParsedPage {
+        outages: table_rows,
+        failures,
+    })
 }
 
-pub async fn find_ongoing_failures(client: &Client, data_table_name: &str) -> Result<Vec<String>> {
+pub async fn find_ongoing_failures(client: &Client, data_table_name: &str) -> Result<Vec<String>, ElectricityError> {
     let hours_24_from_now = chrono::Utc::now() + chrono::Duration::hours(24);
-    let formatted_date = hours_24_from_now.format("%d-%m-%Y").to_string();
+    let formatted_date = hours_24_from_now.format(DATE_FORMAT).to_string();
     let date_av = AttributeValue::S(formatted_date.to_owned());
 
     let results = client
@@ -504,7 +829,8 @@ pub async fn find_ongoing_failures(client: &Client, data_table_name: &str) -> Re
         .expression_attribute_names("#date", "date")
         .expression_attribute_values(":date", date_av)
         .send()
-        .await?;
+        .await
+        .map_err(ElectricityError::storage)?;
 
     let mut data: Vec<String> = vec![];
     for items in results.items() {
@@ -512,7 +838,7 @@ pub async fn find_ongoing_failures(client: &Client, data_table_name: &str) -> Re
             .get("street")
             .and_then(|av| av.as_s().ok())
             .map(ToOwned::to_owned)
-            .context("street is missing")?;
+            .ok_or_else(|| ElectricityError::CorruptItem("street is missing".to_owned()))?;
 
         data.push(street);
     }
@@ -572,4 +898,179 @@ mod tests {
         assert_eq!(rows[0].time, "08:00 - 16:00");
         assert_eq!(rows[0].date, "01-01-2021");
     }
+    #[test]
+    fn test_parse_page_with_unknown_layout() {
+        let page = r#"
+            <table><tr><td><b>БЕОГРАД - Планирана искључења за датум: 2023-11-20</b></td></tr></table>
+            <table>
+                <tr><td>Општина</td><td>Време</td><td>Адресе</td></tr>
+                <tr><td>Палилула</td><td>08:00 - 16:00</td><td>Таковска: 5</td></tr>
+            </table>
+        "#;
+
+        assert!(matches!(
+            parse_page(page),
+            Err(ElectricityError::MissingColumn("Улице"))
+        ));
+        assert!(matches!(
+            parse_page("<table></table>"),
+            Err(ElectricityError::LayoutChanged(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_page_with_leading_table() {
+        let page = r#"
+            <table><tr><td><b>Обавештење</b></td></tr></table>
+            <table><tr><td><b>БЕОГРАД - Планирана искључења за датум: 2023-11-20</b></td></tr></table>
+            <table>
+                <tr><td>Општина</td><td>Време</td><td>Улице</td></tr>
+                <tr><td>Палилула</td><td>08:00 - 16:00</td><td>Таковска: 5</td></tr>
+            </table>
+        "#;
+        let rows = parse_page(page).unwrap();
+
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].city, "БЕОГРАД");
+        assert_eq!(rows[0].date, "20-11-2023");
+    }
+
+    #[test]
+    fn test_parse_page_with_renamed_columns() {
+        let page = r#"
+            <table><tr><td><b>БЕОГРАД - Планирана искључења за датум: 2023-11-20</b></td></tr></table>
+            <table>
+                <tr><td>Ulice</td><td>Opština</td><td>Vreme</td><td>Напомена</td><td>ТС</td></tr>
+                <tr><td>Таковска: 5</td><td>Палилула</td><td>08:00 - 16:00</td><td>радови на мрежи</td><td>ТС 10/0,4</td></tr>
+                <tr><td>Цетињска: 1</td><td>Стари град</td><td>09:00 - 13:00</td><td></td></tr>
+            </table>
+        "#;
+        let rows = parse_page(page).unwrap();
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].region, "Палилула");
+        assert_eq!(rows[0].note.as_deref(), Some("радови на мрежи"));
+        assert_eq!(
+            rows[0].extra,
+            BTreeMap::from([("ТС".to_string(), "ТС 10/0,4".to_string())])
+        );
+        assert_eq!(rows[1].note, None);
+        assert!(rows[1].extra.is_empty());
+
+        let aliases = ColumnAliases::default().with(layout::Column::Streets, "Адресе");
+        let renamed = page.replace("Ulice", "Адресе");
+        assert_eq!(
+            parse_page_with_aliases(&renamed, &aliases)
+                .unwrap()
+                .outages
+                .len(),
+            2
+        );
+    }
+
+    fn raw_item(hash: &str, html: Option<&str>) -> HashMap<String, AttributeValue> {
+        let mut item = HashMap::from([
+            ("id".to_string(), AttributeValue::S("id".to_string())),
+            (
+                "date".to_string(),
+                AttributeValue::S("20-11-2023".to_string()),
+            ),
+            ("url".to_string(), AttributeValue::S("url".to_string())),
+            ("hash".to_string(), AttributeValue::S(hash.to_string())),
+            ("version".to_string(), AttributeValue::N("2".to_string())),
+        ]);
+        if let Some(html) = html {
+            item.insert("html".to_string(), AttributeValue::S(html.to_string()));
+        }
+
+        item
+    }
+
+    #[tokio::test]
+    async fn test_load_archived_raw_data() {
+        let root = tempfile::tempdir().unwrap();
+        let archive = archive::FsArchive::new(root.path());
+        let hash = archive.put("<html>Улице</html>".as_bytes()).await.unwrap();
+
+        let raw_data = load_raw_data(&archive, &raw_item(&hash, None))
+            .await
+            .unwrap();
+
+        assert_eq!(raw_data.html, "<html>Улице</html>");
+        assert_eq!(raw_data.hash, hash);
+        assert_eq!(raw_data.version, 2);
+    }
+
+    #[tokio::test]
+    async fn test_load_inline_raw_data() {
+        let root = tempfile::tempdir().unwrap();
+        let archive = archive::FsArchive::new(root.path());
+
+        let raw_data = load_raw_data(&archive, &raw_item("12345", Some("<html></html>")))
+            .await
+            .unwrap();
+
+        assert_eq!(raw_data.html, "<html></html>");
+        assert!(
+            load_raw_data(&archive, &raw_item(&content_hash(b"other"), None))
+                .await
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_raw_page_version() {
+        let version = RawPageVersion::try_from(&raw_item("hash", Some("<html></html>"))).unwrap();
+
+        assert_eq!(
+            version,
+            RawPageVersion {
+                id: "id".to_string(),
+                date: "20-11-2023".to_string(),
+                url: "url".to_string(),
+                hash: "hash".to_string(),
+                version: 2,
+            }
+        );
+        assert!(RawPageVersion::try_from(&HashMap::new()).is_err());
+    }
+
+    fn page(time: &str) -> String {
+        format!(
+            r#"<html><body>
+                <table><tbody><tr><td><b>Београд - Дан - 20.11.2023</b></td></tr></tbody></table>
+                <table><tbody>
+                    <tr><td>Општина</td><td>Време</td><td>Улице</td></tr>
+                    <tr><td>Палилула</td><td>{time}</td><td>Таковска: 5</td></tr>
+                </tbody></table>
+            </body></html>"#
+        )
+    }
+
+    #[tokio::test]
+    async fn test_page_changes() {
+        let root = tempfile::tempdir().unwrap();
+        let archive = archive::FsArchive::new(root.path());
+        let previous = page("09:00 - 13:00");
+        let hash = archive.put(previous.as_bytes()).await.unwrap();
+
+        let changes = page_changes(&archive, &hash, &page("10:00 - 15:00"))
+            .await
+            .unwrap();
+
+        assert_eq!(changes.len(), 1);
+        assert!(matches!(
+            &changes[0],
+            Change::Modified { place, from, to, .. }
+                if place.street == "takovska" && from == "09:00 - 13:00" && to == "10:00 - 15:00"
+        ));
+        assert!(page_changes(&archive, &hash, &previous)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(page_changes(&archive, &content_hash(b"legacy"), &previous)
+            .await
+            .unwrap()
+            .is_empty());
+    }
 }
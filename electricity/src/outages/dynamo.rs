@@ -0,0 +1,163 @@
+//! Outages are read from the table the electricity parser writes to, see
+//! [`crate::db::data_table_name`].
+use super::models::{NewOutage, Outage};
+use super::repository::Repository;
+use crate::db::table_spec::{IndexSpec, KeySpec, TableSpec};
+use crate::db::{data_table_name, DATE_FORMAT};
+use anyhow::{Context, Result};
+use aws_sdk_dynamodb::types::AttributeValue;
+use aws_sdk_dynamodb::Client;
+use chrono::NaiveDate;
+use std::collections::HashMap;
+use tracing::{event, Level};
+
+const ID_FIELD: &str = "id";
+const DATE_FIELD: &str = "date";
+const TIME_FIELD: &str = "time";
+const REGION_FIELD: &str = "region";
+const SETTLEMENT_FIELD: &str = "settlement";
+const STREET_FIELD: &str = "street";
+/// Index of the outages by their date, see [`Repository::find_on`].
+const DATE_INDEX: &str = "by_date";
+
+pub fn tables() -> Vec<TableSpec> {
+    vec![TableSpec::new(&data_table_name(), KeySpec::s(ID_FIELD))
+        .with_index(IndexSpec::new(DATE_INDEX, KeySpec::s(DATE_FIELD)))]
+}
+
+impl Repository for Client {
+    async fn append(&self, outage: NewOutage) -> Result<()> {
+        let date = outage.date.format(DATE_FORMAT).to_string();
+        // Unlike the parser, which generates random ids, the id is derived
+        // from the content, so appending the same outage twice is a no-op.
+        let id = format!(
+            "{date}|{}|{}|{}|{}",
+            outage.time,
+            outage.region,
+            outage.settlement.as_deref().unwrap_or_default(),
+            outage.street
+        );
+
+        self.put_item()
+            .table_name(data_table_name())
+            .item(ID_FIELD, AttributeValue::S(id))
+            .item(DATE_FIELD, AttributeValue::S(date))
+            .item(TIME_FIELD, AttributeValue::S(outage.time))
+            .item(REGION_FIELD, AttributeValue::S(outage.region))
+            .item(
+                SETTLEMENT_FIELD,
+                match outage.settlement {
+                    Some(settlement) => AttributeValue::S(settlement),
+                    None => AttributeValue::Null(true),
+                },
+            )
+            .item(STREET_FIELD, AttributeValue::S(outage.street))
+            .send()
+            .await?;
+
+        Ok(())
+    }
+
+    async fn find_by_id(&self, id: &str) -> Result<Option<Outage>> {
+        let response = self
+            .get_item()
+            .table_name(data_table_name())
+            .key(ID_FIELD, AttributeValue::S(id.to_owned()))
+            .send()
+            .await?;
+
+        response.item.as_ref().map(to_outage).transpose()
+    }
+
+    async fn find_on(&self, date: NaiveDate) -> Result<Vec<Outage>> {
+        let mut outages = vec![];
+        let mut start_key = None;
+
+        loop {
+            let response = self
+                .query()
+                .table_name(data_table_name())
+                .index_name(DATE_INDEX)
+                .key_condition_expression("#date = :date")
+                .expression_attribute_names("#date", DATE_FIELD)
+                .expression_attribute_values(
+                    ":date",
+                    AttributeValue::S(date.format(DATE_FORMAT).to_string()),
+                )
+                .set_exclusive_start_key(start_key)
+                .send()
+                .await?;
+
+            for item in response.items() {
+                match to_outage(item) {
+                    Ok(outage) => outages.push(outage),
+                    Err(err) => event!(Level::WARN, error = %err, "skipping outage item"),
+                }
+            }
+
+            start_key = response.last_evaluated_key;
+            if start_key.is_none() {
+                break;
+            }
+        }
+
+        Ok(outages)
+    }
+
+    // Dates are not sortable in the parser's format, so the table is scanned
+    // and filtered here.
+    async fn find_since(&self, date: NaiveDate) -> Result<Vec<Outage>> {
+        let mut outages = vec![];
+        let mut start_key = None;
+
+        loop {
+            let response = self
+                .scan()
+                .table_name(data_table_name())
+                .set_exclusive_start_key(start_key)
+                .send()
+                .await?;
+
+            for item in response.items() {
+                // A malformed item doesn't hide the other outages.
+                let outage = match to_outage(item) {
+                    Ok(outage) => outage,
+                    Err(err) => {
+                        event!(Level::WARN, error = %err, "skipping outage item");
+                        continue;
+                    }
+                };
+                if outage.date >= date {
+                    outages.push(outage);
+                }
+            }
+
+            start_key = response.last_evaluated_key;
+            if start_key.is_none() {
+                break;
+            }
+        }
+
+        Ok(outages)
+    }
+}
+
+fn to_outage(item: &HashMap<String, AttributeValue>) -> Result<Outage> {
+    let string = |field: &str| {
+        item.get(field)
+            .and_then(|av| av.as_s().ok())
+            .map(ToOwned::to_owned)
+            .with_context(|| format!("{field} is missing"))
+    };
+
+    let date = string(DATE_FIELD)?;
+
+    Ok(Outage {
+        id: string(ID_FIELD)?,
+        date: NaiveDate::parse_from_str(&date, DATE_FORMAT).with_context(|| format!("invalid date {date}"))?,
+        time: string(TIME_FIELD)?,
+        region: string(REGION_FIELD)?,
+        settlement: string(SETTLEMENT_FIELD).ok(),
+        street: string(STREET_FIELD)?,
+    })
+}
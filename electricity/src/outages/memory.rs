@@ -0,0 +1,61 @@
+use super::models::{NewOutage, Outage};
+use super::repository::Repository;
+use anyhow::Result;
+use chrono::NaiveDate;
+use std::sync::RwLock;
+
+/// Outages kept in memory.
+#[derive(Default)]
+pub struct InMemoryOutages {
+    outages: RwLock<Vec<Outage>>,
+}
+
+impl InMemoryOutages {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Repository for InMemoryOutages {
+    async fn append(&self, outage: NewOutage) -> Result<()> {
+        let mut outages = self.outages.write().unwrap();
+        let id = (outages.len() + 1).to_string();
+
+        outages.push(Outage {
+            id,
+            date: outage.date,
+            time: outage.time,
+            region: outage.region,
+            settlement: outage.settlement,
+            street: outage.street,
+        });
+
+        Ok(())
+    }
+
+    async fn find_by_id(&self, id: &str) -> Result<Option<Outage>> {
+        let outages = self.outages.read().unwrap();
+
+        Ok(outages.iter().find(|it| it.id == id).cloned())
+    }
+
+    async fn find_on(&self, date: NaiveDate) -> Result<Vec<Outage>> {
+        let outages = self.outages.read().unwrap();
+
+        Ok(outages
+            .iter()
+            .filter(|it| it.date == date)
+            .cloned()
+            .collect())
+    }
+
+    async fn find_since(&self, date: NaiveDate) -> Result<Vec<Outage>> {
+        let outages = self.outages.read().unwrap();
+
+        Ok(outages
+            .iter()
+            .filter(|it| it.date >= date)
+            .cloned()
+            .collect())
+    }
+}
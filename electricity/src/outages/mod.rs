@@ -0,0 +1,74 @@
+//! The parsed outages as read by the bot and the API, every storage backend
+//! provides a [`Repository`] over the rows the parser writes.
+use crate::db::{init_client, Backend};
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use sqlx::postgres::{PgPool, PgPoolOptions};
+
+mod dynamo;
+mod memory;
+mod models;
+mod pg;
+mod repository;
+
+pub use dynamo::tables as dynamo_tables;
+pub use memory::InMemoryOutages;
+pub use models::*;
+pub use repository::*;
+
+/// Outages of the backend selected at runtime.
+pub enum AnyOutages {
+    Postgres(PgPool),
+    DynamoDb(aws_sdk_dynamodb::Client),
+}
+
+impl AnyOutages {
+    /// Connects to the backend from `STORAGE_BACKEND` (`postgres` by default)
+    /// and `POSTGRESQL_URL` environment variables, the same ones the bot's
+    /// storage is configured with.
+    pub async fn connect_from_env() -> Result<Self> {
+        match Backend::from_env()? {
+            Backend::Postgres => {
+                let url = dotenvy::var("POSTGRESQL_URL")
+                    .map_err(|_| anyhow!("POSTGRESQL_URL is required for the postgres storage"))?;
+                let pool = PgPoolOptions::new()
+                    .max_connections(5)
+                    .connect(&url)
+                    .await?;
+
+                Ok(AnyOutages::Postgres(pool))
+            }
+            Backend::DynamoDb => Ok(AnyOutages::DynamoDb(init_client().await?)),
+        }
+    }
+}
+
+impl Repository for AnyOutages {
+    async fn append(&self, outage: NewOutage) -> Result<()> {
+        match self {
+            AnyOutages::Postgres(pool) => pool.append(outage).await,
+            AnyOutages::DynamoDb(client) => client.append(outage).await,
+        }
+    }
+
+    async fn find_by_id(&self, id: &str) -> Result<Option<Outage>> {
+        match self {
+            AnyOutages::Postgres(pool) => pool.find_by_id(id).await,
+            AnyOutages::DynamoDb(client) => client.find_by_id(id).await,
+        }
+    }
+
+    async fn find_on(&self, date: NaiveDate) -> Result<Vec<Outage>> {
+        match self {
+            AnyOutages::Postgres(pool) => pool.find_on(date).await,
+            AnyOutages::DynamoDb(client) => client.find_on(date).await,
+        }
+    }
+
+    async fn find_since(&self, date: NaiveDate) -> Result<Vec<Outage>> {
+        match self {
+            AnyOutages::Postgres(pool) => pool.find_since(date).await,
+            AnyOutages::DynamoDb(client) => client.find_since(date).await,
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use crate::translit::Translit;
+use chrono::NaiveDate;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// A street without electricity during a time window, as published in the
+/// maintenance schedule.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewOutage {
+    pub date: NaiveDate,
+    /// The time window as published, e.g. `08:00 - 16:00`.
+    pub time: String,
+    pub region: String,
+    pub settlement: Option<String>,
+    pub street: String,
+}
+
+#[derive(sqlx::FromRow, Debug, Clone, PartialEq)]
+pub struct Outage {
+    pub id: String,
+    pub date: NaiveDate,
+    /// The time window as published, e.g. `08:00 - 16:00`.
+    pub time: String,
+    pub region: String,
+    pub settlement: Option<String>,
+    pub street: String,
+}
+
+impl Outage {
+    pub fn affects(&self, address: &str) -> bool {
+        street_affects(&self.street, address)
+    }
+}
+
+/// Whether an outage on the street affects a subscribed address. Addresses
+/// are transliterated and start with the street, which can be followed by
+/// a building number, so the street has to match up to a word boundary.
+pub fn street_affects(street: &str, address: &str) -> bool {
+    house_number(street, address).is_some()
+}
+
+/// The house number of an address on the street, empty when the address is
+/// the whole street and `None` when it is on another street.
+pub fn house_number(street: &str, address: &str) -> Option<String> {
+    let address = address.translit();
+    let street = street.translit();
+    let street = street.trim();
+    if street.is_empty() {
+        return None;
+    }
+
+    let rest = address.trim().strip_prefix(street)?;
+    if rest.starts_with(char::is_alphanumeric) {
+        return None;
+    }
+
+    Some(
+        rest.trim_start_matches(|c: char| c == ',' || c.is_whitespace())
+            .trim_end()
+            .to_owned(),
+    )
+}
+
+/// Whether a building as published, a number, a range of them or `BB`,
+/// includes the house number.
+pub fn building_includes(building: &str, house: &str) -> bool {
+    let compact = |it: &str| it.split_whitespace().collect::<String>().translit();
+    let (building, house) = (compact(building), compact(house));
+    // Only the number counts for ranges, `5a` is within `1-9`.
+    let number = |it: &str| {
+        let digits = it
+            .chars()
+            .take_while(char::is_ascii_digit)
+            .collect::<String>();
+        digits.parse::<usize>().ok()
+    };
+
+    building == house
+        || building.split_once('-').is_some_and(
+            |(from, to)| match (number(from), number(to), number(&house)) {
+                (Some(from), Some(to), Some(house)) => from <= house && house <= to,
+                _ => false,
+            },
+        )
+}
+
+/// Addresses affected by outages, by date and published time window.
+pub type Schedule = BTreeMap<NaiveDate, BTreeMap<String, BTreeSet<String>>>;
+
+/// Groups the outages affecting any of the addresses, the parser can store
+/// the same row more than once, so duplicates are merged.
+pub fn schedule(outages: &[Outage], addresses: &[String]) -> Schedule {
+    let mut schedule = Schedule::new();
+
+    for outage in outages {
+        for address in addresses.iter().filter(|it| outage.affects(it)) {
+            schedule
+                .entry(outage.date)
+                .or_default()
+                .entry(outage.time.trim().to_owned())
+                .or_default()
+                .insert(address.to_owned());
+        }
+    }
+
+    schedule
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outage(street: &str) -> Outage {
+        Outage {
+            id: "1".to_string(),
+            date: NaiveDate::from_ymd_opt(2023, 11, 20).unwrap(),
+            time: "08:00 - 16:00".to_string(),
+            region: "Palilula".to_string(),
+            settlement: None,
+            street: street.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_affects_address_on_the_street() {
+        assert!(outage("kneza miloša").affects("kneza miloša 5"));
+        assert!(outage("Кнеза Милоша").affects("kneza miloša, 5"));
+        assert!(outage("kneza miloša").affects("kneza miloša"));
+    }
+
+    #[test]
+    fn test_house_number() {
+        assert_eq!(
+            house_number("Таковска", "takovska 5a").as_deref(),
+            Some("5a")
+        );
+        assert_eq!(
+            house_number("kneza miloša", "kneza miloša, 5").as_deref(),
+            Some("5")
+        );
+        assert_eq!(house_number("takovska", "Takovska").as_deref(), Some(""));
+        assert_eq!(house_number("takovska", "takovskaja 5"), None);
+    }
+
+    #[test]
+    fn test_building_includes() {
+        assert!(building_includes("5", "5"));
+        assert!(building_includes("5A", "5a"));
+        assert!(building_includes("BB", "bb"));
+        assert!(building_includes("9-11", "10"));
+        assert!(building_includes("1-9", "5a"));
+        assert!(!building_includes("5", "55"));
+        assert!(!building_includes("9-11", "12"));
+        assert!(!building_includes("BB", "5"));
+    }
+
+    #[test]
+    fn test_does_not_affect_other_streets() {
+        assert!(!outage("kneza miloša").affects("kneza miloševa 5"));
+        assert!(!outage("bulevar oslobođenja").affects("kneza miloša 5"));
+        assert!(!outage("").affects("kneza miloša 5"));
+    }
+}
@@ -0,0 +1,63 @@
+use super::models::{NewOutage, Outage};
+use super::Repository;
+use anyhow::Result;
+use chrono::NaiveDate;
+use sqlx::PgPool;
+
+/// The parser writes the same table, see [`crate::db::outages`]. Like in
+/// DynamoDB, appending the same outage twice is a no-op.
+impl Repository for PgPool {
+    async fn append(&self, outage: NewOutage) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO outages (date, time, region, settlement, street) values($1, $2, $3, $4, $5) \
+             ON CONFLICT DO NOTHING",
+        )
+        .bind(outage.date)
+        .bind(outage.time)
+        .bind(outage.region)
+        .bind(outage.settlement)
+        .bind(outage.street)
+        .execute(self)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn find_by_id(&self, id: &str) -> Result<Option<Outage>> {
+        // Ids of other backends are not numbers, such ids are just not found.
+        let Ok(id) = id.parse::<i32>() else {
+            return Ok(None);
+        };
+
+        let outage = sqlx::query_as::<_, Outage>(
+            "SELECT id::text AS id, date, time, region, settlement, street FROM outages WHERE id = $1",
+        )
+        .bind(id)
+        .fetch_optional(self)
+        .await?;
+
+        Ok(outage)
+    }
+
+    async fn find_on(&self, date: NaiveDate) -> Result<Vec<Outage>> {
+        let outages = sqlx::query_as::<_, Outage>(
+            "SELECT id::text AS id, date, time, region, settlement, street FROM outages WHERE date = $1 ORDER BY time",
+        )
+        .bind(date)
+        .fetch_all(self)
+        .await?;
+
+        Ok(outages)
+    }
+
+    async fn find_since(&self, date: NaiveDate) -> Result<Vec<Outage>> {
+        let outages = sqlx::query_as::<_, Outage>(
+            "SELECT id::text AS id, date, time, region, settlement, street FROM outages WHERE date >= $1 ORDER BY date, time",
+        )
+        .bind(date)
+        .fetch_all(self)
+        .await?;
+
+        Ok(outages)
+    }
+}
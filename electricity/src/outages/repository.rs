@@ -0,0 +1,15 @@
+use super::models::{NewOutage, Outage};
+use anyhow::Result;
+use chrono::NaiveDate;
+use std::future::Future;
+
+pub trait Repository {
+    /// Stores an outage parsed from a published schedule.
+    fn append(&self, outage: NewOutage) -> impl Future<Output = Result<()>> + Send;
+    fn find_by_id(&self, id: &str) -> impl Future<Output = Result<Option<Outage>>> + Send;
+    /// Finds the outages planned on the given day, a keyed lookup in every
+    /// backend, so it is cheap enough for public requests.
+    fn find_on(&self, date: NaiveDate) -> impl Future<Output = Result<Vec<Outage>>> + Send;
+    /// Finds the outages planned on the given day or later.
+    fn find_since(&self, date: NaiveDate) -> impl Future<Output = Result<Vec<Outage>>> + Send;
+}
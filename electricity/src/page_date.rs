@@ -0,0 +1,233 @@
+//! Module to find the date of the schedule in the header of a page, e.g.
+//! "БЕОГРАД - Планирана искључења за датум: 2023-11-20". Dates are written
+//! as ISO, as `dd.mm.yyyy.` or with the name of the month, in either script.
+use crate::error::ElectricityError;
+use crate::translit::normalize;
+use chrono::NaiveDate;
+use nom::branch::alt;
+use nom::bytes::complete::take_while_m_n;
+use nom::character::complete::{alpha1, char, one_of, space0, space1};
+use nom::combinator::{consumed, map, map_opt, map_res, opt};
+use nom::sequence::tuple;
+use nom::IResult;
+
+const MONTHS: [&str; 12] = [
+    "januar",
+    "februar",
+    "mart",
+    "april",
+    "maj",
+    "jun",
+    "jul",
+    "avgust",
+    "septembar",
+    "oktobar",
+    "novembar",
+    "decembar",
+];
+
+/// Finds the date in the header. A date after "за датум" wins over other
+/// dates of the header, such as the time of the last update.
+pub fn parse_page_date(header: &str) -> Result<NaiveDate, ElectricityError> {
+    let header = normalize(header);
+    let mut invalid = None;
+
+    let scheduled = header.find("za datum").map(|index| &header[index..]);
+    for text in scheduled.into_iter().chain([header.as_str()]) {
+        match find_date(text) {
+            Some(Ok(date)) => return Ok(date),
+            Some(Err(candidate)) => {
+                invalid.get_or_insert(candidate);
+            }
+            None => {}
+        }
+    }
+
+    Err(ElectricityError::BadDate(invalid.unwrap_or(header)))
+}
+
+/// The first date of the text, or the first thing which looks like a date
+/// but is not a valid one.
+fn find_date(text: &str) -> Option<Result<NaiveDate, String>> {
+    let mut invalid = None;
+
+    for (index, _) in text.char_indices() {
+        if text[..index].ends_with(|c: char| c.is_ascii_digit()) {
+            continue;
+        }
+
+        let Ok((rest, (matched, (year, month, day)))) = date(&text[index..]) else {
+            continue;
+        };
+        if rest.starts_with(|c: char| c.is_ascii_digit()) {
+            continue;
+        }
+
+        match NaiveDate::from_ymd_opt(year, month, day) {
+            Some(date) => return Some(Ok(date)),
+            None => {
+                invalid.get_or_insert(matched.trim_end_matches('.').to_owned());
+            }
+        }
+    }
+
+    invalid.map(Err)
+}
+
+fn number(min: usize, max: usize) -> impl Fn(&str) -> IResult<&str, u32> {
+    move |input| {
+        map_res(
+            take_while_m_n(min, max, |c: char| c.is_ascii_digit()),
+            str::parse,
+        )(input)
+    }
+}
+
+/// Month by its name, in the nominative ("новембар") or the genitive
+/// ("новембра") case.
+fn month_name(input: &str) -> IResult<&str, u32> {
+    map_opt(alpha1, |name: &str| {
+        MONTHS
+            .iter()
+            .position(|month| {
+                let genitive = match month.strip_suffix("bar") {
+                    Some(stem) => format!("{stem}bra"),
+                    None => format!("{month}a"),
+                };
+
+                name == *month || name == genitive
+            })
+            .map(|index| index as u32 + 1)
+    })(input)
+}
+
+/// 2023-11-20
+fn iso_date(input: &str) -> IResult<&str, (i32, u32, u32)> {
+    map(
+        tuple((
+            number(4, 4),
+            char('-'),
+            number(1, 2),
+            char('-'),
+            number(1, 2),
+        )),
+        |(year, _, month, _, day)| (year as i32, month, day),
+    )(input)
+}
+
+/// 20.11.2023., 20. 11. 2023 or 20-11-2023
+fn numeric_date(input: &str) -> IResult<&str, (i32, u32, u32)> {
+    map(
+        tuple((
+            number(1, 2),
+            one_of(".-/"),
+            space0,
+            number(1, 2),
+            one_of(".-/"),
+            space0,
+            number(4, 4),
+            opt(char('.')),
+        )),
+        |(day, _, _, month, _, _, year, _)| (year as i32, month, day),
+    )(input)
+}
+
+/// 20. новембар 2023. or 20 novembra 2023
+fn named_date(input: &str) -> IResult<&str, (i32, u32, u32)> {
+    map(
+        tuple((
+            number(1, 2),
+            opt(char('.')),
+            space1,
+            month_name,
+            space1,
+            number(4, 4),
+            opt(char('.')),
+        )),
+        |(day, _, _, month, _, year, _)| (year as i32, month, day),
+    )(input)
+}
+
+fn date(input: &str) -> IResult<&str, (&str, (i32, u32, u32))> {
+    consumed(alt((iso_date, numeric_date, named_date)))(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ymd(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn test_header_samples() {
+        let samples = [
+            (
+                "БЕОГРАД - Планирана искључења за датум: 2023-11-20",
+                ymd(2023, 11, 20),
+            ),
+            (
+                "НОВИ САД - Планирана искључења за датум: 2023-11-21",
+                ymd(2023, 11, 21),
+            ),
+            ("Београд - Палилула - 20.11.2023", ymd(2023, 11, 20)),
+            ("Планирана искључења за датум: 5.1.2024.", ymd(2024, 1, 5)),
+            (
+                "NOVI SAD - Planirana isključenja za datum: 05. 01. 2024.",
+                ymd(2024, 1, 5),
+            ),
+            (
+                "Планирана искључења за датум:\u{a0}20-11-2023",
+                ymd(2023, 11, 20),
+            ),
+            (
+                "Планирана искључења за 20. новембар 2023.",
+                ymd(2023, 11, 20),
+            ),
+            ("понедељак, 1. јула 2024. године", ymd(2024, 7, 1)),
+            ("Planirana isključenja, 3 Septembra 2024", ymd(2024, 9, 3)),
+            (
+                "Ажурирано 19.11.2023. у 14:30 - за датум: 20.11.2023.",
+                ymd(2023, 11, 20),
+            ),
+        ];
+
+        for (header, expected) in samples {
+            assert_eq!(parse_page_date(header).unwrap(), expected, "{header}");
+        }
+    }
+
+    #[test]
+    fn test_invalid_date() {
+        let error = parse_page_date("БЕОГРАД - Планирана искључења за датум: 2023-13-45").unwrap_err();
+        assert!(matches!(&error, ElectricityError::BadDate(date) if date == "2023-13-45"));
+
+        let error = parse_page_date("Планирана искључења за датум: 31.02.2023.").unwrap_err();
+        assert!(matches!(&error, ElectricityError::BadDate(date) if date == "31.02.2023"));
+    }
+
+    #[test]
+    fn test_valid_date_after_invalid_one() {
+        assert_eq!(
+            parse_page_date("Измена 45.11.2023, важи 20.11.2023").unwrap(),
+            ymd(2023, 11, 20)
+        );
+    }
+
+    #[test]
+    fn test_missing_date() {
+        for header in [
+            "",
+            "Планирана искључења",
+            "Ажурирано у 14:30",
+            "20. Новембарска 2023",
+            "120.11.2023",
+        ] {
+            assert!(
+                matches!(parse_page_date(header), Err(ElectricityError::BadDate(_))),
+                "{header}"
+            );
+        }
+    }
+}
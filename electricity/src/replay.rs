@@ -0,0 +1,382 @@
+//! Parsing of saved pages without the collector and the databases, to debug
+//! the parser on the page of a problematic day.
+use crate::db::DATE_FORMAT;
+use crate::export::Row;
+use crate::fetcher::decode;
+use crate::validation::{daily_reports, street_index, validate, CheckedAddress};
+use crate::{parse_page_with_failures, ElectricityError, ElectricityFailuresData, RowFailure};
+use anyhow::{Context as _, Result};
+use chrono::NaiveDate;
+use serde_json::{json, Value};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const TABLE_HEADER: [&str; 6] = [
+    "date",
+    "time",
+    "municipality",
+    "settlement",
+    "street",
+    "building",
+];
+
+/// The outcome of parsing a saved page.
+#[derive(Debug)]
+pub struct Replay {
+    pub path: PathBuf,
+    /// The outages, a row per address and building.
+    pub rows: Vec<Row>,
+    /// Rows of the table which are skipped by the parser.
+    pub failures: Vec<RowFailure>,
+    /// The parsed addresses checked against `beograd_streets`.
+    pub streets: Vec<CheckedAddress>,
+    /// Why the page could not be parsed at all.
+    pub error: Option<ElectricityError>,
+}
+
+/// The files to replay: the given files, and the `.htm` and `.html` files of
+/// the given directories, in the order of their names.
+pub fn html_files(paths: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut files = vec![];
+
+    for path in paths {
+        if !path.is_dir() {
+            files.push(path.to_owned());
+            continue;
+        }
+
+        let mut entries = vec![];
+        for entry in fs::read_dir(path).with_context(|| format!("failed to read {}", path.display()))? {
+            let entry = entry?.path();
+            let is_html = entry
+                .extension()
+                .is_some_and(|it| it.eq_ignore_ascii_case("htm") || it.eq_ignore_ascii_case("html"));
+
+            if is_html && entry.is_file() {
+                entries.push(entry);
+            }
+        }
+        entries.sort();
+        files.extend(entries);
+    }
+
+    Ok(files)
+}
+
+/// Parses a saved page the same way the parser does. The page is decoded by
+/// its `<meta>` charset, UTF-8 by default.
+pub fn replay_file(path: &Path) -> Result<Replay> {
+    let content = fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+
+    Ok(replay(path, &decode(None, &content)))
+}
+
+fn replay(path: &Path, html: &str) -> Replay {
+    let parsed = parse_page_with_failures(html).and_then(|page| {
+        let rows = page
+            .outages
+            .iter()
+            .map(outage_rows)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok((
+            rows.concat(),
+            page.failures,
+            validate(street_index(), &page.outages),
+        ))
+    });
+
+    match parsed {
+        Ok((rows, failures, streets)) => Replay {
+            path: path.to_owned(),
+            rows,
+            failures,
+            streets,
+            error: None,
+        },
+        Err(error) => Replay {
+            path: path.to_owned(),
+            rows: vec![],
+            failures: vec![],
+            streets: vec![],
+            error: Some(error),
+        },
+    }
+}
+
+/// Rows of a parsed outage, one per address and building.
+fn outage_rows(outage: &ElectricityFailuresData) -> Result<Vec<Row>, ElectricityError> {
+    let date = NaiveDate::parse_from_str(&outage.date, DATE_FORMAT)
+        .map_err(|_| ElectricityError::BadDate(outage.date.to_owned()))?;
+    let mut rows = vec![];
+
+    for address in outage.addresses.iter() {
+        let row = Row {
+            date,
+            time: outage.time.trim().to_owned(),
+            region: outage.region.trim().to_owned(),
+            settlement: address.settlement.clone(),
+            street: address.street.clone(),
+            building: None,
+            location: None,
+        };
+
+        if address.buildings.is_empty() {
+            rows.push(row);
+        } else {
+            rows.extend(address.buildings.iter().map(|building| Row {
+                building: Some(building.to_string()),
+                ..row.clone()
+            }));
+        }
+    }
+
+    Ok(rows)
+}
+
+/// Prints every page as a table of its outages followed by the failures.
+pub fn write_table<W: Write>(replays: &[Replay], mut writer: W) -> Result<()> {
+    for (index, replay) in replays.iter().enumerate() {
+        if index > 0 {
+            writeln!(writer)?;
+        }
+
+        let path = replay.path.display();
+        if let Some(error) = &replay.error {
+            writeln!(writer, "{path}: failed: {error:#}")?;
+            continue;
+        }
+
+        writeln!(
+            writer,
+            "{path}: {} rows, {} failures",
+            replay.rows.len(),
+            replay.failures.len()
+        )?;
+
+        let cells = replay
+            .rows
+            .iter()
+            .map(|row| {
+                [
+                    row.date.format("%Y-%m-%d").to_string(),
+                    row.time.to_owned(),
+                    row.region.to_owned(),
+                    row.settlement.to_owned().unwrap_or_default(),
+                    row.street.to_owned(),
+                    row.building.to_owned().unwrap_or_default(),
+                ]
+            })
+            .collect::<Vec<_>>();
+
+        let mut widths = TABLE_HEADER.map(|it| it.chars().count());
+        for row in &cells {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+
+        let header = TABLE_HEADER.map(ToOwned::to_owned);
+        for row in std::iter::once(&header).chain(&cells) {
+            let line = row
+                .iter()
+                .zip(widths)
+                .map(|(cell, width)| format!("{cell:width$}"))
+                .collect::<Vec<_>>()
+                .join("  ");
+            writeln!(writer, "{}", line.trim_end())?;
+        }
+
+        for failure in &replay.failures {
+            writeln!(writer, "failed {failure}")?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Prints the streets of the pages which are not found in `beograd_streets`,
+/// a report per day.
+pub fn write_street_reports<W: Write>(replays: &[Replay], mut writer: W) -> Result<()> {
+    let streets = replays
+        .iter()
+        .flat_map(|it| it.streets.iter().cloned())
+        .collect::<Vec<_>>();
+
+    for report in daily_reports(&streets) {
+        writeln!(writer, "{report}")?;
+    }
+
+    Ok(())
+}
+
+pub fn to_json(replays: &[Replay]) -> Value {
+    let pages = replays
+        .iter()
+        .map(|replay| {
+            let rows = replay
+                .rows
+                .iter()
+                .map(|row| {
+                    json!({
+                        "date": row.date.format("%Y-%m-%d").to_string(),
+                        "time": row.time,
+                        "municipality": row.region,
+                        "settlement": row.settlement,
+                        "street": row.street,
+                        "building": row.building,
+                    })
+                })
+                .collect::<Vec<_>>();
+            let failures = replay
+                .failures
+                .iter()
+                .map(|failure| {
+                    json!({
+                        "row": failure.row,
+                        "text": failure.text.trim(),
+                        "error": format!("{:#}", failure.error).trim_end(),
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            json!({
+                "file": replay.path.display().to_string(),
+                "outages": rows,
+                "failures": failures,
+                "error": replay.error.as_ref().map(|it| format!("{it:#}")),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    Value::Array(pages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: &str = r#"
+        <html>
+            <head><meta charset="utf-8"></head>
+            <body>
+                <table><tbody><tr><td><b>Београд - Палилула - 20.11.2023</b></td></tr></tbody></table>
+                <table>
+                    <tbody>
+                        <tr><td>Општина</td><td>Време</td><td>Улице</td></tr>
+                        <tr><td>Палилула</td><td>08:00 - 16:00</td><td>Таковска: 5,7</td></tr>
+                        <tr><td>Палилула</td><td>09:00 - 13:00</td><td>: 5</td></tr>
+                    </tbody>
+                </table>
+            </body>
+        </html>
+    "#;
+
+    #[test]
+    fn test_replay() {
+        let replay = replay(Path::new("page.htm"), PAGE);
+
+        assert!(replay.error.is_none());
+        assert_eq!(
+            replay
+                .rows
+                .iter()
+                .map(|it| (it.street.as_str(), it.building.as_deref()))
+                .collect::<Vec<_>>(),
+            vec![("takovska", Some("5")), ("takovska", Some("7"))]
+        );
+        assert_eq!(
+            replay.rows[0].date,
+            NaiveDate::from_ymd_opt(2023, 11, 20).unwrap()
+        );
+        assert_eq!(replay.failures.len(), 1);
+        assert_eq!(replay.failures[0].row, 2);
+        assert_eq!(replay.failures[0].text, ": 5");
+    }
+
+    #[test]
+    fn test_replay_invalid_page() {
+        let replay = replay(
+            Path::new("page.htm"),
+            &PAGE.replace("20.11.2023", "20.13.2023"),
+        );
+
+        assert!(replay.error.is_some());
+        assert!(replay.rows.is_empty());
+    }
+
+    #[test]
+    fn test_write_table() {
+        let replays = [replay(Path::new("page.htm"), PAGE)];
+        let mut output = vec![];
+        write_table(&replays, &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let lines = output.lines().collect::<Vec<_>>();
+
+        assert_eq!(lines[0], "page.htm: 2 rows, 1 failures");
+        assert_eq!(
+            lines[1],
+            "date        time           municipality  settlement  street    building"
+        );
+        assert_eq!(
+            lines[2],
+            "2023-11-20  08:00 - 16:00  Палилула                  takovska  5"
+        );
+        assert!(lines[4].starts_with("failed row 2: : 5: "));
+    }
+
+    #[test]
+    fn test_write_street_reports() {
+        let replays = [replay(Path::new("page.htm"), PAGE)];
+        let mut output = vec![];
+        write_street_reports(&replays, &mut output).unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "20-11-2023: 1 matched, 0 unknown, 0 ambiguous, 0 unchecked\n"
+        );
+    }
+
+    #[test]
+    fn test_to_json() {
+        let replays = [replay(Path::new("page.htm"), PAGE)];
+        let json = to_json(&replays);
+
+        assert_eq!(json[0]["file"], "page.htm");
+        assert_eq!(json[0]["outages"].as_array().unwrap().len(), 2);
+        assert_eq!(json[0]["outages"][1]["building"], "7");
+        assert_eq!(json[0]["failures"][0]["row"], 2);
+        assert_eq!(json[0]["error"], Value::Null);
+    }
+
+    #[test]
+    fn test_html_files() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["b.htm", "a.html", "notes.txt"] {
+            fs::write(dir.path().join(name), PAGE).unwrap();
+        }
+        let single = dir.path().join("notes.txt");
+
+        let files = html_files(&[dir.path().to_owned(), single.clone()]).unwrap();
+
+        assert_eq!(
+            files,
+            vec![dir.path().join("a.html"), dir.path().join("b.htm"), single]
+        );
+    }
+
+    #[test]
+    fn test_replay_file_decodes_charset() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("page.htm");
+        let page = PAGE.replace("utf-8", "windows-1251");
+        let (content, _, _) = encoding_rs::WINDOWS_1251.encode(&page);
+        fs::write(&path, content).unwrap();
+
+        let replay = replay_file(&path).unwrap();
+
+        assert_eq!(replay.rows.len(), 2);
+        assert_eq!(replay.rows[0].region, "Палилула");
+    }
+}
@@ -1,6 +1,10 @@
 //! To simplify text processing and models all the input text from users and
 //! data obtained from web sites will be transliterated into Latin script and to
 //! lower case register.
+//!
+//! When the original form matters (e.g. to show an address back to a user) the
+//! case-preserving [`Translit::translit_keep_case`] and the reverse
+//! [`Cyrillize`] transformation can be used instead.
 use fnv::FnvHashMap;
 use std::iter::FromIterator;
 use std::sync::OnceLock;
@@ -41,8 +45,34 @@ static CHAR_MAP: OnceLock<FnvHashMap<char, CharOrString>> = OnceLock::new();
 
 static NAKED_MAP: OnceLock<FnvHashMap<char, CharOrString>> = OnceLock::new();
 
+static CYRILLIC_MAP: OnceLock<FnvHashMap<char, char>> = OnceLock::new();
+
+/// Words where `lj`, `nj` or `dž` are two separate letters rather than
+/// a digraph. Each entry is a lower case word stem, the digraphs inside the
+/// stem are never merged.
+static DIGRAPH_EXCEPTIONS: &[&str] = &[
+    "injek",
+    "konjug",
+    "konjunk",
+    "vanjez",
+    "nadživ",
+    "nadžnj",
+    "odživ",
+    "podžanr",
+    "podželud",
+    "podžup",
+];
+
 pub trait Translit {
     fn translit(&self) -> String;
+
+    /// Transliterates into Latin script keeping the letter case of the input.
+    fn translit_keep_case(&self) -> String;
+}
+
+/// Transliteration from Latin script back into Serbian Cyrillic.
+pub trait Cyrillize {
+    fn cyrillize(&self) -> String;
 }
 
 pub trait Naked {
@@ -56,6 +86,19 @@ where
     fn translit(&self) -> String {
         translit(self.as_ref())
     }
+
+    fn translit_keep_case(&self) -> String {
+        translit_keep_case(self.as_ref())
+    }
+}
+
+impl<T> Cyrillize for T
+where
+    T: AsRef<str>,
+{
+    fn cyrillize(&self) -> String {
+        cyrillize(self.as_ref())
+    }
 }
 
 impl<T> Naked for T
@@ -67,8 +110,8 @@ where
     }
 }
 
-fn translit(input: &str) -> String {
-    let map = CHAR_MAP.get_or_init(|| {
+fn char_map() -> &'static FnvHashMap<char, CharOrString> {
+    CHAR_MAP.get_or_init(|| {
         let mut map = FnvHashMap::default();
         smap![map, 'a', 'А', 'а'];
         smap![map, 'b', 'Б', 'б'];
@@ -101,9 +144,125 @@ fn translit(input: &str) -> String {
         smap![map, 'z', 'З', 'з'];
         smap![map, 'ž', 'Ж', 'ж'];
         map
-    });
+    })
+}
 
-    remap_characters(map, input)
+fn cyrillic_map() -> &'static FnvHashMap<char, char> {
+    CYRILLIC_MAP.get_or_init(|| {
+        char_map()
+            .iter()
+            .filter(|(cyr, _)| cyr.is_lowercase())
+            .filter_map(|(cyr, lat)| match lat {
+                CharOrString::Char(lat) => Some((*lat, *cyr)),
+                CharOrString::String(_) => None,
+            })
+            .collect()
+    })
+}
+
+fn translit(input: &str) -> String {
+    remap_characters(char_map(), input)
+}
+
+fn translit_keep_case(input: &str) -> String {
+    let map = char_map();
+    let mut result = String::with_capacity(input.len());
+    let mut chars = input.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match map.get(&c) {
+            Some(CharOrString::Char(rc)) if c.is_uppercase() => result.extend(rc.to_uppercase()),
+            Some(CharOrString::Char(rc)) => result.push(*rc),
+            // A digraph is fully capitalized only within an upper case word: "ЉУБА" -> "LJUBA", "Љуба" -> "Ljuba".
+            Some(CharOrString::String(rs)) if c.is_uppercase() => {
+                if chars.peek().is_some_and(|next| next.is_uppercase()) {
+                    result.push_str(&rs.to_uppercase());
+                } else {
+                    let mut rs_chars = rs.chars();
+                    result.extend(rs_chars.next().into_iter().flat_map(char::to_uppercase));
+                    result.extend(rs_chars);
+                }
+            }
+            Some(CharOrString::String(rs)) => result.push_str(rs),
+            None => result.push(c),
+        }
+    }
+
+    result
+}
+
+/// Returns the Cyrillic letter for a Latin digraph (`lj`, `nj` or `dž`).
+fn digraph(first: char, second: char) -> Option<char> {
+    match (first.to_lowercase().next()?, second.to_lowercase().next()?) {
+        ('l', 'j') => Some('љ'),
+        ('n', 'j') => Some('њ'),
+        ('d', 'ž') => Some('џ'),
+        _ => None,
+    }
+}
+
+/// Number of leading characters of the word where digraphs must be kept apart.
+fn digraph_exception_len(word: &[char]) -> usize {
+    let word = String::from_iter(word.iter().flat_map(|c| c.to_lowercase()));
+
+    DIGRAPH_EXCEPTIONS
+        .iter()
+        .find(|stem| word.starts_with(**stem))
+        .map(|stem| stem.chars().count())
+        .unwrap_or(0)
+}
+
+fn cyrillize(input: &str) -> String {
+    let map = cyrillic_map();
+    let chars = input.chars().collect::<Vec<_>>();
+    let mut result = String::with_capacity(input.len() * 2);
+    // Index up to which digraphs of the current word are written as two letters.
+    let mut split_until = 0;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+
+        if c.is_alphabetic() && (i == 0 || !chars[i - 1].is_alphabetic()) {
+            let word_len = chars[i..].iter().take_while(|c| c.is_alphabetic()).count();
+            split_until = i + digraph_exception_len(&chars[i..i + word_len]);
+        }
+
+        let merged = chars
+            .get(i + 1)
+            .filter(|_| i + 1 >= split_until)
+            .and_then(|next| digraph(c, *next));
+
+        let (cyr, consumed) = match merged {
+            Some(cyr) => (Some(cyr), 2),
+            None => (
+                c.to_lowercase().next().and_then(|lc| map.get(&lc).copied()),
+                1,
+            ),
+        };
+
+        match cyr {
+            Some(cyr) if c.is_uppercase() => result.extend(cyr.to_uppercase()),
+            Some(cyr) => result.push(cyr),
+            None => result.push(c),
+        }
+
+        i += consumed;
+    }
+
+    result
+}
+
+/// A text reduced for comparison: single spaces, lowercase Latin script
+/// without diacritics, so "Кнеза  Милоша" and "kneza milosa" are equal.
+pub fn normalize(input: &str) -> String {
+    input
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+        .translit()
+        .naked()
 }
 
 fn naked(input: &str) -> String {
@@ -111,6 +270,7 @@ fn naked(input: &str) -> String {
         let mut map = FnvHashMap::default();
         map.insert('ć', CharOrString::Char('c'));
         map.insert('č', CharOrString::Char('c'));
+        map.insert('š', CharOrString::Char('s'));
         map.insert('ž', CharOrString::Char('z'));
         map.insert('đ', CharOrString::String("dj".to_string()));
         map
@@ -155,6 +315,54 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_translit_keep_case() {
+        let output = "Улица Љубе Ненадовића 12, ЏОРЏА ВАШИНГТОНА".translit_keep_case();
+        assert_eq!(&output, "Ulica Ljube Nenadovića 12, DŽORDŽA VAŠINGTONA");
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("  Кнеза\tМилоша  "), "kneza milosa");
+        assert_eq!(normalize("Bulevar OSLOBOĐENJA"), "bulevar oslobodjenja");
+    }
+
+    #[test]
+    fn test_cyrillize_digraphs() {
+        assert_eq!("ljubav, njegoš, džep".cyrillize(), "љубав, његош, џеп");
+        assert_eq!("LJUBAV Njegoš DŽEP".cyrillize(), "ЉУБАВ Његош ЏЕП");
+    }
+
+    #[test]
+    fn test_cyrillize_mixed_case_digraphs() {
+        assert_eq!("ЉА".translit_keep_case(), "LJA");
+        for input in ["ЉА", "Ља", "ЊЏ", "аЏА"] {
+            assert_eq!(input.translit_keep_case().cyrillize(), input);
+        }
+    }
+
+    #[test]
+    fn test_cyrillize_digraph_exceptions() {
+        assert_eq!("injekcija".cyrillize(), "инјекција");
+        assert_eq!("Konjunkcija".cyrillize(), "Конјункција");
+        assert_eq!("nadživeti".cyrillize(), "надживети");
+        // the exception applies to the stem only
+        assert_eq!("injekcija konj".cyrillize(), "инјекција коњ");
+    }
+
+    #[test]
+    fn test_cyrillize_keeps_unknown_characters() {
+        assert_eq!("Bulevar 12/3, (x)".cyrillize(), "Булевар 12/3, (x)");
+    }
+
+    #[test]
+    fn test_naked() {
+        assert_eq!(
+            "kneza miloša, ćirila, đakona žarka".naked(),
+            "kneza milosa, cirila, djakona zarka"
+        );
+    }
+
     proptest! {
         #[test]
         fn test_translit_to_lowercase(s in "\\PC*") {
@@ -162,6 +370,22 @@ mod tests {
             prop_assert_eq!(result.to_lowercase(), result);
         }
 
+        #[test]
+        fn test_translit_keep_case_round_trip(s in "[\\p{Cyrillic}a-zA-Z0-9 .,:-]{0,30}") {
+            prop_assert_eq!(s.translit_keep_case().to_lowercase(), s.translit());
+        }
+
+        #[test]
+        fn test_cyrillize_round_trip(s in "[абвгдђежзијклљмнњопрстћуфхцчџшАБВГДЂЕЖЗИЈКЛЉМНЊОПРСТЋУФХЦЧЏШ .,]{0,30}") {
+            // Latin script can not tell "нј" from "њ" apart, such strings are not reversible.
+            let lower = s.to_lowercase();
+            prop_assume!(!["лј", "нј", "дж"].iter().any(|it| lower.contains(it)));
+            prop_assume!(lower.split(' ').all(|word| digraph_exception_len(&word.translit().chars().collect::<Vec<_>>()) == 0));
+            // Mixed case digraphs round-trip, a digraph is fully capitalized when the next letter is ("ЉА" -> "LJA").
+
+            prop_assert_eq!(s.translit_keep_case().cyrillize(), s);
+        }
+
         #[test]
         fn test_translit_cyrillic_symb(s in "\\p{Cyrillic}{0,10}") {
             let result: String = s.translit();
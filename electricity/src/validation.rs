@@ -0,0 +1,322 @@
+//! Cross-check of the parsed streets against the register of Belgrade
+//! streets. A street which is not found in the municipality of the outage is
+//! either misparsed or missing from `beograd_streets`, and the daily report
+//! of such names helps to improve both.
+use crate::translit::normalize;
+use crate::ElectricityFailuresData;
+use beograd_streets::{StaticStreet, STREETS};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Display;
+use std::sync::OnceLock;
+
+static STREET_INDEX: OnceLock<StreetIndex> = OnceLock::new();
+
+/// The index of `beograd_streets`, built on the first use.
+pub fn street_index() -> &'static StreetIndex {
+    STREET_INDEX.get_or_init(StreetIndex::default)
+}
+
+/// Whether a parsed street is in the register.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StreetStatus {
+    /// The street is found in a single settlement of the municipality.
+    Matched,
+    /// No street of the municipality has the name.
+    Unknown,
+    /// Streets with the name are in several settlements of the municipality,
+    /// and the row doesn't say which one it is.
+    Ambiguous(Vec<&'static str>),
+}
+
+impl Display for StreetStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StreetStatus::Matched => write!(f, "matched"),
+            StreetStatus::Unknown => write!(f, "unknown"),
+            StreetStatus::Ambiguous(_) => write!(f, "ambiguous"),
+        }
+    }
+}
+
+/// A parsed address with the result of the lookup.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckedAddress {
+    pub date: String,
+    pub region: String,
+    pub settlement: Option<String>,
+    pub street: String,
+    /// `None` when the municipality is not in the register, e.g. outside of
+    /// Belgrade.
+    pub status: Option<StreetStatus>,
+}
+
+/// Streets of the register by their municipality.
+pub struct StreetIndex {
+    municipalities: HashMap<String, Vec<(String, &'static StaticStreet)>>,
+}
+
+impl Default for StreetIndex {
+    fn default() -> Self {
+        Self::new(STREETS)
+    }
+}
+
+impl StreetIndex {
+    pub fn new(streets: &'static [StaticStreet]) -> Self {
+        let mut municipalities: HashMap<_, Vec<_>> = HashMap::new();
+
+        for street in streets {
+            let names = municipalities
+                .entry(normalize(street.municipality))
+                .or_default();
+
+            names.push((normalize(street.street_name), street));
+            if let Some(old_name) = street.old_street_name {
+                names.push((normalize(old_name), street));
+            }
+        }
+
+        Self { municipalities }
+    }
+
+    /// Looks up the street in the municipality, and in the settlement when
+    /// the row names it.
+    pub fn check(&self, municipality: &str, settlement: Option<&str>, street: &str) -> Option<StreetStatus> {
+        let streets = self.municipalities.get(&normalize(municipality))?;
+        let street = normalize(street);
+        let settlement = settlement.map(normalize);
+
+        let settlements = streets
+            .iter()
+            .filter(|(name, _)| *name == street)
+            .map(|(_, street)| *street)
+            .filter(|it| {
+                settlement.as_ref().is_none_or(|settlement| {
+                    *settlement == normalize(it.settlement) || *settlement == normalize(it.settlement_part)
+                })
+            })
+            .map(|it| it.settlement)
+            .collect::<BTreeSet<_>>();
+
+        Some(match settlements.len() {
+            0 => StreetStatus::Unknown,
+            1 => StreetStatus::Matched,
+            _ => StreetStatus::Ambiguous(settlements.into_iter().collect()),
+        })
+    }
+}
+
+/// Checks every address of the outages.
+pub fn validate(index: &StreetIndex, outages: &[ElectricityFailuresData]) -> Vec<CheckedAddress> {
+    outages
+        .iter()
+        .flat_map(|outage| {
+            outage.addresses.iter().map(|address| CheckedAddress {
+                date: outage.date.to_owned(),
+                region: outage.region.trim().to_owned(),
+                settlement: address.settlement.clone(),
+                street: address.street.clone(),
+                status: index.check(
+                    &outage.region,
+                    address.settlement.as_deref(),
+                    &address.street,
+                ),
+            })
+        })
+        .collect()
+}
+
+/// Streets of a day which are not matched, with the number of their
+/// addresses.
+#[derive(Debug, Default, PartialEq)]
+pub struct StreetReport {
+    pub date: String,
+    pub matched: usize,
+    /// Addresses in municipalities which are not in the register.
+    pub unchecked: usize,
+    /// By the municipality, the settlement and the street.
+    pub unknown: BTreeMap<(String, Option<String>, String), usize>,
+    /// By the municipality and the street, with the candidate settlements.
+    pub ambiguous: BTreeMap<(String, String), Vec<&'static str>>,
+}
+
+/// Reports of the checked addresses by their dates, in the order of the
+/// dates on the pages.
+pub fn daily_reports(addresses: &[CheckedAddress]) -> Vec<StreetReport> {
+    let mut reports: Vec<StreetReport> = vec![];
+
+    for address in addresses {
+        let index = match reports.iter().position(|it| it.date == address.date) {
+            Some(index) => index,
+            None => {
+                reports.push(StreetReport {
+                    date: address.date.to_owned(),
+                    ..StreetReport::default()
+                });
+                reports.len() - 1
+            }
+        };
+        let report = &mut reports[index];
+
+        match &address.status {
+            None => report.unchecked += 1,
+            Some(StreetStatus::Matched) => report.matched += 1,
+            Some(StreetStatus::Unknown) => {
+                *report
+                    .unknown
+                    .entry((
+                        address.region.to_owned(),
+                        address.settlement.to_owned(),
+                        address.street.to_owned(),
+                    ))
+                    .or_default() += 1;
+            }
+            Some(StreetStatus::Ambiguous(settlements)) => {
+                report.ambiguous.insert(
+                    (address.region.to_owned(), address.street.to_owned()),
+                    settlements.to_owned(),
+                );
+            }
+        }
+    }
+
+    reports
+}
+
+impl StreetReport {
+    pub fn is_clean(&self) -> bool {
+        self.unknown.is_empty() && self.ambiguous.is_empty()
+    }
+}
+
+impl Display for StreetReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {} matched, {} unknown, {} ambiguous, {} unchecked",
+            self.date,
+            self.matched,
+            self.unknown.values().sum::<usize>(),
+            self.ambiguous.len(),
+            self.unchecked
+        )?;
+
+        for ((region, settlement, street), count) in &self.unknown {
+            match settlement {
+                Some(settlement) => write!(f, "\nunknown {region}: {settlement}: {street} ({count})")?,
+                None => write!(f, "\nunknown {region}: {street} ({count})")?,
+            }
+        }
+        for ((region, street), settlements) in &self.ambiguous {
+            write!(
+                f,
+                "\nambiguous {region}: {street} ({})",
+                settlements.join(", ")
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_page;
+
+    const PAGE: &str = r#"
+        <table><tr><td><b>БЕОГРАД - Планирана искључења за датум: 2023-11-20</b></td></tr></table>
+        <table>
+            <tr><td>Општина</td><td>Време</td><td>Улице</td></tr>
+            <tr><td>Палилула </td><td>08:00 - 16:00</td><td>ТАКОВСКА: 5,7, ИЗМИШЉЕНА: 1,</td></tr>
+            <tr><td>Сурчин</td><td>09:00 - 13:00</td><td>Насеље Сурчин: ЦАРА ЛАЗАРА: 1, Насеље Бечмен: ЦАРА ЛАЗАРА: 2,</td></tr>
+            <tr><td>Гроцка</td><td>09:00 - 13:00</td><td>ТАКОВСКА: 3,</td></tr>
+            <tr><td>Нови Сад</td><td>09:00 - 13:00</td><td>ЗМАЈ ЈОВИНА: 3,</td></tr>
+        </table>
+    "#;
+
+    fn checked() -> Vec<CheckedAddress> {
+        validate(street_index(), &parse_page(PAGE).unwrap())
+    }
+
+    #[test]
+    fn test_check() {
+        let index = StreetIndex::default();
+
+        assert_eq!(
+            index.check("Палилула", None, "takovska"),
+            Some(StreetStatus::Matched)
+        );
+        assert_eq!(
+            index.check("Stari grad", None, "KNEZA MILOSA"),
+            Some(StreetStatus::Matched)
+        );
+        assert_eq!(
+            index.check("Палилула", None, "izmišljena"),
+            Some(StreetStatus::Unknown)
+        );
+        assert_eq!(index.check("Нови Сад", None, "takovska"), None);
+    }
+
+    #[test]
+    fn test_check_by_settlement() {
+        let index = StreetIndex::default();
+
+        assert_eq!(
+            index.check("Сурчин", Some("surčin"), "cara lazara"),
+            Some(StreetStatus::Matched)
+        );
+        assert_eq!(
+            index.check("Сурчин", Some("bečmen"), "cara lazara"),
+            Some(StreetStatus::Unknown)
+        );
+        assert!(matches!(
+            index.check("Гроцка", None, "takovska"),
+            Some(StreetStatus::Ambiguous(settlements)) if settlements.contains(&"vinča")
+        ));
+        assert_eq!(
+            index.check("Гроцка", Some("vinča"), "takovska"),
+            Some(StreetStatus::Matched)
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        let statuses = checked()
+            .into_iter()
+            .map(|it| (it.street, it.status.map(|it| it.to_string())))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            statuses,
+            vec![
+                ("takovska".to_string(), Some("matched".to_string())),
+                ("izmišljena".to_string(), Some("unknown".to_string())),
+                ("cara lazara".to_string(), Some("matched".to_string())),
+                ("cara lazara".to_string(), Some("unknown".to_string())),
+                ("takovska".to_string(), Some("ambiguous".to_string())),
+                ("zmaj jovina".to_string(), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_daily_report() {
+        let reports = daily_reports(&checked());
+
+        assert_eq!(reports.len(), 1);
+        let report = &reports[0];
+        assert_eq!((report.matched, report.unchecked), (2, 1));
+        assert!(!report.is_clean());
+
+        let text = report.to_string();
+        let lines = text.lines().collect::<Vec<_>>();
+        assert_eq!(
+            lines[0],
+            "20-11-2023: 2 matched, 2 unknown, 1 ambiguous, 1 unchecked"
+        );
+        assert_eq!(lines[1], "unknown Палилула: izmišljena (1)");
+        assert_eq!(lines[2], "unknown Сурчин: bečmen: cara lazara (1)");
+        assert!(lines[3].starts_with("ambiguous Гроцка: takovska (kaluđerica, leštane, "));
+    }
+}
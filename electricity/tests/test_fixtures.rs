@@ -0,0 +1,48 @@
+//! Snapshots of the parsed pages of `tests/fixtures`, so a change of the
+//! parser shows which real-world rows are parsed differently, and of their
+//! streets which are not found in `beograd_streets`.
+//!
+//! Review the changes with `cargo insta review`, or accept them all with
+//! `INSTA_UPDATE=always cargo test -p electricity --test test_fixtures`.
+use electricity::replay::{html_files, replay_file, write_street_reports, write_table};
+use std::path::{Path, PathBuf};
+
+fn fixtures() -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+
+    html_files(&[dir]).expect("list the fixtures")
+}
+
+#[test]
+fn test_fixtures() {
+    let fixtures = fixtures();
+    assert!(!fixtures.is_empty(), "no fixtures found");
+
+    for path in fixtures {
+        let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+        let mut replay = replay_file(&path).expect("read the fixture");
+        // Snapshots shouldn't depend on where the repository is checked out.
+        replay.path = PathBuf::from(path.file_name().unwrap());
+
+        let mut output = vec![];
+        write_table(&[replay], &mut output).unwrap();
+
+        insta::assert_snapshot!(name, String::from_utf8(output).unwrap());
+    }
+}
+
+#[test]
+fn test_fixture_streets() {
+    for path in fixtures() {
+        let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+        let replay = replay_file(&path).expect("read the fixture");
+
+        let mut output = vec![];
+        write_street_reports(&[replay], &mut output).unwrap();
+
+        insta::assert_snapshot!(
+            format!("{name}_streets"),
+            String::from_utf8(output).unwrap()
+        );
+    }
+}